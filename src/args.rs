@@ -1,15 +1,171 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use fetchy::{
+    install::CompletionsMode,
+    repos::arch::{CpuArch, System},
+};
+use jiff::{
+    civil::{Date, DateTime, Time},
+    tz::TimeZone,
+    Timestamp,
+};
 use log::LevelFilter;
 
-use crate::sources::pattern::Pattern;
-
 #[derive(Parser)]
 #[clap(version, about, author)]
 pub struct Args {
-    #[clap(short, long, help = "Level of verbosity", default_value = "info")]
-    pub verbosity: LevelFilter,
+    #[clap(
+        short,
+        long,
+        global = true,
+        help = "Level of verbosity [default: info]"
+    )]
+    pub verbosity: Option<LevelFilter>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Proxy to use for all requests (overrides the HTTP(S)_PROXY environment variables)"
+    )]
+    pub proxy: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Connect and read timeout for network requests, in seconds [default: 30]"
+    )]
+    pub timeout: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Number of retries for failed network requests [default: 3]"
+    )]
+    pub retries: Option<u32>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Maximum number of simultaneous network operations [default: unlimited]"
+    )]
+    pub concurrency: Option<usize>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Maximum aggregate download speed, in bytes per second [default: unlimited]"
+    )]
+    pub max_rate: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        env = "FETCHY_BIN_DIR",
+        help = "Directory to install binaries into"
+    )]
+    pub bin_dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Use this directory for all of Fetchy's data, including the database and binaries (ignores the binaries directory's configuration)"
+    )]
+    pub root: Option<PathBuf>,
+
+    #[clap(short, long, global = true, help = "Don't ask for confirmation")]
+    pub yes: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Append all log messages to this file (with timestamps and no colors)"
+    )]
+    pub log_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Disable colors (also disabled by the NO_COLOR environment variable)"
+    )]
+    pub no_color: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Disable progress bars (also disabled when not running in a terminal)"
+    )]
+    pub no_progress: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Don't use cached responses from the GitHub API"
+    )]
+    pub refresh: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Don't access the network (cached GitHub responses are still used)"
+    )]
+    pub offline: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Maximum size of a single file extracted from an archive, in megabytes [default: 512]"
+    )]
+    pub max_extract_size: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Maximum total size of the files extracted from an archive, in megabytes [default: 2048]"
+    )]
+    pub max_total_extract_size: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Always use the declared format of assets instead of detecting it from their content"
+    )]
+    pub no_format_detection: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Where to install shell completions: 'auto' for the shells' own directories, 'off' to keep them in Fetchy's data directory [default: off]"
+    )]
+    pub completions: Option<CompletionsMode>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Install binaries for another operating system than the current one"
+    )]
+    pub os: Option<System>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Install binaries for another CPU architecture than the current one"
+    )]
+    pub arch: Option<CpuArch>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Keep installing the other packages when one of them fails"
+    )]
+    pub keep_going: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "GitHub access token to use for API calls (overrides all other sources)"
+    )]
+    pub github_token: Option<String>,
 
     #[clap(subcommand)]
     pub action: Action,
@@ -19,14 +175,62 @@ pub struct Args {
 pub enum Action {
     #[clap(about = "Install package(s)")]
     Install {
-        #[clap(help = "Name of the package(s) to install", required = true)]
+        #[clap(
+            help = "Name of the package(s) to install, optionally prefixed by a repository name (e.g. 'repo/package')",
+            required_unless_present = "from_file"
+        )]
         names: Vec<String>,
 
+        #[clap(
+            long,
+            help = "Also install the packages listed in this file (one per line, '#' starts a comment)"
+        )]
+        from_file: Option<PathBuf>,
+
         #[clap(short, long, help = "Check updates of installed packages")]
         check_updates: bool,
 
         #[clap(short, long, help = "Display less informations")]
         discreet: bool,
+
+        #[clap(
+            short,
+            long,
+            help = "Store binaries in the data directory and symlink them into the binaries directory"
+        )]
+        link: bool,
+
+        #[clap(
+            long,
+            help = "Also install the optional dependencies of the package(s)"
+        )]
+        with_optional: bool,
+
+        #[clap(
+            short,
+            long,
+            help = "Install the package(s) from this repository, regardless of priorities"
+        )]
+        repo: Option<String>,
+
+        #[clap(long, help = "Update outdated repositories before installing")]
+        auto_refresh: bool,
+
+        #[clap(long, help = "Refuse to install deprecated packages")]
+        no_deprecated: bool,
+
+        #[clap(
+            long,
+            help = "Keep the installed version(s) of the package(s) alongside the new one (see the 'use' command)"
+        )]
+        side_by_side: bool,
+
+        #[clap(
+            long,
+            requires = "side_by_side",
+            help = "Version to install alongside the other ones, as repositories otherwise only provide the latest one"
+        )]
+        pin: Option<String>,
     },
 
     #[clap(about = "Re-install some already-installed package(s)")]
@@ -39,6 +243,33 @@ pub enum Action {
     Update {
         #[clap(help = "Only update some package(s)")]
         names: Vec<String>,
+
+        #[clap(
+            long,
+            help = "Only run the verification commands of the installed packages to check their binaries still work (e.g. after an OS upgrade)"
+        )]
+        verify_only: bool,
+    },
+
+    #[clap(about = "Restore the previous version of an updated package")]
+    Rollback {
+        #[clap(help = "Name of the package to roll back")]
+        name: String,
+    },
+
+    #[clap(about = "Switch the active version of a package installed side-by-side")]
+    Use {
+        #[clap(help = "Name of the package")]
+        name: String,
+
+        #[clap(help = "Version to use")]
+        version: String,
+    },
+
+    #[clap(about = "Remove the inactive versions of packages installed side-by-side")]
+    PruneVersions {
+        #[clap(help = "Name of the package(s) to prune (all if none is provided)")]
+        names: Vec<String>,
     },
 
     #[clap(about = "Uninstall package(s)")]
@@ -54,8 +285,38 @@ pub enum Action {
         deps: bool,
     },
 
+    #[clap(about = "Uninstall packages installed as dependencies that are no longer needed")]
+    Autoremove,
+
     #[clap(about = "List installed packages")]
-    List {},
+    List {
+        #[clap(
+            short,
+            long,
+            help = "Sort packages by this key [default: repository, then name]"
+        )]
+        sort: Option<PkgSortBy>,
+
+        #[clap(short, long, help = "Reverse the sorting order")]
+        reverse: bool,
+
+        #[clap(
+            long,
+            default_value = "relative",
+            help = "How to display install dates"
+        )]
+        date: DateFormat,
+
+        #[clap(long, help = "Also show the URL each package was downloaded from")]
+        verbose: bool,
+
+        #[clap(
+            long,
+            value_parser = parse_since,
+            help = "Only show packages installed since a duration (e.g. '7d') or a date (e.g. '2024-12-31')"
+        )]
+        since: Option<Timestamp>,
+    },
 
     #[clap(about = "Repair broken packages")]
     Repair {
@@ -63,16 +324,101 @@ pub enum Action {
         names: Vec<String>,
     },
 
+    #[clap(about = "Check for problems with installed packages and repositories")]
+    Doctor,
+
+    #[clap(
+        about = "Show the detected platform and the effective configuration (e.g. for bug reports)"
+    )]
+    Env,
+
+    #[clap(about = "Check that installed binaries weren't modified since their installation")]
+    Verify {
+        #[clap(help = "Only verify specific package(s)")]
+        names: Vec<String>,
+    },
+
     #[clap(about = "Search for a package in the repositories")]
     Search {
-        #[clap(help = "Pattern to search (regular expression)")]
-        pattern: Pattern,
+        #[clap(help = "Glob pattern to search (e.g. 'rg*'), lists all packages if omitted")]
+        pattern: Option<String>,
+
+        #[clap(long, help = "Treat the pattern as a regular expression")]
+        regex: bool,
+
+        #[clap(
+            long,
+            requires = "pattern",
+            conflicts_with_all = ["regex", "field"],
+            help = "Only show packages whose name is exactly the pattern"
+        )]
+        exact: bool,
+
+        #[clap(short, long, help = "Match the pattern case-insensitively")]
+        ignore_case: bool,
 
         #[clap(short = 'r', long, help = "Search in a specific set of repositories")]
         in_repos: Vec<String>,
 
         #[clap(short, long, help = "Show installed packages as well")]
         show_installed: bool,
+
+        #[clap(
+            short,
+            long,
+            requires = "show_installed",
+            help = "Check if the installed packages are outdated (requires network access)"
+        )]
+        check: bool,
+
+        #[clap(
+            long,
+            default_value = "all",
+            help = "Field(s) to match the pattern against"
+        )]
+        field: SearchField,
+
+        #[clap(
+            short,
+            long,
+            help = "Only show packages with this tag (can be repeated)"
+        )]
+        tag: Vec<String>,
+
+        #[clap(
+            short,
+            long,
+            default_value = "50",
+            help = "Maximum number of results to show (0 for no limit)"
+        )]
+        limit: usize,
+
+        #[clap(long, default_value = "0", help = "Number of results to skip")]
+        offset: usize,
+    },
+
+    #[clap(about = "Export the list of installed packages with their exact version")]
+    Export {},
+
+    #[clap(about = "Install the exact packages versions from an exported list")]
+    Import {
+        #[clap(help = "Path to the exported packages list")]
+        path: PathBuf,
+    },
+
+    #[clap(about = "Show informations about a package")]
+    Info {
+        #[clap(help = "Name of the package")]
+        name: String,
+    },
+
+    #[clap(about = "Show the dependency tree of a package")]
+    Deps {
+        #[clap(help = "Name of the package")]
+        name: String,
+
+        #[clap(short, long, help = "Show the packages depending on this one instead")]
+        reverse: bool,
     },
 
     #[clap(about = "Add a repository")]
@@ -89,10 +435,57 @@ pub enum Action {
             help = "Don't show warning message if repository is already registered"
         )]
         ignore: bool,
+
+        #[clap(
+            long,
+            default_value = "0",
+            allow_negative_numbers = true,
+            help = "Priority of the repository when a package exists in multiple repositories (highest wins)"
+        )]
+        priority: i64,
+    },
+
+    #[clap(about = "Set the priority of a repository")]
+    SetRepoPriority {
+        #[clap(help = "Name of the repository")]
+        name: String,
+
+        #[clap(
+            allow_negative_numbers = true,
+            help = "New priority (highest wins when a package exists in multiple repositories)"
+        )]
+        priority: i64,
+    },
+
+    #[clap(about = "Convert a repository file between Fetchy's format and JSON")]
+    ConvertRepo {
+        #[clap(help = "Path to the repository's file")]
+        path: PathBuf,
+
+        #[clap(
+            long,
+            help = "Format to convert the repository to (the file must be in the other format)"
+        )]
+        to: RepoFormat,
+    },
+
+    #[clap(about = "Check a repository file for errors")]
+    ValidateRepo {
+        #[clap(help = "Path to the repository's file")]
+        path: PathBuf,
+
+        #[clap(long, help = "Parse the repository as JSON instead of Fetchy format")]
+        json_repo: bool,
+
+        #[clap(long, help = "Output the diagnostics as JSON")]
+        json: bool,
     },
 
     #[clap(about = "Update repositories")]
-    UpdateRepos {},
+    UpdateRepos {
+        #[clap(help = "Name of the repositories to update (all if none is provided)")]
+        names: Vec<String>,
+    },
 
     #[clap(about = "Remove one or more repositories")]
     RemoveRepos {
@@ -100,9 +493,128 @@ pub enum Action {
         names: Vec<String>,
     },
 
+    #[clap(about = "Remove repositories no installed package comes from")]
+    PruneRepos,
+
     #[clap(about = "List registered repositories")]
     ListRepos {},
 
+    #[clap(about = "Show informations about a repository and its packages")]
+    ShowRepo {
+        #[clap(help = "Name of the repository")]
+        name: String,
+    },
+
+    #[clap(about = "Update Fetchy itself to its latest version")]
+    SelfUpdate,
+
     #[clap(about = "Get path to the binaries directory")]
     BinPath,
+
+    #[clap(about = "Add the binaries directory to PATH")]
+    Path {
+        #[clap(subcommand)]
+        action: PathAction,
+    },
+
+    #[clap(about = "Manage the configuration file")]
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PkgSortBy {
+    Name,
+    InstallDate,
+    Size,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SearchField {
+    Name,
+    Desc,
+    Bin,
+    All,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DateFormat {
+    Relative,
+    Absolute,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RepoFormat {
+    Fetchy,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(Subcommand)]
+pub enum PathAction {
+    #[clap(about = "Print the line to add to your shell's configuration file")]
+    Export {
+        #[clap(
+            long,
+            help = "Shell to print the line for [default: detected from $SHELL]"
+        )]
+        shell: Option<Shell>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    #[clap(about = "Get path to the configuration file")]
+    Path,
+}
+
+/// Parse a point in time, either as a duration before now (e.g. `7d`) or as a local date
+fn parse_since(value: &str) -> Result<Timestamp, String> {
+    let local = |datetime: DateTime| {
+        datetime
+            .to_zoned(TimeZone::system())
+            .map(|zoned| zoned.timestamp())
+            .map_err(|err| err.to_string())
+    };
+
+    if let Ok(datetime) = value.parse::<DateTime>() {
+        return local(datetime);
+    }
+
+    if let Ok(date) = value.parse::<Date>() {
+        return local(date.to_datetime(Time::midnight()));
+    }
+
+    let invalid =
+        || format!("invalid duration or date '{value}' (expected e.g. '7d' or '2024-12-31')");
+
+    let (amount, unit) = value.split_at(
+        value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len()),
+    );
+
+    let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        "y" => 60 * 60 * 24 * 365,
+        _ => return Err(invalid()),
+    };
+
+    amount
+        .checked_mul(unit_secs)
+        .and_then(|secs| Timestamp::from_second(Timestamp::now().as_second() - secs).ok())
+        .ok_or_else(|| format!("duration '{value}' is too large"))
 }
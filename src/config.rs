@@ -0,0 +1,75 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use log::LevelFilter;
+use serde::Deserialize;
+use tokio::fs;
+
+use fetchy::install::CompletionsMode;
+
+static CONFIG_FILENAME: &str = "config.toml";
+
+/// Persistent defaults, overridable by command-line flags
+#[derive(Default, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub bin_dir: Option<PathBuf>,
+    pub concurrency: Option<usize>,
+    pub max_rate: Option<u64>,
+    // Base URL of the GitHub API, for GitHub Enterprise
+    pub github_api_url: Option<String>,
+    pub github_token: Option<String>,
+    // Tokens for specific GitHub API base URLs, taking precedence over `github_token`
+    pub github_tokens: Option<BTreeMap<String, String>>,
+    // Command printing the GitHub token to use, when none is provided otherwise
+    pub github_token_helper: Option<String>,
+    pub proxy: Option<String>,
+    pub timeout: Option<u64>,
+    pub retries: Option<u32>,
+    pub verbosity: Option<LevelFilter>,
+    pub skip_confirmations: Option<bool>,
+    pub link_binaries: Option<bool>,
+    pub offline: Option<bool>,
+    pub stale_repos_days: Option<u64>,
+    pub auto_refresh: Option<bool>,
+    pub max_extract_size: Option<u64>,
+    pub max_total_extract_size: Option<u64>,
+    pub format_detection: Option<bool>,
+    pub completions: Option<CompletionsMode>,
+}
+
+impl Config {
+    pub async fn load(data_dir: &Path) -> Result<Self> {
+        let path = Self::path(data_dir);
+
+        if !fs::try_exists(&path).await.with_context(|| {
+            format!(
+                "Failed to check if configuration file exists at path: {}",
+                path.display()
+            )
+        })? {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).await.with_context(|| {
+            format!(
+                "Failed to read configuration file at path: {}",
+                path.display()
+            )
+        })?;
+
+        toml::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse configuration file at path: {}",
+                path.display()
+            )
+        })
+    }
+
+    pub fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join(CONFIG_FILENAME)
+    }
+}
@@ -1,11 +1,14 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use jiff::Zoned;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     fetch_repos::RepositorySource,
-    repos::ast::{PackageManifest, Repository},
+    repos::{
+        arch::{CpuArch, System},
+        ast::{PackageManifest, Repository},
+    },
 };
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -14,14 +17,35 @@ pub struct AppData {
     pub installed: BTreeMap<String, InstalledPackage>,
 }
 
+impl AppData {
+    /// Clone the content of all repositories, so packages can be resolved while the database is being updated
+    pub fn cloned_repositories(&self) -> BTreeMap<String, Repository> {
+        self.repositories
+            .iter()
+            .map(|(name, repo)| (name.clone(), repo.content.clone()))
+            .collect()
+    }
+
+    pub fn repositories_priorities(&self) -> BTreeMap<String, i64> {
+        self.repositories
+            .iter()
+            .map(|(name, repo)| (name.clone(), repo.priority))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SourcedRepository {
     pub content: Repository,
     pub source: RepositorySource,
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PackageVersion(pub String);
+    // Repositories with a higher priority win when a package exists in several of them
+    #[serde(default)]
+    pub priority: i64,
+
+    // Last time the repository's content was fetched from its source
+    pub last_fetched: Zoned,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstalledPackage {
@@ -30,5 +54,58 @@ pub struct InstalledPackage {
     pub version: String,
     pub at: Zoned,
     pub binaries: Vec<String>,
+
+    // SHA-256 digest of each binary, indexed by binary name (missing for packages installed by older versions)
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
+
+    // URL the asset was downloaded from (missing for packages installed by older versions)
+    #[serde(default)]
+    pub source_url: Option<String>,
+
+    // Platform the asset was selected for (missing for packages installed by older versions)
+    #[serde(default)]
+    pub platform: Option<(System, CpuArch)>,
+
+    // Every file created when installing the package
+    pub installed_files: Vec<InstalledFile>,
+
     pub installed_as_dep: bool,
+
+    // Directory containing the actual binaries when they are symlinked into the binaries directory
+    pub linked_from: Option<PathBuf>,
+
+    // Previous version of the package, used for rollbacks
+    pub backup: Option<PackageBackup>,
+
+    // Whether the package was installed side-by-side, each version being stored in its own directory
+    #[serde(default)]
+    pub versioned: bool,
+
+    // Inactive versions kept alongside this one, indexed by version
+    #[serde(default)]
+    pub side_by_side: BTreeMap<String, InstalledPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledFile {
+    pub path: PathBuf,
+    pub kind: InstalledFileKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstalledFileKind {
+    Binary,
+
+    // Link to a binary stored in the package's directory
+    Symlink,
+
+    Completion,
+    ManPage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageBackup {
+    pub installed: Box<InstalledPackage>,
+    pub dir: PathBuf,
 }
@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use jiff::{tz::TimeZone, Timestamp, Zoned};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::data::{AppData, InstalledFile, InstalledFileKind};
+
+/// Current version of the database's schema
+///
+/// Must be incremented (and a migration added to [`MIGRATIONS`]) every time
+/// the layout of [`AppData`] changes in a backward-incompatible way
+pub static SCHEMA_VERSION: u64 = 3;
+
+/// List of migrations, where the migration at index `i` upgrades
+/// a database from version `i` to version `i + 1`
+static MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3];
+
+type Migration = fn(&mut Map<String, Value>, &MigrationContext) -> Result<()>;
+
+/// Informations required by some migrations
+pub struct MigrationContext<'a> {
+    pub bin_dir: &'a Path,
+}
+
+#[derive(Serialize)]
+pub struct VersionedAppData<T> {
+    pub schema_version: u64,
+
+    #[serde(flatten)]
+    pub data: T,
+}
+
+pub fn parse_and_migrate(data: &str, context: &MigrationContext) -> Result<AppData> {
+    let mut data = serde_json::from_str::<Map<String, Value>>(data)
+        .context("Failed to parse database file as a JSON object")?;
+
+    // Databases written before versioning was introduced don't have a version field
+    let version = match data.remove("schema_version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .context("Database schema version is not a valid integer")?,
+    };
+
+    if version > SCHEMA_VERSION {
+        bail!(
+            "Database was written by a more recent version of Fetchy (schema version {version}, while this version only supports up to {SCHEMA_VERSION}), please upgrade fetchy"
+        );
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut data, context).with_context(|| {
+            format!(
+                "Failed to migrate database from schema version {from} to {}",
+                from + 1
+            )
+        })?;
+    }
+
+    serde_json::from_value(Value::Object(data)).context("Failed to parse database file")
+}
+
+/// Installation dates used to be stored as a [`std::time::SystemTime`]
+fn migrate_v0_to_v1(data: &mut Map<String, Value>, _: &MigrationContext) -> Result<()> {
+    let Some(installed) = data.get_mut("installed").and_then(Value::as_object_mut) else {
+        return Ok(());
+    };
+
+    for (name, pkg) in installed {
+        let Some(at) = pkg.get_mut("at") else {
+            continue;
+        };
+
+        let Some(at_obj) = at.as_object() else {
+            continue;
+        };
+
+        let secs = at_obj
+            .get("secs_since_epoch")
+            .and_then(Value::as_i64)
+            .with_context(|| format!("Invalid installation date for package '{name}'"))?;
+
+        let nanos = at_obj
+            .get("nanos_since_epoch")
+            .and_then(Value::as_i64)
+            .with_context(|| format!("Invalid installation date for package '{name}'"))?;
+
+        let timestamp = Timestamp::new(secs, nanos.try_into()?)
+            .with_context(|| format!("Invalid installation date for package '{name}'"))?;
+
+        *at = serde_json::to_value(timestamp.to_zoned(TimeZone::system()))
+            .context("Failed to serialize installation date")?;
+    }
+
+    Ok(())
+}
+
+/// Installed files used to be deduced from the package's binaries
+fn migrate_v1_to_v2(data: &mut Map<String, Value>, context: &MigrationContext) -> Result<()> {
+    let Some(installed) = data.get_mut("installed").and_then(Value::as_object_mut) else {
+        return Ok(());
+    };
+
+    for (name, pkg) in installed {
+        backfill_installed_files(pkg, context.bin_dir)
+            .with_context(|| format!("Invalid record for package '{name}'"))?;
+    }
+
+    Ok(())
+}
+
+fn backfill_installed_files(pkg: &mut Value, bin_dir: &Path) -> Result<()> {
+    let pkg = pkg
+        .as_object_mut()
+        .context("Package record is not an object")?;
+
+    let binaries = pkg
+        .get("binaries")
+        .and_then(Value::as_array)
+        .context("Invalid list of binaries")?
+        .iter()
+        .map(|bin| bin.as_str().context("Invalid binary name"))
+        .collect::<Result<Vec<_>>>()?;
+
+    let linked_from = pkg
+        .get("linked_from")
+        .and_then(Value::as_str)
+        .map(Path::new);
+
+    let mut installed_files = vec![];
+
+    for bin in binaries {
+        match linked_from {
+            None => installed_files.push(InstalledFile {
+                path: bin_dir.join(bin),
+                kind: InstalledFileKind::Binary,
+            }),
+
+            Some(pkg_dir) => {
+                installed_files.push(InstalledFile {
+                    path: pkg_dir.join(bin),
+                    kind: InstalledFileKind::Binary,
+                });
+
+                installed_files.push(InstalledFile {
+                    path: bin_dir.join(bin),
+                    kind: InstalledFileKind::Symlink,
+                });
+            }
+        }
+    }
+
+    pkg.insert(
+        "installed_files".to_owned(),
+        serde_json::to_value(installed_files).context("Failed to serialize installed files")?,
+    );
+
+    // Backups contain a record of the previous version
+    if let Some(backup) = pkg
+        .get_mut("backup")
+        .and_then(Value::as_object_mut)
+        .and_then(|backup| backup.get_mut("installed"))
+    {
+        backfill_installed_files(backup, bin_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Repositories used not to record when they were last fetched
+///
+/// As this information is not available, repositories are considered as just fetched.
+fn migrate_v2_to_v3(data: &mut Map<String, Value>, _: &MigrationContext) -> Result<()> {
+    let Some(repositories) = data.get_mut("repositories").and_then(Value::as_object_mut) else {
+        return Ok(());
+    };
+
+    let now = serde_json::to_value(Zoned::now()).context("Failed to serialize current date")?;
+
+    for (name, repo) in repositories {
+        repo.as_object_mut()
+            .with_context(|| format!("Invalid record for repository '{name}'"))?
+            .insert("last_fetched".to_owned(), now.clone());
+    }
+
+    Ok(())
+}
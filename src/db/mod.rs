@@ -1,20 +1,45 @@
 use std::{
+    fs::{File, TryLockError},
     ops::Deref,
     path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use tokio::fs;
 
-use self::data::AppData;
+use self::{
+    data::AppData,
+    migrations::{parse_and_migrate, MigrationContext, VersionedAppData, SCHEMA_VERSION},
+};
 
 pub mod data;
+mod migrations;
+
+/// Get the path to the data directory, using the provided root directory if any
+pub fn get_data_dir(root: Option<&Path>) -> Result<PathBuf> {
+    if let Some(root) = root {
+        return std::path::absolute(root).with_context(|| {
+            format!(
+                "Failed to get absolute path of root directory: {}",
+                root.display()
+            )
+        });
+    }
+
+    Ok(dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .context("Failed to get path to the user's app state directory")?
+        .join("fetchy"))
+}
 
 pub struct Db {
-    // data_dir: PathBuf,
+    data_dir: PathBuf,
     bin_dir: PathBuf,
     db_path: PathBuf,
     db_data: AppData,
+
+    // Exclusive lock on the data directory, released when dropped
+    _lock: File,
 }
 
 impl Deref for Db {
@@ -52,40 +77,103 @@ impl Db {
             })?;
         }
 
-        let db_path = data_dir.join("data.db");
-
-        let db_data = if db_path.exists() {
-            let data = fs::read_to_string(&db_path)
-                .await
-                .context("Failed to read database file")?;
+        let lock = acquire_lock(&data_dir.join(".lock"))?;
 
-            serde_json::from_str(&data).context("Failed to parse database file")?
-        } else {
-            AppData::default()
-        };
+        let db_path = data_dir.join("data.db");
+        let db_data = Self::read_data_dir(&data_dir, &bin_dir).await?;
 
         Ok(Self {
-            // data_dir,
+            data_dir,
             bin_dir,
             db_path,
             db_data,
+            _lock: lock,
         })
     }
 
+    /// Read the database's content without locking the data directory, for actions which don't modify it
+    pub async fn read_data_dir(data_dir: &Path, bin_dir: &Path) -> Result<AppData> {
+        let db_path = data_dir.join("data.db");
+
+        if !db_path.exists() {
+            return Ok(AppData::default());
+        }
+
+        let data = fs::read_to_string(&db_path)
+            .await
+            .context("Failed to read database file")?;
+
+        parse_and_migrate(&data, &MigrationContext { bin_dir })
+    }
+
     pub async fn update(&mut self, with: impl FnOnce(&mut AppData)) -> Result<()> {
         with(&mut self.db_data);
 
-        let data = serde_json::to_string(&self.db_data)
-            .map_err(|err| anyhow!("Failed to serialize database: {err:?}"))?;
+        let data = serde_json::to_string(&VersionedAppData {
+            schema_version: SCHEMA_VERSION,
+            data: &self.db_data,
+        })
+        .map_err(|err| anyhow!("Failed to serialize database: {err:?}"))?;
+
+        // Write to a temporary file first so an interruption can't leave the database half-written
+        let tmp_path = self.db_path.with_extension("db.tmp");
 
-        fs::write(&self.db_path, data)
+        fs::write(&tmp_path, data)
             .await
             .context("Failed to write database content to disk")?;
 
+        fs::rename(&tmp_path, &self.db_path)
+            .await
+            .context("Failed to replace database file")?;
+
         Ok(())
     }
 
     pub fn bin_dir(&self) -> &Path {
         &self.bin_dir
     }
+
+    /// Directory where packages installed in link mode store their binaries
+    pub fn pkgs_dir(&self) -> PathBuf {
+        self.data_dir.join("packages")
+    }
+
+    /// Directory where the previous version of updated packages is kept
+    pub fn backups_dir(&self) -> PathBuf {
+        self.data_dir.join("backups")
+    }
+
+    /// Directory where shell completions are installed, with one subdirectory per shell
+    pub fn completions_dir(&self) -> PathBuf {
+        self.data_dir.join("completions")
+    }
+
+    /// Directory where man pages are installed, with one subdirectory per section
+    pub fn man_dir(&self) -> PathBuf {
+        self.data_dir.join("man")
+    }
+}
+
+fn acquire_lock(lock_path: &Path) -> Result<File> {
+    let lock = File::create(lock_path).with_context(|| {
+        format!(
+            "Failed to create lock file at path: {}",
+            lock_path.display()
+        )
+    })?;
+
+    match lock.try_lock() {
+        Ok(()) => Ok(lock),
+
+        Err(TryLockError::WouldBlock) => {
+            bail!("Another fetchy process is running, please wait for it to complete")
+        }
+
+        Err(TryLockError::Error(err)) => Err(err).with_context(|| {
+            format!(
+                "Failed to acquire lock file at path: {}",
+                lock_path.display()
+            )
+        }),
+    }
 }
@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use parsy::{Parser, ParserExpectation, ParsingError};
+use serde::Serialize;
+
+use fetchy::{
+    fetch_repos::{check_min_fetchy_version, find_min_fetchy_version, resolve_includes},
+    repos::{ast::Repository, parser::repository},
+    validator::validate_repository,
+};
+
+/// Machine-readable issue found in a repository's file
+#[derive(Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+
+    /// Package the issue relates to, if any
+    pub package: Option<String>,
+
+    /// Location of the issue in the file, if known
+    pub span: Option<DiagnosticSpan>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+}
+
+/// Location in a file (lines and columns start at 1)
+#[derive(Serialize)]
+pub struct DiagnosticSpan {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+/// Parse and validate a repository, returning all the issues found
+///
+/// The repository's path is used to resolve its included files.
+pub fn diagnose_repository(content: &str, path: &Path, json: bool) -> Vec<Diagnostic> {
+    let parsed = if json {
+        serde_json::from_str::<Repository>(content).map_err(|err| Diagnostic {
+            severity: Severity::Error,
+            message: format!("Failed to parse JSON repository: {err}"),
+            package: None,
+            span: (err.line() > 0).then(|| DiagnosticSpan {
+                line: err.line(),
+                column: err.column(),
+                len: 0,
+            }),
+        })
+    } else {
+        repository()
+            .parse_str(content)
+            .map(|parsed| parsed.data)
+            .map_err(|err| parsing_diagnostic(content, &err))
+    };
+
+    // Newer repositories may use syntax this version doesn't understand
+    let min_version = match &parsed {
+        Ok(repo) => repo.min_fetchy_version.clone(),
+        Err(_) => find_min_fetchy_version(content, json),
+    };
+
+    if let Some(Err(err)) = min_version.as_deref().map(check_min_fetchy_version) {
+        return vec![Diagnostic {
+            severity: Severity::Error,
+            message: format!("{err:#}"),
+            package: None,
+            span: None,
+        }];
+    }
+
+    let mut repo = match parsed {
+        Ok(repo) => repo,
+        Err(diagnostic) => return vec![diagnostic],
+    };
+
+    if let Err(err) = resolve_includes(&mut repo, path, json) {
+        return vec![Diagnostic {
+            severity: Severity::Error,
+            message: format!("{err:#}"),
+            package: None,
+            span: None,
+        }];
+    }
+
+    match validate_repository(&repo) {
+        Ok(()) => vec![],
+        Err(errors) => errors
+            .into_iter()
+            .map(|err| Diagnostic {
+                severity: Severity::Error,
+                message: err.message,
+                package: err.package,
+                span: None,
+            })
+            .collect(),
+    }
+}
+
+fn parsing_diagnostic(content: &str, err: &ParsingError) -> Diagnostic {
+    let message = match err.critical_message() {
+        Some(message) => message.to_owned(),
+        None => match err.inner().expected() {
+            ParserExpectation::Char(c) => format!("expected char '{c}'"),
+            ParserExpectation::Str(str) => format!("expected '{str}'"),
+            ParserExpectation::Custom(message) => (*message).to_owned(),
+            ParserExpectation::Break => "unexpected parser break".to_owned(),
+        },
+    };
+
+    let at = err.inner().at();
+
+    // Errors at the very end of the input are reported after its last character
+    let span = match at.start.compute_offset_in(content) {
+        Ok(location) => Some(DiagnosticSpan {
+            line: location.line + 1,
+            column: location.col + 1,
+            len: at.len,
+        }),
+
+        Err(_) => content
+            .lines()
+            .enumerate()
+            .last()
+            .map(|(i, line)| DiagnosticSpan {
+                line: i + 1,
+                column: line.len() + 1,
+                len: 0,
+            }),
+    };
+
+    Diagnostic {
+        severity: Severity::Error,
+        message,
+        package: None,
+        span,
+    }
+}
@@ -0,0 +1,215 @@
+//! Consistency checks between the database, the registered repositories and the filesystem
+
+use std::{collections::HashSet, path::PathBuf};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use log::{info, warn};
+
+use fetchy::{
+    db::Db,
+    fetch_repos::fetch_repository,
+    utils::{join_iter, relative_time},
+};
+
+/// Category of problems found by [`doctor`]
+struct Problems {
+    title: &'static str,
+    entries: Vec<String>,
+    // Command (or instructions) to fix the problems
+    fix: String,
+}
+
+pub async fn doctor(db: &Db, stale: &[String]) -> Result<()> {
+    let mut categories = vec![];
+
+    // Installed packages with missing files (same detection as the 'repair' command)
+    let broken = db
+        .installed
+        .values()
+        .filter_map(|installed| {
+            let missing = installed
+                .installed_files
+                .iter()
+                .filter(|file| !file.path.is_file())
+                .map(|file| file.path.display().to_string().bright_magenta())
+                .collect::<Vec<_>>();
+
+            (!missing.is_empty()).then_some((installed.manifest.name.as_str(), missing))
+        })
+        .collect::<Vec<_>>();
+
+    categories.push(Problems {
+        title: "Packages with missing files",
+        entries: broken
+            .iter()
+            .map(|(name, missing)| {
+                format!(
+                    "{}: {}",
+                    name.bright_yellow(),
+                    join_iter(missing.iter(), ", ")
+                )
+            })
+            .collect(),
+        fix: format!(
+            "fetchy repair {}",
+            join_iter(broken.iter().map(|(name, _)| name), " ")
+        ),
+    });
+
+    // Installed packages whose repository or manifest has vanished
+    let mut unknown_repo = vec![];
+    let mut vanished = vec![];
+
+    for installed in db.installed.values() {
+        match db.repositories.get(&installed.repo_name) {
+            None => unknown_repo.push(installed),
+            Some(repo) => {
+                if !repo.content.packages.contains_key(&installed.manifest.name) {
+                    vanished.push(installed);
+                }
+            }
+        }
+    }
+
+    categories.push(Problems {
+        title: "Packages belonging to an unregistered repository",
+        entries: unknown_repo
+            .iter()
+            .map(|installed| {
+                format!(
+                    "{} (from repository {})",
+                    installed.manifest.name.bright_yellow(),
+                    installed.repo_name.bright_blue()
+                )
+            })
+            .collect(),
+        fix: "fetchy add-repo <path> (or uninstall the packages)".to_owned(),
+    });
+
+    categories.push(Problems {
+        title: "Packages no longer present in their repository",
+        entries: vanished
+            .iter()
+            .map(|installed| {
+                format!(
+                    "{} (from repository {})",
+                    installed.manifest.name.bright_yellow(),
+                    installed.repo_name.bright_blue()
+                )
+            })
+            .collect(),
+        fix: format!(
+            "fetchy uninstall {}",
+            join_iter(
+                vanished.iter().map(|installed| &installed.manifest.name),
+                " "
+            )
+        ),
+    });
+
+    // Files in the binaries directory which don't belong to any package
+    let owned = db
+        .installed
+        .values()
+        .flat_map(|installed| std::iter::once(installed).chain(installed.side_by_side.values()))
+        .flat_map(|installed| installed.installed_files.iter())
+        .map(|file| file.path.clone())
+        .collect::<HashSet<_>>();
+
+    let mut orphans = match std::fs::read_dir(db.bin_dir()) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| !owned.contains(path))
+            .collect::<Vec<PathBuf>>(),
+
+        Err(err) => {
+            warn!(
+                "Failed to read binaries directory at path {}: {err}",
+                db.bin_dir().display().to_string().bright_magenta()
+            );
+
+            vec![]
+        }
+    };
+
+    orphans.sort();
+
+    categories.push(Problems {
+        title: "Orphaned files in the binaries directory",
+        entries: orphans
+            .iter()
+            .map(|path| path.display().to_string().bright_magenta().to_string())
+            .collect(),
+        fix: "remove them manually if they are not needed".to_owned(),
+    });
+
+    // Repositories whose source can't be fetched or doesn't parse anymore
+    let mut unparsable = vec![];
+
+    for (name, repo) in &db.repositories {
+        if let Err(err) = fetch_repository(&repo.source).await {
+            unparsable.push((name, err));
+        }
+    }
+
+    categories.push(Problems {
+        title: "Repositories which can't be loaded anymore",
+        entries: unparsable
+            .iter()
+            .map(|(name, err)| format!("{}: {}", name.bright_blue(), format!("{err:#}").trim()))
+            .collect(),
+        fix: format!(
+            "fix the repositories' files or remove them with 'fetchy remove-repos {}'",
+            join_iter(unparsable.iter().map(|(name, _)| name), " ")
+        ),
+    });
+
+    categories.push(Problems {
+        title: "Stale repositories",
+        entries: stale
+            .iter()
+            .map(|name| {
+                format!(
+                    "{} (last updated {})",
+                    name.bright_blue(),
+                    relative_time(&db.repositories[name].last_fetched)
+                )
+            })
+            .collect(),
+        fix: "fetchy update-repos".to_owned(),
+    });
+
+    let mut problems = 0;
+
+    for Problems {
+        title,
+        entries,
+        fix,
+    } in categories
+    {
+        if entries.is_empty() {
+            info!("{} {title}", "✓".bright_green());
+            continue;
+        }
+
+        problems += entries.len();
+
+        warn!("{} {title} ({}):", "✗".bright_red(), entries.len());
+
+        for entry in entries {
+            warn!("  - {entry}");
+        }
+
+        warn!("  Fix: {}", fix.bright_cyan());
+    }
+
+    if problems > 0 {
+        bail!("Found {} problem(s)", problems.to_string().bright_red());
+    }
+
+    info!("\nEverything looks fine!");
+
+    Ok(())
+}
@@ -1,18 +1,25 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, bail, Context, Result};
 use colored::Colorize;
 use parsy::{ErrorReport, Parser};
-use serde::{Deserialize, Serialize};
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tokio::{fs, task::JoinSet};
 
 use crate::{
-    repos::{ast::Repository, parser::repository},
+    repos::{
+        ast::{Repository, RepositoryFragment},
+        parser::{repository, repository_fragment},
+    },
     utils::{join_fallible_ordered_set, join_iter, progress_bar, ITEMS_PROGRESS_BAR_STYLE},
-    validator::validate_repository,
+    validator::{parse_version, validate_repository},
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RepositorySource {
     pub location: RepositoryLocation,
     pub json: bool,
@@ -55,25 +62,225 @@ pub async fn fetch_repository(source: &RepositorySource) -> Result<Repository> {
         }
     };
 
-    let parsed = if *json {
-        serde_json::from_str(&repo_str)
-            .with_context(|| format!("Failed to parse JSON repository at {location}"))?
+    let location = location.clone();
+    let json = *json;
+
+    // Parsing and validating large repositories is CPU-bound, so it shouldn't block the runtime
+    tokio::task::spawn_blocking(move || {
+        let mut repo = match parse_file(repository(), &repo_str, &location, json) {
+            Ok(repo) => repo,
+            Err(err) => {
+                // Newer repositories may use syntax this version doesn't understand
+                if let Some(min_version) = find_min_fetchy_version(&repo_str, json) {
+                    check_min_fetchy_version(&min_version)?;
+                }
+
+                return Err(err);
+            }
+        };
+
+        if let Some(min_version) = &repo.min_fetchy_version {
+            check_min_fetchy_version(min_version)?;
+        }
+
+        match &location {
+            RepositoryLocation::File(path) => resolve_includes(&mut repo, path, json)?,
+        }
+
+        validate(repo)
+    })
+    .await
+    .context("Failed to wait on Tokio task")?
+}
+
+/// Look for the minimum version of Fetchy required by a repository which failed to parse
+pub fn find_min_fetchy_version(repo_str: &str, json: bool) -> Option<String> {
+    if json {
+        serde_json::from_str::<serde_json::Value>(repo_str)
+            .ok()
+            .and_then(|value| {
+                value
+                    .get("min_fetchy_version")
+                    .and_then(|version| version.as_str())
+                    .map(str::to_owned)
+            })
     } else {
-        repository()
-            .parse_str(&repo_str)
+        Regex::new(r#"(?m)^\s*min_fetchy_version\s+"([^"]*)"\s*(#.*)?$"#)
+            .unwrap()
+            .captures(repo_str)
+            .map(|captures| captures[1].to_owned())
+    }
+}
+
+/// Ensure the running version of Fetchy is recent enough for a repository
+pub fn check_min_fetchy_version(min_version: &str) -> Result<()> {
+    let current_version = env!("CARGO_PKG_VERSION");
+
+    let (Some(mut min), Some(mut current)) =
+        (parse_version(min_version), parse_version(current_version))
+    else {
+        bail!(
+            "Invalid minimum Fetchy version {} in repository",
+            min_version.bright_cyan()
+        );
+    };
+
+    // Make '0.5' and '0.5.0' compare equal
+    let len = min.len().max(current.len());
+    min.resize(len, 0);
+    current.resize(len, 0);
+
+    if current < min {
+        bail!(
+            "This repository requires Fetchy {} or newer, but the installed version is {}\nPlease upgrade fetchy to use it.",
+            min_version.bright_cyan(),
+            current_version.bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Maximum nesting level of included files
+static MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Merge the packages of all files included by a repository (recursively)
+pub fn resolve_includes(repo: &mut Repository, repo_path: &Path, json: bool) -> Result<()> {
+    if repo.includes.is_empty() {
+        return Ok(());
+    }
+
+    let repo_path = std::fs::canonicalize(repo_path).with_context(|| {
+        format!(
+            "Failed to canonicalize repository path: {}",
+            repo_path.display()
+        )
+    })?;
+
+    // File each package comes from
+    let mut origins = repo
+        .packages
+        .keys()
+        .map(|name| (name.clone(), repo_path.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut merged = HashSet::new();
+
+    // Included files, with the chain of files that led to them
+    // Includes are removed from the repository as their packages are merged into it
+    let mut queue = std::mem::take(&mut repo.includes)
+        .into_iter()
+        .rev()
+        .map(|include| (include, vec![repo_path.clone()]))
+        .collect::<Vec<_>>();
+
+    while let Some((include, parents)) = queue.pop() {
+        let parent = parents.last().unwrap();
+
+        let path = parent.parent().unwrap_or(Path::new("")).join(&include);
+
+        let path = std::fs::canonicalize(&path).with_context(|| {
+            format!(
+                "Failed to find file {} included by {}",
+                path.display().to_string().bright_magenta(),
+                parent.display().to_string().bright_magenta()
+            )
+        })?;
+
+        if parents.contains(&path) {
+            bail!(
+                "Circular include detected: {}",
+                join_iter(
+                    parents
+                        .iter()
+                        .chain([&path])
+                        .map(|parent| parent.display().to_string().bright_magenta()),
+                    " -> "
+                )
+            );
+        }
+
+        if parents.len() > MAX_INCLUDE_DEPTH {
+            bail!(
+                "Too many nested includes (maximum is {MAX_INCLUDE_DEPTH}) when including file {}",
+                path.display().to_string().bright_magenta()
+            );
+        }
+
+        // Files included multiple times are only merged once
+        if !merged.insert(path.clone()) {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path).with_context(|| {
+            format!(
+                "Failed to read included file {}",
+                path.display().to_string().bright_magenta()
+            )
+        })?;
+
+        let RepositoryFragment { includes, packages } = parse_file(
+            repository_fragment(),
+            &content,
+            &RepositoryLocation::File(path.clone()),
+            json,
+        )?;
+
+        for (name, manifest) in packages {
+            if let Some(origin) = origins.get(&name) {
+                bail!(
+                    "Package {} is declared in both {} and {}",
+                    name.bright_yellow(),
+                    origin.display().to_string().bright_magenta(),
+                    path.display().to_string().bright_magenta()
+                );
+            }
+
+            origins.insert(name.clone(), path.clone());
+            repo.packages.insert(name, manifest);
+        }
+
+        let mut parents = parents;
+        parents.push(path);
+
+        queue.extend(
+            includes
+                .into_iter()
+                .rev()
+                .map(|include| (include, parents.clone())),
+        );
+    }
+
+    Ok(())
+}
+
+fn parse_file<T: DeserializeOwned>(
+    parser: impl Parser<T>,
+    content: &str,
+    location: &RepositoryLocation,
+    json: bool,
+) -> Result<T> {
+    if json {
+        serde_json::from_str(content)
+            .with_context(|| format!("Failed to parse JSON repository at {location}"))
+    } else {
+        parser
+            .parse_str(content)
             .map(|parsed| parsed.data)
             .map_err(|err| {
                 let location = format!("{location}");
-                let err = ErrorReport::parsing_error(&repo_str, &location, &err);
+                let err = ErrorReport::parsing_error(content, &location, &err);
 
                 // NOTE: I didn't find a way to reset styles here
                 // Error messages are printed in red, but I'd like to only print the error using the
                 // terminal's default style here. But even using an ANSI escape code to reset style
                 // doesn't work for some reason...
                 anyhow!("{}", format!("{err}").white())
-            })?
-    };
+            })
+    }
+}
 
+fn validate(parsed: Repository) -> Result<Repository> {
     if let Err(errors) = validate_repository(&parsed) {
         bail!(
             "Found {} issues with the repository:\n\n{}",
@@ -90,29 +297,54 @@ pub async fn fetch_repository(source: &RepositorySource) -> Result<Repository> {
     Ok(parsed)
 }
 
+/// Fetch some repositories, the result of each fetch being returned separately
 pub async fn fetch_repositories(
     sources: impl ExactSizeIterator<Item = RepositorySource>,
-) -> Result<Vec<Repository>> {
+) -> Result<Vec<Result<Repository>>> {
+    // Multiple repositories may point to the same source, which only needs to be fetched once
+    let mut unique_sources = Vec::<RepositorySource>::new();
+
+    let positions = sources
+        .map(|source| {
+            unique_sources
+                .iter()
+                .position(|unique| *unique == source)
+                .unwrap_or_else(|| {
+                    unique_sources.push(source);
+                    unique_sources.len() - 1
+                })
+        })
+        .collect::<Vec<_>>();
+
     let pb = progress_bar(
-        sources.len(),
+        unique_sources.len(),
         ITEMS_PROGRESS_BAR_STYLE.clone(),
         "Fetching repositories...",
     );
 
     let mut tasks = JoinSet::new();
 
-    for (i, source) in sources.enumerate() {
+    for (i, source) in unique_sources.into_iter().enumerate() {
         let pb = pb.clone();
 
         tasks.spawn(async move {
             let result = fetch_repository(&source).await;
             pb.inc(1);
-            result.map(|repo| (i, repo))
+            Ok((i, result))
         });
     }
 
-    join_fallible_ordered_set(tasks)
+    let fetched = join_fallible_ordered_set(tasks)
         .await
         .inspect(|_| pb.finish_and_clear())
-        .inspect_err(|_| pb.abandon())
+        .inspect_err(|_| pb.abandon())?;
+
+    Ok(positions
+        .into_iter()
+        .map(|pos| match &fetched[pos] {
+            Ok(repo) => Ok(repo.clone()),
+            // Errors can't be cloned
+            Err(err) => Err(anyhow!("{err:#}")),
+        })
+        .collect())
 }
@@ -0,0 +1,100 @@
+//! This module is responsible for keeping a copy of the previous version of updated packages,
+//! so they can be rolled back if the new version turns out to be broken.
+//!
+//! Like extraction, it is a fully-blocking module.
+
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+
+use crate::db::data::{InstalledPackage, PackageBackup};
+
+use super::extract::link_binary;
+
+pub fn backup_binaries(
+    installed: &InstalledPackage,
+    bins_dir: &Path,
+    backup_dir: &Path,
+) -> Result<()> {
+    if backup_dir.exists() {
+        fs::remove_dir_all(backup_dir).with_context(|| {
+            format!(
+                "Failed to remove previous backup directory at path '{}'",
+                backup_dir.display()
+            )
+        })?;
+    }
+
+    fs::create_dir_all(backup_dir).with_context(|| {
+        format!(
+            "Failed to create backup directory at path '{}'",
+            backup_dir.display()
+        )
+    })?;
+
+    let source_dir = installed.linked_from.as_deref().unwrap_or(bins_dir);
+
+    for bin in &installed.binaries {
+        fs::copy(source_dir.join(bin), backup_dir.join(bin))
+            .with_context(|| format!("Failed to backup binary '{bin}'"))?;
+    }
+
+    Ok(())
+}
+
+/// Replace the package's backup with a freshly made one, once its new version is deployed
+pub fn swap_backup(staged_dir: &Path, backup_dir: &Path) -> Result<()> {
+    if backup_dir.exists() {
+        fs::remove_dir_all(backup_dir).with_context(|| {
+            format!(
+                "Failed to remove previous backup directory at path '{}'",
+                backup_dir.display()
+            )
+        })?;
+    }
+
+    fs::rename(staged_dir, backup_dir).with_context(|| {
+        format!(
+            "Failed to move backup directory from '{}' to '{}'",
+            staged_dir.display(),
+            backup_dir.display()
+        )
+    })
+}
+
+pub fn restore_binaries(backup: &PackageBackup, bins_dir: &Path) -> Result<()> {
+    let PackageBackup { installed, dir } = backup;
+
+    let dest_dir = installed.linked_from.as_deref().unwrap_or(bins_dir);
+
+    fs::create_dir_all(dest_dir).with_context(|| {
+        format!(
+            "Failed to create directory at path '{}'",
+            dest_dir.display()
+        )
+    })?;
+
+    for bin in &installed.binaries {
+        let dest = dest_dir.join(bin);
+
+        // Remove the existing binary first, as it may be in use
+        if dest.symlink_metadata().is_ok() {
+            fs::remove_file(&dest)
+                .with_context(|| format!("Failed to remove current binary '{bin}'"))?;
+        }
+
+        fs::copy(dir.join(bin), &dest)
+            .with_context(|| format!("Failed to restore binary '{bin}'"))?;
+
+        if installed.linked_from.is_some() {
+            link_binary(&dest, &bins_dir.join(bin))?;
+        }
+    }
+
+    fs::remove_dir_all(dir).with_context(|| {
+        format!(
+            "Failed to remove backup directory at path '{}'",
+            dir.display()
+        )
+    })
+}
@@ -0,0 +1,45 @@
+//! This module computes the checksums of installed binaries, which are recorded at install time
+//! so modified or corrupted binaries can be detected later on.
+//!
+//! Like extraction, it is a fully-blocking module.
+
+use std::{
+    fmt::Write,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use openssl::sha::Sha256;
+
+/// Compute the SHA-256 digest of a file, as a lowercase hexadecimal string
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open file at path '{}'", path.display()))?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+
+    loop {
+        let read = match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to read file at path '{}'", path.display()))
+            }
+        };
+
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hasher
+        .finish()
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            write!(hex, "{byte:02x}").unwrap();
+            hex
+        }))
+}
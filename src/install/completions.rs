@@ -0,0 +1,92 @@
+//! Installation of shell completions into the directories shells load them from
+
+use std::{fmt, path::PathBuf, str::FromStr, sync::OnceLock};
+
+use serde::Deserialize;
+
+use crate::repos::arch::is_cross_target;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionsMode {
+    /// Install completions into the current user's per-shell directories
+    Auto,
+
+    /// Keep completions in Fetchy's data directory
+    #[default]
+    Off,
+}
+
+impl fmt::Display for CompletionsMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompletionsMode::Auto => write!(f, "auto"),
+            CompletionsMode::Off => write!(f, "off"),
+        }
+    }
+}
+
+impl FromStr for CompletionsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "off" => Ok(Self::Off),
+            _ => Err(format!("unknown value '{s}', expected one of: auto, off")),
+        }
+    }
+}
+
+static COMPLETIONS_MODE: OnceLock<CompletionsMode> = OnceLock::new();
+
+/// Set where shell completions are installed (defaults to [`CompletionsMode::Off`])
+pub fn init_completions_mode(mode: CompletionsMode) {
+    assert!(
+        COMPLETIONS_MODE.set(mode).is_ok(),
+        "Completions mode was already initialized"
+    );
+}
+
+fn completions_mode() -> CompletionsMode {
+    COMPLETIONS_MODE.get().copied().unwrap_or_default()
+}
+
+/// Path a completion file should be installed at in its shell's own directory
+///
+/// Returns `None` if the completion should be kept in Fetchy's data directory instead
+/// (unknown shell, completions for another platform, or disabled feature).
+pub fn shell_completion_path(shell: &str, name: &str) -> Option<PathBuf> {
+    if completions_mode() == CompletionsMode::Off || is_cross_target() {
+        return None;
+    }
+
+    // Shells only pick up completions following their naming convention
+    match shell {
+        "bash" => {
+            dirs::data_dir().map(|dir| dir.join("bash-completion").join("completions").join(name))
+        }
+
+        "fish" => {
+            let name = if name.ends_with(".fish") {
+                name.to_owned()
+            } else {
+                format!("{name}.fish")
+            };
+
+            dirs::config_dir().map(|dir| dir.join("fish").join("completions").join(name))
+        }
+
+        "zsh" => {
+            let name = if name.starts_with('_') {
+                name.to_owned()
+            } else {
+                format!("_{}", name.trim_end_matches(".zsh"))
+            };
+
+            dirs::data_dir().map(|dir| dir.join("zsh").join("site-functions").join(name))
+        }
+
+        _ => None,
+    }
+}
@@ -1,5 +1,7 @@
+use std::collections::HashSet;
+
 use colored::Colorize;
-use comfy_table::{presets, Cell, Color, ContentArrangement, Table};
+use comfy_table::{presets, Cell, Color, ContentArrangement};
 use log::info;
 
 use crate::{
@@ -7,6 +9,7 @@ use crate::{
     install::phases::{PackagesToInstall, UntouchedPackages},
     resolver::ResolvedPkg,
     sources::AssetInfos,
+    utils::new_table,
 };
 
 use super::{phases::InstallPhases, InstalledPackagesHandling};
@@ -87,6 +90,79 @@ pub(super) fn display_install_phases(
     }
 }
 
+/// Packages handled by an installation, used to display a summary once it's done
+pub(super) struct InstallSummary {
+    pub planned: Vec<PlannedInstall>,
+    pub up_to_date: Vec<String>,
+}
+
+pub(super) struct PlannedInstall {
+    pub name: String,
+    pub from_version: Option<String>,
+    pub to_version: String,
+}
+
+pub(super) fn display_install_summary(summary: &InstallSummary, finished: &HashSet<String>) {
+    let InstallSummary {
+        planned,
+        up_to_date,
+    } = summary;
+
+    // Not worth a summary when a single package is involved
+    if planned.len() + up_to_date.len() < 2 {
+        return;
+    }
+
+    let mut table = new_table();
+
+    table
+        // Remove borders
+        .load_preset(presets::NOTHING)
+        // Ask table to take as much width as possible
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    for PlannedInstall {
+        name,
+        from_version,
+        to_version,
+    } in planned
+    {
+        let (status, version) = match from_version {
+            // The package either failed or was aborted because of another one
+            _ if !finished.contains(name) => {
+                (Cell::new("not installed").fg(Color::Red), Cell::new(""))
+            }
+
+            None => (
+                Cell::new("installed").fg(Color::Green),
+                Cell::new(to_version).fg(Color::DarkCyan),
+            ),
+
+            Some(from_version) if from_version == to_version => (
+                Cell::new("reinstalled").fg(Color::Green),
+                Cell::new(to_version).fg(Color::DarkCyan),
+            ),
+
+            Some(from_version) => (
+                Cell::new("updated").fg(Color::Green),
+                Cell::new(format!("{from_version} -> {to_version}")).fg(Color::DarkCyan),
+            ),
+        };
+
+        table.add_row([Cell::new(name).fg(Color::Yellow), status, version]);
+    }
+
+    for name in up_to_date {
+        table.add_row([
+            Cell::new(name).fg(Color::Yellow),
+            Cell::new("up to date"),
+            Cell::new(""),
+        ]);
+    }
+
+    info!("{}\n\n{table}\n", "Summary:".bright_blue());
+}
+
 static PKGS_PER_ROW: usize = 5;
 
 pub fn display_pkg_phase<'a, 'b>(title: &str, content: impl Iterator<Item = ResolvedPkg<'a, 'b>>) {
@@ -97,7 +173,7 @@ pub fn display_pkg_phase<'a, 'b>(title: &str, content: impl Iterator<Item = Reso
         return;
     }
 
-    let mut pkgs_table = Table::new();
+    let mut pkgs_table = new_table();
 
     pkgs_table
         // Remove borders
@@ -122,7 +198,7 @@ pub fn display_update_phase<'a, 'b, 'c, 'd>(
         return;
     }
 
-    let mut pkgs_table = Table::new();
+    let mut pkgs_table = new_table();
 
     pkgs_table
         // Remove borders
@@ -1,28 +1,55 @@
 use std::{
+    collections::HashMap,
     future::Future,
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use colored::Colorize;
-use indicatif::{MultiProgress, ProgressBar};
-use reqwest::Client;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+use log::{debug, info, warn};
+use minisign_verify::{PublicKey, Signature};
+use reqwest::{header::CONTENT_TYPE, Response};
 use tempfile::TempDir;
-use tokio::{fs::File, io::AsyncWriteExt, task::JoinSet};
+use tokio::{
+    fs::{self, File},
+    io::AsyncWriteExt,
+    sync::watch,
+    task::JoinSet,
+};
 
 use crate::{
+    http::{acquire_network_slot, http_client, send_with_retries, throttle_download},
     repos::ast::PackageManifest,
-    sources::AssetInfos,
-    utils::{join_fallible_ordered_set, BYTES_PROGRESS_BAR_STYLE, SPINNER_PROGRESS_BAR_STYLE},
+    resolver::satisfying_pkgs,
+    sources::{AssetChecksum, AssetInfos, AssetSignature},
+    utils::{
+        join_cancellable_ordered_set, join_iter, progress_bars_enabled, BYTES_PROGRESS_BAR_STYLE,
+        SPINNER_PROGRESS_BAR_STYLE,
+    },
 };
 
+use super::checksums::sha256_file;
+
+/// Download the provided assets concurrently, then run the provided finalizer on each of them
+///
+/// Assets can be downloaded in any order, but a package is only finalized once all its
+/// dependencies present in the list have been finalized.
+///
+/// If `keep_going` is enabled, a failing package doesn't abort the other ones (except the packages
+/// depending on it), and all failures are reported once everything else is done.
 pub async fn download_assets_and<
     S: Clone + Send + 'static,
     O: Send + 'static,
     F: Future<Output = Result<O>> + Send,
 >(
     pkgs: Vec<(PackageManifest, AssetInfos)>,
+    keep_going: bool,
     finalize_state: S,
     finalize: impl Fn(PackageManifest, AssetInfos, PathBuf, S, ProgressBar) -> F
         + Clone
@@ -35,16 +62,74 @@ pub async fn download_assets_and<
 )> {
     let dl_dir = TempDir::new().context("Failed to create a temporary downloads directory")?;
 
-    let multi = MultiProgress::new();
+    let multi = if progress_bars_enabled() {
+        MultiProgress::new()
+    } else {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    };
+
     let mut tasks = JoinSet::new();
 
+    let pkgs_count = pkgs.len();
+    let done_count = Arc::new(AtomicUsize::new(0));
+
     let largest_pkg_name = pkgs
         .iter()
         .map(|(manifest, _)| manifest.name.len())
         .max()
         .unwrap();
 
+    // Overall progress, with the total size growing as downloads' lengths get known
+    let summary_pb = if pkgs_count > 1 {
+        let pb = multi.add(
+            ProgressBar::new(0)
+                .with_style(SPINNER_PROGRESS_BAR_STYLE.clone())
+                .with_prefix(format!("{:largest_pkg_name$} ", "total"))
+                .with_message(format!("0/{pkgs_count} packages")),
+        );
+
+        pb.enable_steady_tick(Duration::from_millis(125));
+        pb
+    } else {
+        ProgressBar::hidden()
+    };
+
+    // Channels used to notify dependents when a package has been finalized
+    let (finalized_senders, finalized_receivers): (HashMap<_, _>, HashMap<_, _>) = pkgs
+        .iter()
+        .map(|(pkg, _)| {
+            let (sender, receiver) = watch::channel(false);
+            ((pkg.name.clone(), sender), (pkg.name.clone(), receiver))
+        })
+        .unzip();
+
+    let mut finalized_senders = finalized_senders;
+
+    // Used to ask the tasks to stop when the user hits Ctrl-C or a package fails to install
+    let (cancel, cancelled) = watch::channel(false);
+
+    let manifests = pkgs.iter().map(|(pkg, _)| pkg).collect::<Vec<_>>();
+
+    let mut deps_finalized = manifests
+        .iter()
+        .map(|pkg| {
+            pkg.all_deps()
+                .flat_map(|dep| satisfying_pkgs(pkg, dep, &manifests))
+                .filter_map(|dep| {
+                    finalized_receivers
+                        .get(dep)
+                        .map(|recv| (dep.to_owned(), recv.clone()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+        .into_iter();
+
     for (i, (pkg, asset_infos)) in pkgs.into_iter().enumerate() {
+        let finalized = finalized_senders.remove(&pkg.name).unwrap();
+
+        let deps_finalized = deps_finalized.next().unwrap();
+
         let pb = multi.add(
             ProgressBar::new_spinner()
                 .with_style(SPINNER_PROGRESS_BAR_STYLE.clone())
@@ -58,42 +143,195 @@ pub async fn download_assets_and<
 
         let finalize = finalize.clone();
         let finalize_state = finalize_state.clone();
+        let done_count = Arc::clone(&done_count);
+        let summary_pb = summary_pb.clone();
+
+        let task_pb = pb.clone();
+
+        let mut cancelled = cancelled.clone();
 
         tasks.spawn(async move {
-            let asset_path = download_asset(&pkg, &asset_infos, &dl_dir, pb.clone())
-                .await
-                .with_context(|| {
-                    format!(
-                        "Failed to download asset for package {}...",
+            let result = async move {
+                // Downloading and verifying the asset can be interrupted at any time
+                let prepared = async {
+                    let mut asset_infos = asset_infos;
+
+                    let network_slot = acquire_network_slot().await;
+
+                    let (asset_path, url) =
+                        download_asset(&pkg, &asset_infos, &dl_dir, pb.clone(), &summary_pb)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Failed to download asset for package {}...",
+                                    pkg.name.bright_yellow()
+                                )
+                            })?;
+
+                    drop(network_slot);
+
+                    // Signatures sit next to the asset, so they must come from the same mirror
+                    if url != asset_infos.url {
+                        if let Some(signature) = &mut asset_infos.signature {
+                            signature.url = format!("{url}.minisig");
+                        }
+                    }
+
+                    // Let the finalizer know which mirror the asset actually came from
+                    asset_infos.url = url;
+
+                    if let Some(signature) = &asset_infos.signature {
+                        pb.set_message("verifying signature...");
+
+                        verify_signature(&asset_path, signature, &asset_infos)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Failed to verify signature of asset for package {}",
+                                    pkg.name.bright_yellow()
+                                )
+                            })?;
+                    }
+
+                    if let Some(checksum) = &asset_infos.checksum {
+                        pb.set_message("verifying checksum...");
+
+                        verify_checksum(&asset_path, checksum, &asset_infos)
+                            .await
+                            .with_context(|| {
+                                format!(
+                                    "Failed to verify checksum of asset for package {}",
+                                    pkg.name.bright_yellow()
+                                )
+                            })?;
+                    }
+
+                    if !deps_finalized.is_empty() {
+                        pb.set_message("waiting for dependencies...");
+                    }
+
+                    for (dep, mut recv) in deps_finalized {
+                        // The sender is dropped without notifying if the dependency failed to install
+                        recv.wait_for(|finalized| *finalized).await.map_err(|_| {
+                            anyhow!(
+                                "Dependency {} of package {} failed to install",
+                                dep.bright_yellow(),
+                                pkg.name.bright_yellow()
+                            )
+                        })?;
+                    }
+
+                    Ok::<_, anyhow::Error>((asset_infos, asset_path))
+                };
+
+                let (asset_infos, asset_path) = tokio::select! {
+                    prepared = prepared => prepared?,
+                    _ = cancelled.wait_for(|cancelled| *cancelled) => bail!(
+                        "Installation of package {} was cancelled",
+                        pkg.name.bright_yellow()
+                    ),
+                };
+
+                // Finalizing can't be interrupted without leaving the package half-installed,
+                // so once started it always runs to completion
+                if *cancelled.borrow() {
+                    bail!(
+                        "Installation of package {} was cancelled",
                         pkg.name.bright_yellow()
-                    )
-                })?;
+                    );
+                }
+
+                let pkg_name = pkg.name.clone();
 
-            let pkg_name = pkg.name.clone();
+                let output = finalize(pkg, asset_infos, asset_path, finalize_state, pb.clone())
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to downloaded asset for package {}",
+                            pkg_name.bright_yellow()
+                        )
+                    })?;
 
-            let output = finalize(pkg, asset_infos, asset_path, finalize_state, pb.clone())
-                .await
-                .with_context(|| {
-                    format!(
-                        "Failed to downloaded asset for package {}",
+                pb.finish_and_clear();
+
+                let done = done_count.fetch_add(1, Ordering::Relaxed) + 1;
+
+                summary_pb.set_message(format!("{done}/{pkgs_count} packages"));
+
+                if !progress_bars_enabled() {
+                    info!(
+                        "Finished package {} ({done}/{pkgs_count})",
                         pkg_name.bright_yellow()
-                    )
-                })?;
+                    );
+                }
+
+                finalized.send_replace(true);
 
-            pb.finish_and_clear();
+                Ok(output)
+            }
+            .await;
 
-            Ok((i, output))
+            match result {
+                Ok(output) => Ok((i, Ok(output))),
+
+                // Other packages keep being installed, the error is reported at the end
+                Err(err) if keep_going => {
+                    task_pb.abandon_with_message("failed".bright_red().to_string());
+                    Ok((i, Err(err)))
+                }
+
+                Err(err) => Err(err),
+            }
         });
     }
 
-    let joined = join_fallible_ordered_set(tasks)
+    let joined = join_cancellable_ordered_set(tasks, cancel)
         .await
-        .map(|downloaded| (dl_dir, downloaded));
+        .and_then(|results| {
+            let mut outputs = vec![];
+            let mut errors = vec![];
+
+            for result in results {
+                match result {
+                    Ok(output) => outputs.push(output),
+                    Err(err) => errors.push(err),
+                }
+            }
+
+            if errors.is_empty() {
+                return Ok(outputs);
+            }
+
+            Err(anyhow!(
+                "{} package(s) failed to install:\n\n{}",
+                errors.len().to_string().bright_red(),
+                join_iter(errors.iter().map(|err| format!("* {err:#}")), "\n")
+            ))
+        });
+
+    summary_pb.finish_and_clear();
 
     // Ignore errors from failing to clear multibar
     let _ = multi.clear();
 
-    joined
+    match joined {
+        Ok(downloaded) => Ok((dl_dir, downloaded)),
+
+        // Remove partial downloads right away (e.g. when interrupted with Ctrl-C),
+        // all tasks are done using the directory at this point
+        Err(err) => {
+            let dl_dir_path = dl_dir.path().to_owned();
+
+            if let Err(close_err) = dl_dir.close() {
+                warn!(
+                    "Failed to remove temporary downloads directory at path {}: {close_err}",
+                    dl_dir_path.display()
+                );
+            }
+
+            Err(err)
+        }
+    }
 }
 
 async fn download_asset(
@@ -101,40 +339,246 @@ async fn download_asset(
     asset_infos: &AssetInfos,
     dl_dir: &Path,
     pb: ProgressBar,
-) -> Result<PathBuf> {
+    summary_pb: &ProgressBar,
+) -> Result<(PathBuf, String)> {
     let dl_file_path = dl_dir.join(format!("{}.tmp", pkg.name));
 
     let mut dl_file = File::create(&dl_file_path)
         .await
         .context("Failed to create temporary download file")?;
 
-    let mut res = Client::new()
-        .get(&asset_infos.url)
-        .headers(asset_infos.headers.clone())
-        .send()
-        .await
-        .context("Failed to perform GET request on asset's URL")?;
+    let urls = std::iter::once(&asset_infos.url)
+        .chain(&asset_infos.mirrors)
+        .collect::<Vec<_>>();
+
+    let mut response = None;
+
+    // Fall back to the next mirror when an URL fails
+    for (i, url) in urls.iter().enumerate() {
+        match request_asset(url, asset_infos, &pb).await {
+            Ok(res) => {
+                debug!(
+                    "Downloading asset for package {} from URL: {url}",
+                    pkg.name.bright_yellow()
+                );
+
+                if res.url().as_str() != url.as_str() {
+                    debug!(
+                        "Asset URL of package {} was redirected to: {}",
+                        pkg.name.bright_yellow(),
+                        res.url()
+                    );
+                }
+
+                response = Some((*url, res));
+                break;
+            }
+
+            Err(err) if i + 1 < urls.len() => {
+                debug!(
+                    "Failed to download asset for package {} from URL {url}, trying next mirror: {err:#}",
+                    pkg.name.bright_yellow()
+                );
+
+                pb.set_message("trying next mirror...");
+            }
+
+            Err(err) => return Err(err),
+        }
+    }
+
+    let (url, mut res) = response.unwrap();
+
+    let is_html = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+
+    if is_html {
+        bail!(
+            "Asset URL returned an HTML page instead of a file: {}",
+            url.bright_magenta()
+        );
+    }
 
     if let Some(len) = res.content_length() {
         pb.set_length(len);
+        summary_pb.inc_length(len);
+        summary_pb.set_style(BYTES_PROGRESS_BAR_STYLE.clone());
     }
 
     pb.set_style(BYTES_PROGRESS_BAR_STYLE.clone());
 
+    let mut downloaded = 0;
+
     while let Some(chunk) = res
         .chunk()
         .await
         .context("Failed to read chunk from response")?
     {
+        throttle_download(chunk.len()).await;
+
         dl_file
             .write(&chunk)
             .await
             .context("Failed to write chunk to disk")?;
 
+        downloaded += chunk.len();
+
         pb.inc(chunk.len().try_into().unwrap());
+        summary_pb.inc(chunk.len().try_into().unwrap());
     }
 
     dl_file.flush().await?;
 
-    Ok(dl_file_path)
+    if downloaded == 0 {
+        bail!("Asset URL returned an empty file: {}", url.bright_magenta());
+    }
+
+    Ok((dl_file_path, url.clone()))
+}
+
+/// Send a request to an asset's URL, failing on non-success statuses
+async fn request_asset(url: &str, asset_infos: &AssetInfos, pb: &ProgressBar) -> Result<Response> {
+    let res = send_with_retries(
+        http_client().get(url).headers(asset_infos.headers.clone()),
+        |attempt, max| pb.set_message(format!("retrying download ({attempt}/{max})...")),
+    )
+    .await
+    .context("Failed to perform GET request on asset's URL")?;
+
+    let status = res.status();
+
+    if !status.is_success() {
+        let body = res.text().await.unwrap_or_default();
+
+        bail!(
+            "Asset URL returned {}: {}\n{}",
+            status.to_string().bright_red(),
+            url.bright_magenta(),
+            body_snippet(&body)
+        );
+    }
+
+    Ok(res)
+}
+
+/// Maximum number of characters from an error response's body to display
+static BODY_SNIPPET_MAX_CHARS: usize = 200;
+
+fn body_snippet(body: &str) -> String {
+    // Error pages are often made of many indented lines
+    let body = join_iter(body.split_whitespace(), " ");
+
+    match body.char_indices().nth(BODY_SNIPPET_MAX_CHARS) {
+        None => body,
+        Some((pos, _)) => format!("{}...", &body[..pos]),
+    }
+}
+
+async fn verify_signature(
+    asset_path: &Path,
+    signature: &AssetSignature,
+    asset_infos: &AssetInfos,
+) -> Result<()> {
+    let AssetSignature { url, minisign_key } = signature;
+
+    let public_key =
+        PublicKey::from_base64(minisign_key).context("Failed to decode minisign public key")?;
+
+    let res = send_with_retries(
+        http_client().get(url).headers(asset_infos.headers.clone()),
+        |_, _| {},
+    )
+    .await
+    .and_then(|res| res.error_for_status().map_err(Into::into))
+    .with_context(|| format!("Failed to download signature file at URL: {url}"))?;
+
+    let signature = res
+        .text()
+        .await
+        .context("Failed to decode signature file as text")?;
+
+    let signature = Signature::decode(&signature).context("Failed to decode signature file")?;
+
+    let content = fs::read(asset_path)
+        .await
+        .context("Failed to read downloaded asset")?;
+
+    public_key
+        .verify(&content, &signature, true)
+        .context("Signature does not match downloaded asset")
+}
+
+async fn verify_checksum(
+    asset_path: &Path,
+    checksum: &AssetChecksum,
+    asset_infos: &AssetInfos,
+) -> Result<()> {
+    let AssetChecksum { url, filename } = checksum;
+
+    let res = send_with_retries(
+        http_client().get(url).headers(asset_infos.headers.clone()),
+        |_, _| {},
+    )
+    .await
+    .and_then(|res| res.error_for_status().map_err(Into::into))
+    .with_context(|| format!("Failed to download checksums file at URL: {url}"))?;
+
+    let sums = res
+        .text()
+        .await
+        .context("Failed to decode checksums file as text")?;
+
+    let expected = find_checksum(&sums, filename)
+        .with_context(|| format!("Failed to parse checksums file at URL: {url}"))?;
+
+    let asset_path = asset_path.to_owned();
+
+    let actual = tokio::task::spawn_blocking(move || sha256_file(&asset_path))
+        .await
+        .context("Failed to wait on Tokio task")??;
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!(
+            "Checksum does not match downloaded asset (expected {}, got {})",
+            expected.bright_cyan(),
+            actual.bright_cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Find a file's digest in a checksums file, as generated by `sha256sum`
+///
+/// Each line is made of a digest followed by a file name, prefixed with a `*` in binary mode.
+fn find_checksum<'a>(sums: &'a str, filename: &str) -> Result<&'a str> {
+    for (i, line) in sums.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (digest, name) = line
+            .split_once(char::is_whitespace)
+            .map(|(digest, name)| (digest, name.trim()))
+            .filter(|(digest, name)| {
+                digest.len() == 64
+                    && digest.chars().all(|c| c.is_ascii_hexdigit())
+                    && !name.is_empty()
+            })
+            .with_context(|| format!("Line {} is not a valid checksum line: {line}", i + 1))?;
+
+        let name = name.strip_prefix('*').unwrap_or(name);
+
+        if name.strip_prefix("./").unwrap_or(name) == filename {
+            return Ok(digest);
+        }
+    }
+
+    bail!(
+        "Checksums file doesn't contain any entry for {}",
+        filename.bright_magenta()
+    )
 }
@@ -0,0 +1,104 @@
+//! Detection of the assets' compression from their first bytes
+//!
+//! Repositories sometimes declare the wrong format for an asset (or upstreams change their
+//! packaging between releases), so the detected compression is used over the declared one.
+
+use std::{fmt, fs::File, io::Read, path::Path, sync::OnceLock};
+
+use anyhow::{bail, Context, Result};
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use xz::read::XzDecoder;
+
+use crate::sources::ArchiveFormat;
+
+static FORMAT_DETECTION: OnceLock<bool> = OnceLock::new();
+
+/// Enable or disable format detection (when disabled, the declared formats are always used)
+///
+/// Format detection is enabled by default.
+pub fn init_format_detection(enabled: bool) {
+    assert!(
+        FORMAT_DETECTION.set(enabled).is_ok(),
+        "Format detection was already initialized"
+    );
+}
+
+pub fn format_detection_enabled() -> bool {
+    FORMAT_DETECTION.get().copied().unwrap_or(true)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+    Zip,
+}
+
+static MAGIC_BYTES: [(Compression, &[u8]); 5] = [
+    (Compression::Gzip, &[0x1f, 0x8b]),
+    (Compression::Xz, &[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]),
+    (Compression::Bzip2, b"BZh"),
+    (Compression::Zstd, &[0x28, 0xb5, 0x2f, 0xfd]),
+    (Compression::Zip, b"PK\x03\x04"),
+];
+
+impl Compression {
+    /// Archive format using this compression
+    pub fn archive_format(self) -> ArchiveFormat {
+        match self {
+            Compression::Gzip => ArchiveFormat::TarGz,
+            Compression::Xz => ArchiveFormat::TarXz,
+            Compression::Bzip2 => ArchiveFormat::TarBz2,
+            Compression::Zstd => ArchiveFormat::TarZst,
+            Compression::Zip => ArchiveFormat::Zip,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Xz => write!(f, "xz"),
+            Compression::Bzip2 => write!(f, "bzip2"),
+            Compression::Zstd => write!(f, "zstd"),
+            Compression::Zip => write!(f, "zip"),
+        }
+    }
+}
+
+/// Detect the compression of a file from its magic bytes
+///
+/// Returns `None` if the file isn't compressed (or uses an unknown compression)
+pub fn detect_compression(path: &Path) -> Result<Option<Compression>> {
+    let file = File::open(path).context("Failed to open downloaded asset")?;
+
+    let mut header = Vec::with_capacity(6);
+
+    file.take(6)
+        .read_to_end(&mut header)
+        .context("Failed to read downloaded asset")?;
+
+    Ok(MAGIC_BYTES
+        .iter()
+        .find(|(_, magic)| header.starts_with(magic))
+        .map(|(compression, _)| *compression))
+}
+
+/// Decompress a single-file asset on the fly
+pub fn decompress(file: File, compression: Compression) -> Result<Box<dyn Read>> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(GzDecoder::new(file)),
+        Compression::Xz => Box::new(XzDecoder::new(file)),
+        Compression::Bzip2 => Box::new(BzDecoder::new(file)),
+        Compression::Zstd => {
+            Box::new(zstd::Decoder::new(file).context("Failed to initialize zstd decoder")?)
+        }
+        Compression::Zip => {
+            bail!("Asset is a zip archive, it should be declared with 'archive(Zip)'")
+        }
+    })
+}
@@ -7,49 +7,217 @@
 
 use std::{
     fs::File,
-    io::Read,
+    io::{Read, Write},
     path::{Component, Path, PathBuf},
+    sync::OnceLock,
 };
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use bzip2::read::BzDecoder;
 use colored::Colorize;
 use flate2::read::GzDecoder;
-use indicatif::ProgressBar;
+use indicatif::{HumanBytes, ProgressBar};
+use log::warn;
+use rapidfuzz::distance::jaro_winkler::BatchComparator;
 use xz::read::XzDecoder;
 
 use crate::{
-    sources::{ArchiveFormat, AssetType, BinaryInArchive},
+    repos::arch::{target_platform, System},
+    sources::{
+        pattern::Pattern, ArchiveFormat, AssetType, BinaryInArchive, ExtraFileInArchive,
+        ExtraFileKind,
+    },
     utils::join_iter,
 };
 
-use self::{tar::TarReader, zip::ZipReader};
+use self::{
+    compression::{decompress, detect_compression, format_detection_enabled, Compression},
+    tar::TarReader,
+    zip::ZipReader,
+};
+
+pub use self::compression::init_format_detection;
 
+mod compression;
 mod tar;
 mod zip;
 
+/// Maximum sizes of the files extracted from archives, to protect against decompression bombs
+pub struct ExtractionLimits {
+    pub max_file_size: u64,
+    pub max_total_size: u64,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: 512 * 1024 * 1024,
+            max_total_size: 2048 * 1024 * 1024,
+        }
+    }
+}
+
+static EXTRACTION_LIMITS: OnceLock<ExtractionLimits> = OnceLock::new();
+
+/// Set the maximum sizes of extracted files (defaults to [`ExtractionLimits::default`])
+pub fn init_extraction_limits(limits: ExtractionLimits) {
+    assert!(
+        EXTRACTION_LIMITS.set(limits).is_ok(),
+        "Extraction limits were already initialized"
+    );
+}
+
 trait AssetContentIter {
-    fn next_file(&mut self) -> Option<Result<(PathBuf, impl Read)>>;
+    fn next_file(&mut self) -> Option<Result<(PathBuf, EntryKind, impl Read)>>;
 }
 
+/// Type of an entry in an archive
+enum EntryKind {
+    File,
+    Directory,
+
+    /// Symbolic or hard link, whose content is empty
+    Link {
+        /// Path of the target from the archive's root (`None` if it points outside of the archive)
+        target: Option<PathBuf>,
+    },
+}
+
+/// Content extracted from an asset
+pub struct ExtractedAsset {
+    pub binaries: Vec<String>,
+    pub extra_files: Vec<ExtractedExtraFile>,
+}
+
+/// Completion or man page extracted from an archive
+pub struct ExtractedExtraFile {
+    /// Location of the file in the staging directory
+    pub staged: PathBuf,
+
+    pub kind: ExtraFileKind,
+
+    /// Basename of the file in the archive
+    pub name: String,
+}
+
+/// Extract an asset's binaries and extra files into the provided directories
+///
+/// When targeting Windows, executables without an extension get a `.exe` suffix so they can be run
+/// from PATH, unless `exe_suffix` is disabled.
 pub fn extract_asset(
+    pkg_name: &str,
     asset_path: &Path,
     content: &AssetType,
     bins_dir: &Path,
+    extras_dir: &Path,
+    exe_suffix: bool,
     pb: ProgressBar,
-) -> Result<()> {
+) -> Result<ExtractedAsset> {
+    let mut extracted =
+        extract_asset_content(pkg_name, asset_path, content, bins_dir, extras_dir, pb)?;
+
+    if exe_suffix && target_platform().0 == System::windows {
+        for bin in &mut extracted.binaries {
+            let path = bins_dir.join(&*bin);
+
+            if Path::new(bin).extension().is_some() || !is_windows_executable(&path)? {
+                continue;
+            }
+
+            let with_suffix = format!("{bin}.exe");
+
+            std::fs::rename(&path, bins_dir.join(&with_suffix))
+                .with_context(|| format!("Failed to rename binary '{bin}' to '{with_suffix}'"))?;
+
+            *bin = with_suffix;
+        }
+    }
+
+    Ok(extracted)
+}
+
+fn extract_asset_content(
+    pkg_name: &str,
+    asset_path: &Path,
+    content: &AssetType,
+    bins_dir: &Path,
+    extras_dir: &Path,
+    pb: ProgressBar,
+) -> Result<ExtractedAsset> {
     match content {
         AssetType::Binary { copy_as } => {
-            let dest = bins_dir.join(copy_as);
+            // Single binaries may be compressed (e.g. 'tool.xz')
+            let compression = if format_detection_enabled() {
+                detect_compression(asset_path)?
+            } else {
+                None
+            };
 
-            std::fs::copy(asset_path, &dest)
-                .with_context(|| format!("Failed to copy binary '{copy_as}'"))?;
+            if let Some(compression) = compression {
+                pb.set_message(format!("decompressing {compression} binary..."));
+            }
+
+            for copy_as in copy_as {
+                let dest = bins_dir.join(copy_as);
+
+                match compression {
+                    None => {
+                        std::fs::copy(asset_path, &dest)
+                            .with_context(|| format!("Failed to copy binary '{copy_as}'"))?;
+                    }
 
-            apply_bin_perms(&dest)?;
+                    Some(compression) => {
+                        let file =
+                            File::open(asset_path).context("Failed to open downloaded asset")?;
 
-            Ok(())
+                        let out_file = File::create(&dest)
+                            .context("Failed to create temporary file to extract binary")?;
+
+                        copy_limited(&mut decompress(file, compression)?, out_file, &dest, 0)
+                            .with_context(|| format!("Failed to decompress binary '{copy_as}'"))?;
+                    }
+                }
+
+                apply_bin_perms(&dest)?;
+            }
+
+            Ok(ExtractedAsset {
+                binaries: copy_as.clone(),
+                extra_files: vec![],
+            })
         }
 
-        AssetType::Archive { format, files } => {
+        AssetType::Archive {
+            format,
+            files,
+            extra_files,
+            strip_components,
+        } => {
+            let detected = if format_detection_enabled() {
+                detect_compression(asset_path)?.map(Compression::archive_format)
+            } else {
+                None
+            };
+
+            let format = match detected {
+                Some(detected) if detected != *format => {
+                    pb.suspend(|| {
+                        warn!(
+                            "{} Asset of package {} is declared as {} but looks like {}, extracting it as such (use {} to force the declared format)",
+                            "WARNING:".bright_red().bold(),
+                            pkg_name.bright_yellow(),
+                            format.to_string().bright_cyan(),
+                            detected.to_string().bright_cyan(),
+                            "--no-format-detection".bright_cyan()
+                        )
+                    });
+
+                    detected
+                }
+
+                _ => *format,
+            };
+
             pb.set_message("opening archive...");
 
             let file = File::open(asset_path).context("Failed to open downloaded archive")?;
@@ -57,17 +225,70 @@ pub fn extract_asset(
             match format {
                 ArchiveFormat::TarGz => {
                     let mut reader = TarReader::new(GzDecoder::new(file));
-                    extract_archive(reader.iter()?, files, bins_dir, pb.clone())
+                    extract_archive(
+                        reader.iter()?,
+                        files,
+                        extra_files,
+                        *strip_components,
+                        bins_dir,
+                        extras_dir,
+                        pb.clone(),
+                    )
                 }
 
                 ArchiveFormat::TarXz => {
                     let mut reader = TarReader::new(XzDecoder::new(file));
-                    extract_archive(reader.iter()?, files, bins_dir, pb.clone())
+                    extract_archive(
+                        reader.iter()?,
+                        files,
+                        extra_files,
+                        *strip_components,
+                        bins_dir,
+                        extras_dir,
+                        pb.clone(),
+                    )
+                }
+
+                ArchiveFormat::TarBz2 => {
+                    let mut reader = TarReader::new(BzDecoder::new(file));
+                    extract_archive(
+                        reader.iter()?,
+                        files,
+                        extra_files,
+                        *strip_components,
+                        bins_dir,
+                        extras_dir,
+                        pb.clone(),
+                    )
+                }
+
+                ArchiveFormat::TarZst => {
+                    let decoder =
+                        zstd::Decoder::new(file).context("Failed to initialize zstd decoder")?;
+
+                    let mut reader = TarReader::new(decoder);
+                    extract_archive(
+                        reader.iter()?,
+                        files,
+                        extra_files,
+                        *strip_components,
+                        bins_dir,
+                        extras_dir,
+                        pb.clone(),
+                    )
                 }
 
                 ArchiveFormat::Zip => {
                     let mut reader = ZipReader::new(file)?;
-                    extract_archive(reader.iter(), files, bins_dir, pb.clone())
+                    extract_archive(
+                        reader.iter(),
+                        files,
+                        extra_files,
+                        *strip_components,
+                        bins_dir,
+                        extras_dir,
+                        pb.clone(),
+                    )
                 }
             }
         }
@@ -77,9 +298,12 @@ pub fn extract_asset(
 fn extract_archive(
     mut reader: impl AssetContentIter,
     files: &[BinaryInArchive],
+    extra_files: &[ExtraFileInArchive],
+    strip_components: usize,
     bins_dir: &Path,
+    extras_dir: &Path,
     pb: ProgressBar,
-) -> Result<()> {
+) -> Result<ExtractedAsset> {
     pb.set_message(format!("searching 1/{}...", files.len()));
 
     let mut extracted = Vec::with_capacity(files.len());
@@ -89,19 +313,44 @@ fn extract_archive(
 
     let mut extracted_count = 0;
 
+    // Total size of the files extracted so far
+    let mut extracted_size = 0;
+
+    let mut extras_matched = vec![false; extra_files.len()];
+    let mut extracted_extras = Vec::<ExtractedExtraFile>::new();
+
+    // Binaries extracted from links whose target wasn't found yet, with their target
+    let mut pending_links = Vec::<(PathBuf, String)>::new();
+
     while let Some(entry) = reader.next_file() {
-        let (path, mut entry_reader) = entry?;
+        let (path, kind, mut entry_reader) = entry?;
+
+        // Entries entirely made of stripped components are skipped
+        let Some(path_in_archive) = simplify_path(&path, strip_components) else {
+            continue;
+        };
+
+        let link_target = match kind {
+            EntryKind::File => None,
+            EntryKind::Directory => continue,
+            EntryKind::Link { target } => {
+                Some(target.and_then(|target| simplify_path(&target, strip_components)))
+            }
+        };
+
+        paths_in_archive.push(path_in_archive.clone());
+
+        let mut consumed = false;
+
+        // Location the entry's content was extracted to
+        let mut extracted_to = None::<PathBuf>;
 
         for (i, file) in files.iter().enumerate() {
             let BinaryInArchive {
                 path_matcher,
-                copy_as,
+                copy_as: _,
             } = file;
 
-            let path_in_archive = simplify_path(&path);
-
-            paths_in_archive.push(path_in_archive.clone());
-
             if !path_matcher.is_match(&path_in_archive) {
                 continue;
             }
@@ -127,6 +376,25 @@ fn extract_archive(
                 );
             }
 
+            // Binaries without an explicit name keep the one of the matched file
+            let copy_as = bin_name(file, &path_in_archive);
+
+            if let Some(clashing_bin_idx) = (0..files.len()).find(|&j| {
+                extracted[j].as_ref().is_some_and(|other_path_in_archive| {
+                    bin_name(&files[j], other_path_in_archive) == copy_as
+                })
+            }) {
+                bail!(
+                    "Patterns '{}' and '{}' both extract a binary named {}",
+                    files[clashing_bin_idx]
+                        .path_matcher
+                        .to_string()
+                        .bright_blue(),
+                    path_matcher.to_string().bright_blue(),
+                    copy_as.bright_green()
+                );
+            }
+
             extracted_count += 1;
 
             pb.set_message(format!(
@@ -136,13 +404,40 @@ fn extract_archive(
 
             let dest = bins_dir.join(copy_as);
 
-            let mut out_file =
-                File::create(&dest).context("Failed to create temporary file to extract binary")?;
+            match &link_target {
+                None => {
+                    let out_file = File::create(&dest)
+                        .context("Failed to create temporary file to extract binary")?;
 
-            std::io::copy(&mut entry_reader, &mut out_file)
-                .with_context(|| format!("Failed to copy binary '{copy_as}'"))?;
+                    extracted_size +=
+                        copy_limited(&mut entry_reader, out_file, &dest, extracted_size)
+                            .with_context(|| format!("Failed to copy binary '{copy_as}'"))?;
+
+                    apply_bin_perms(&dest)?;
+
+                    extracted_to = Some(dest);
+                }
+
+                Some(None) => bail!(
+                    "Binary '{}' is a link pointing outside of the archive",
+                    path_in_archive.bright_yellow()
+                ),
 
-            apply_bin_perms(&dest)?;
+                Some(Some(target)) => {
+                    // Use the target's content if it was already extracted, otherwise wait for it
+                    let extracted_target =
+                        (0..files.len()).find(|&j| extracted[j].as_ref() == Some(target));
+
+                    match extracted_target {
+                        Some(j) => copy_extracted_binary(
+                            &bins_dir.join(bin_name(&files[j], target)),
+                            &dest,
+                        )?,
+
+                        None => pending_links.push((dest, target.clone())),
+                    }
+                }
+            }
 
             pb.set_message(if extracted_count < files.len() {
                 format!("searching  {}/{}...", extracted_count + 1, files.len())
@@ -150,29 +445,260 @@ fn extract_archive(
                 "checking end of archive...".to_owned()
             });
 
-            extracted[i] = Some(path_in_archive)
+            extracted[i] = Some(path_in_archive.clone());
+            consumed = true;
         }
-    }
 
-    for (i, result) in extracted.iter().enumerate() {
-        if result.is_none() {
-            bail!(
-                "Pattern '{}' matched none of the archive's files:\n\n{}",
-                files[i].path_matcher.to_string().bright_blue(),
-                join_iter(
-                    paths_in_archive
-                        .iter()
-                        .map(|path| format!("* {}", path.bright_yellow())),
-                    "\n"
+        for (i, file) in extra_files.iter().enumerate() {
+            let ExtraFileInArchive { path_matcher, kind } = file;
+
+            if !path_matcher.is_match(&path_in_archive) {
+                continue;
+            }
+
+            if consumed {
+                bail!(
+                    "File at path '{}' in archive was matched by multiple patterns",
+                    path_in_archive.bright_yellow()
+                );
+            }
+
+            if link_target.is_some() {
+                bail!(
+                    "File at path '{}' in archive is a link, which is only supported for binaries",
+                    path_in_archive.bright_yellow()
+                );
+            }
+
+            let name = path_in_archive.rsplit('/').next().unwrap().to_owned();
+
+            if extracted_extras
+                .iter()
+                .any(|extracted| extracted.kind == *kind && extracted.name == name)
+            {
+                bail!(
+                    "Pattern '{}' matched multiple files named '{}' in archive",
+                    path_matcher.to_string().bright_blue(),
+                    name.bright_yellow()
+                );
+            }
+
+            pb.set_message(format!("extracting '{name}'..."));
+
+            std::fs::create_dir_all(extras_dir).with_context(|| {
+                format!(
+                    "Failed to create extra files directory at path: {}",
+                    extras_dir.display()
                 )
-            );
+            })?;
+
+            let staged = extras_dir.join(extracted_extras.len().to_string());
+
+            let out_file =
+                File::create(&staged).context("Failed to create temporary file to extract file")?;
+
+            extracted_size += copy_limited(&mut entry_reader, out_file, &staged, extracted_size)
+                .with_context(|| format!("Failed to copy file '{name}'"))?;
+
+            extracted_extras.push(ExtractedExtraFile {
+                staged: staged.clone(),
+                kind: kind.clone(),
+                name,
+            });
+
+            extras_matched[i] = true;
+            extracted_to = Some(staged);
+            consumed = true;
+        }
+
+        if link_target.is_some() {
+            continue;
+        }
+
+        // Resolve the links waiting for this file
+        for (dest, _) in pending_links.extract_if(.., |(_, target)| *target == path_in_archive) {
+            match &extracted_to {
+                Some(source) => copy_extracted_binary(source, &dest)?,
+
+                None => {
+                    let out_file = File::create(&dest)
+                        .context("Failed to create temporary file to extract binary")?;
+
+                    extracted_size +=
+                        copy_limited(&mut entry_reader, out_file, &dest, extracted_size)
+                            .with_context(|| {
+                                format!("Failed to copy linked file '{path_in_archive}'")
+                            })?;
+
+                    apply_bin_perms(&dest)?;
+
+                    extracted_to = Some(dest);
+                }
+            }
         }
     }
 
-    Ok(())
+    if let Some((dest, target)) = pending_links.first() {
+        bail!(
+            "Binary '{}' is a link to '{}', which wasn't found after it in the archive (try matching the target directly)",
+            dest.file_name().unwrap().to_string_lossy().bright_green(),
+            target.bright_yellow()
+        );
+    }
+
+    let mut binaries = Vec::with_capacity(files.len());
+
+    for (i, result) in extracted.iter().enumerate() {
+        let Some(path_in_archive) = result else {
+            return Err(no_match_error(&files[i].path_matcher, &paths_in_archive));
+        };
+
+        binaries.push(bin_name(&files[i], path_in_archive).to_owned());
+    }
+
+    if let Some(i) = extras_matched.iter().position(|matched| !matched) {
+        return Err(no_match_error(
+            &extra_files[i].path_matcher,
+            &paths_in_archive,
+        ));
+    }
+
+    Ok(ExtractedAsset {
+        binaries,
+        extra_files: extracted_extras,
+    })
+}
+
+/// Copy a file from an archive, enforcing the extraction limits
+///
+/// The partially-written file is removed if a limit is exceeded.
+fn copy_limited(
+    reader: &mut impl Read,
+    mut out_file: File,
+    out_path: &Path,
+    extracted_size: u64,
+) -> Result<u64> {
+    let ExtractionLimits {
+        max_file_size,
+        max_total_size,
+    } = EXTRACTION_LIMITS.get_or_init(Default::default);
+
+    let limit = (*max_file_size).min(max_total_size.saturating_sub(extracted_size));
+
+    // Read one more byte than allowed to detect files exceeding the limit
+    let copied = std::io::copy(&mut reader.take(limit.saturating_add(1)), &mut out_file)?;
+
+    if copied <= limit {
+        out_file.flush()?;
+        return Ok(copied);
+    }
+
+    drop(out_file);
+
+    // Ignore errors as the limit's error is more relevant
+    let _ = std::fs::remove_file(out_path);
+
+    if limit == *max_file_size {
+        bail!(
+            "File exceeds the maximum extraction size of {} (use {} to increase it)",
+            HumanBytes(*max_file_size).to_string().bright_magenta(),
+            "--max-extract-size".bright_cyan()
+        )
+    } else {
+        bail!(
+            "Archive exceeds the maximum total extraction size of {} (use {} to increase it)",
+            HumanBytes(*max_total_size).to_string().bright_magenta(),
+            "--max-total-extract-size".bright_cyan()
+        )
+    }
+}
+
+/// Check if a file is a Windows executable (starting with the 'MZ' signature)
+fn is_windows_executable(path: &Path) -> Result<bool> {
+    let mut signature = [0; 2];
+
+    let read = File::open(path)
+        .and_then(|mut file| file.read(&mut signature))
+        .with_context(|| format!("Failed to read binary at path '{}'", path.display()))?;
+
+    Ok(read == 2 && signature == *b"MZ")
+}
+
+/// Copy a binary which was already extracted, for links pointing to it
+fn copy_extracted_binary(source: &Path, dest: &Path) -> Result<()> {
+    std::fs::copy(source, dest).with_context(|| {
+        format!(
+            "Failed to copy extracted file from '{}' to '{}'",
+            source.display(),
+            dest.display()
+        )
+    })?;
+
+    apply_bin_perms(dest)
+}
+
+/// Number of paths from the archive to suggest when a pattern matches none of them
+static CLOSEST_PATHS_COUNT: usize = 5;
+
+/// Build the error for a pattern matching no file, listing the closest paths from the archive
+fn no_match_error(pattern: &Pattern, paths_in_archive: &[String]) -> anyhow::Error {
+    if paths_in_archive.is_empty() {
+        return anyhow!(
+            "Pattern '{}' matched none of the archive's files as it is empty",
+            pattern.to_string().bright_blue()
+        );
+    }
+
+    // Compare against the pattern's literal parts (e.g. '/bin/tool$' => '/bin/tool')
+    let source = pattern
+        .strip_case_insensitive_flag()
+        .unwrap_or(pattern.as_str())
+        .replace(['^', '$', '\\', '(', ')', '[', ']', '*', '+', '?', '|'], "")
+        .to_lowercase();
+
+    let comparator = BatchComparator::new(source.chars());
+
+    let mut closest = paths_in_archive
+        .iter()
+        .map(|path| {
+            let path_lc = path.to_lowercase();
+            let file_name = path_lc.rsplit('/').next().unwrap();
+
+            // Patterns often only target the file's name
+            let distance = comparator
+                .distance(path_lc.chars())
+                .min(comparator.distance(file_name.chars()));
+
+            (distance, path)
+        })
+        .collect::<Vec<_>>();
+
+    closest.sort_by(|(a_dist, a), (b_dist, b)| a_dist.total_cmp(b_dist).then_with(|| a.cmp(b)));
+
+    anyhow!(
+        "Pattern '{}' matched none of the archive's {} file(s), closest paths are:\n\n{}",
+        pattern.to_string().bright_blue(),
+        paths_in_archive.len(),
+        join_iter(
+            closest
+                .iter()
+                .take(CLOSEST_PATHS_COUNT)
+                .map(|(_, path)| format!("* {}", path.bright_yellow())),
+            "\n"
+        )
+    )
+}
+
+/// Name of the binary extracted from a file in an archive
+fn bin_name<'a>(file: &'a BinaryInArchive, path_in_archive: &'a str) -> &'a str {
+    match &file.copy_as {
+        Some(copy_as) => copy_as,
+        None => path_in_archive.rsplit('/').next().unwrap(),
+    }
 }
 
-fn simplify_path(path: &Path) -> String {
+/// Normalize a path from an archive, removing its first `strip_components` components
+fn simplify_path(path: &Path, strip_components: usize) -> Option<String> {
     let mut out = vec![];
 
     for component in path.components() {
@@ -191,10 +717,73 @@ fn simplify_path(path: &Path) -> String {
         }
     }
 
-    out.join("/")
+    if out.len() <= strip_components {
+        return None;
+    }
+
+    Some(out[strip_components..].join("/"))
+}
+
+/// Copy an extracted binary to its final location, replacing any existing file
+pub fn deploy_binary(extracted: &Path, dest: &Path) -> Result<()> {
+    // Remove the existing file first, as it may be in use or be a symbolic link
+    if dest.symlink_metadata().is_ok() {
+        std::fs::remove_file(dest).with_context(|| {
+            format!(
+                "Failed to remove existing binary at path '{}'",
+                dest.display()
+            )
+        })?;
+    }
+
+    std::fs::copy(extracted, dest).with_context(|| {
+        format!(
+            "Failed to copy binary from '{}' to '{}'",
+            extracted.display(),
+            dest.display()
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Create a symbolic link to a binary, replacing any existing file
+pub fn link_binary(target: &Path, link: &Path) -> Result<()> {
+    if link.symlink_metadata().is_ok() {
+        std::fs::remove_file(link).with_context(|| {
+            format!(
+                "Failed to remove existing binary at path '{}'",
+                link.display()
+            )
+        })?;
+    }
+
+    #[cfg(target_family = "unix")]
+    {
+        std::os::unix::fs::symlink(target, link).with_context(|| {
+            format!(
+                "Failed to create symbolic link at path '{}' to binary '{}'",
+                link.display(),
+                target.display()
+            )
+        })
+    }
+
+    #[cfg(not(target_family = "unix"))]
+    {
+        bail!(
+            "Cannot link binary '{}': symbolic links are not supported on this platform",
+            target.display()
+        )
+    }
 }
 
 fn apply_bin_perms(path: &Path) -> Result<()> {
+    // Windows doesn't have an executable bit
+    if target_platform().0 == System::windows {
+        return Ok(());
+    }
+
     #[cfg(target_family = "unix")]
     {
         use std::os::unix::fs::PermissionsExt;
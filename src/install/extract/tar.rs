@@ -1,9 +1,9 @@
 use std::{io::Read, path::PathBuf};
 
 use anyhow::{Context, Result};
-use tar::{Archive, Entries};
+use tar::{Archive, Entries, EntryType};
 
-use super::AssetContentIter;
+use super::{AssetContentIter, EntryKind};
 
 pub struct TarReader<R: Read> {
     archive: Archive<R>,
@@ -16,7 +16,7 @@ impl<R: Read + Unpin> TarReader<R> {
         }
     }
 
-    pub fn iter(&mut self) -> Result<TarReaderIter<R>> {
+    pub fn iter(&mut self) -> Result<TarReaderIter<'_, R>> {
         let entries = self
             .archive
             .entries()
@@ -31,15 +31,40 @@ pub struct TarReaderIter<'a, R: Read> {
 }
 
 impl<R: Read> AssetContentIter for TarReaderIter<'_, R> {
-    fn next_file(&mut self) -> Option<Result<(PathBuf, impl Read)>> {
+    fn next_file(&mut self) -> Option<Result<(PathBuf, EntryKind, impl Read)>> {
         self.entries.next().map(|result| {
             let entry = result.context("Failed to read entry from tarball archive")?;
 
             let path = entry
                 .path()
-                .context("Failed to get entry pat from tarball archive")?;
+                .context("Failed to get entry pat from tarball archive")?
+                .into_owned();
 
-            Ok((path.into_owned(), entry))
+            let kind = match entry.header().entry_type() {
+                EntryType::Directory => EntryKind::Directory,
+
+                typ @ (EntryType::Symlink | EntryType::Link) => {
+                    let target = entry
+                        .link_name()
+                        .context("Failed to get link target from tarball archive")?
+                        .context("Link in tarball archive has no target")?;
+
+                    EntryKind::Link {
+                        // Symbolic links are relative to their parent directory, hard links to the archive's root
+                        target: if target.is_absolute() {
+                            None
+                        } else if typ == EntryType::Symlink {
+                            Some(path.parent().unwrap_or(&path).join(target))
+                        } else {
+                            Some(target.into_owned())
+                        },
+                    }
+                }
+
+                _ => EntryKind::File,
+            };
+
+            Ok((path, kind, entry))
         })
     }
 }
@@ -7,7 +7,7 @@ use std::{
 use anyhow::{Context, Result};
 use zip::ZipArchive;
 
-use super::AssetContentIter;
+use super::{AssetContentIter, EntryKind};
 
 pub struct ZipReader<R: Read + Seek> {
     archive: ZipArchive<R>,
@@ -20,7 +20,7 @@ impl<R: Read + Seek> ZipReader<R> {
         })
     }
 
-    pub fn iter(&mut self) -> ZipReaderIter<R> {
+    pub fn iter(&mut self) -> ZipReaderIter<'_, R> {
         ZipReaderIter {
             files: (0..self.archive.len()),
             archive: &mut self.archive,
@@ -34,14 +34,36 @@ pub struct ZipReaderIter<'a, R: Read + Seek> {
 }
 
 impl<R: Read + Seek> AssetContentIter for ZipReaderIter<'_, R> {
-    fn next_file(&mut self) -> Option<Result<(PathBuf, impl Read)>> {
+    fn next_file(&mut self) -> Option<Result<(PathBuf, EntryKind, impl Read)>> {
         self.files.next().map(move |idx| {
-            let entry = self
+            let mut entry = self
                 .archive
                 .by_index(idx)
                 .context("Failed to get entry from ZIP archive")?;
 
-            Ok((PathBuf::from(entry.name()), entry))
+            let path = PathBuf::from(entry.name());
+
+            let kind = if entry.is_dir() {
+                EntryKind::Directory
+            } else if entry.is_symlink() {
+                // The content of symbolic links is their target
+                let mut target = String::new();
+
+                entry
+                    .read_to_string(&mut target)
+                    .context("Failed to read symbolic link's target from ZIP archive")?;
+
+                let target = PathBuf::from(target);
+
+                EntryKind::Link {
+                    target: (!target.is_absolute())
+                        .then(|| path.parent().unwrap_or(&path).join(target)),
+                }
+            } else {
+                EntryKind::File
+            };
+
+            Ok((path, kind, entry))
         })
     }
 }
@@ -3,14 +3,15 @@ use colored::Colorize;
 use tokio::task::JoinSet;
 
 use crate::{
+    http::acquire_network_slot,
     repos::ast::{DownloadSource, PackageManifest},
     resolver::ResolvedPkg,
-    sources::{AssetInfos, AssetSource},
+    sources::{github::prefetch_latest_releases, AssetInfos},
     utils::{join_fallible_ordered_set, progress_bar, ITEMS_PROGRESS_BAR_STYLE},
 };
 
-pub async fn fetch_pkgs_infos(
-    pkgs: impl ExactSizeIterator<Item = &PackageManifest>,
+pub async fn fetch_pkgs_infos<'a>(
+    pkgs: impl ExactSizeIterator<Item = (&'a PackageManifest, Option<&'a str>)>,
 ) -> Result<Vec<(PackageManifest, AssetInfos)>> {
     let mut tasks = JoinSet::new();
 
@@ -20,17 +21,28 @@ pub async fn fetch_pkgs_infos(
         "Fetching package informations...",
     );
 
-    for (i, pkg) in pkgs.enumerate() {
+    let pkgs = pkgs.collect::<Vec<_>>();
+
+    // Fetch the latest releases from GitHub in batches instead of one request per package
+    prefetch_latest_releases(pkgs.iter().filter_map(|(pkg, version)| match &pkg.source {
+        DownloadSource::GitHub(source) if version.is_none() => Some(source),
+        _ => None,
+    }))
+    .await;
+
+    for (i, (pkg, version)) in pkgs.into_iter().enumerate() {
         let pkg = (*pkg).clone();
+        let version = version.map(str::to_owned);
         let pb = pb.clone();
 
         tasks.spawn(async move {
-            let asset_infos = match &pkg.source {
-                DownloadSource::Direct(params) => params.fetch_infos().await,
-                DownloadSource::GitHub(params) => params.fetch_infos().await,
-            };
+            let _network_slot = acquire_network_slot().await;
+
+            let version = version.as_deref();
 
-            asset_infos
+            pkg.source
+                .fetch_infos(version)
+                .await
                 .with_context(|| {
                     format!(
                         "Failed to fetch informations about package {}",
@@ -51,7 +63,8 @@ pub async fn fetch_pkgs_infos(
 pub async fn fetch_resolved_pkg_infos<'a, 'b>(
     pkgs: &[ResolvedPkg<'a, 'b>],
 ) -> Result<Vec<(ResolvedPkg<'a, 'b>, AssetInfos)>> {
-    let fetched = fetch_pkgs_infos(pkgs.iter().map(|pkg| pkg.manifest)).await?;
+    let fetched =
+        fetch_pkgs_infos(pkgs.iter().map(|pkg| (pkg.manifest, pkg.pinned_version))).await?;
 
     Ok(fetched
         .into_iter()
@@ -1,7 +1,8 @@
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    path::PathBuf,
-    sync::Arc,
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::Instant,
 };
 
@@ -9,25 +10,39 @@ use anyhow::{bail, Context, Result};
 use colored::Colorize;
 use indicatif::ProgressBar;
 use jiff::Zoned;
-use log::info;
-use tokio::sync::RwLock;
+use log::{info, warn};
+use tokio::{fs, sync::RwLock};
 
 use crate::{
-    db::{data::InstalledPackage, Db},
+    db::{
+        data::{InstalledFile, InstalledFileKind, InstalledPackage, PackageBackup},
+        Db,
+    },
+    http::ensure_online,
     install::{
-        display::display_install_phases,
+        display::{
+            display_install_phases, display_install_summary, InstallSummary, PlannedInstall,
+        },
         downloader::download_assets_and,
         phases::{InstallPhases, PackagesToInstall},
     },
-    repos::ast::PackageManifest,
-    resolver::ResolvedPkg,
-    sources::{AssetInfos, AssetType},
+    repos::{
+        arch::{is_cross_target, target_platform},
+        ast::PackageManifest,
+    },
+    resolver::{sort_pkgs_by_deps, ResolvedPkg},
+    sources::{AssetInfos, ExtraFileKind},
     utils::confirm,
 };
 
 use super::{
-    extract::extract_asset,
+    backup::{backup_binaries, swap_backup},
+    checksums::sha256_file,
+    completions::shell_completion_path,
+    extract::{deploy_binary, extract_asset, link_binary, ExtractedAsset, ExtractedExtraFile},
     phases::{compute_install_phases, InstalledPackagesHandling},
+    side_by_side::{keep_version, versioned_bin_name, versioned_pkg_dir},
+    verify::run_verify_command,
 };
 
 pub async fn install_pkgs(
@@ -35,6 +50,9 @@ pub async fn install_pkgs(
     installed_pkgs_handling: InstalledPackagesHandling,
     db: Db,
     discreet: bool,
+    link: bool,
+    side_by_side: bool,
+    keep_going: bool,
 ) -> Result<()> {
     let start = Instant::now();
 
@@ -63,6 +81,9 @@ pub async fn install_pkgs(
         )
         .collect::<Vec<_>>();
 
+    // Dependencies must be installed before the packages relying on them
+    let to_install = sort_pkgs_by_deps(to_install, |(pkg, _)| pkg.manifest)?;
+
     if to_install.is_empty() && discreet {
         return Ok(());
     }
@@ -74,6 +95,19 @@ pub async fn install_pkgs(
         return Ok(());
     }
 
+    // Fail before asking for confirmation, as downloading the assets is going to fail anyway
+    ensure_online()?;
+
+    if is_cross_target() {
+        let (system, cpu_arch) = target_platform();
+
+        warn!(
+            "{} Installing binaries for platform {}, they won't be runnable on this machine",
+            "WARNING:".bright_red().bold(),
+            format!("{system}[{cpu_arch}]").bright_cyan()
+        );
+    }
+
     if to_install.iter().any(|(pkg, _)| pkg.is_dep)
         || matches!(
             installed_pkgs_handling,
@@ -106,14 +140,7 @@ pub async fn install_pkgs(
         .collect::<HashMap<_, _>>();
 
     for (pkg, asset_infos) in &to_install {
-        let binaries = match &asset_infos.typ {
-            AssetType::Binary { copy_as } => vec![copy_as.as_str()],
-            AssetType::Archive { format: _, files } => {
-                files.iter().map(|bin| bin.copy_as.as_str()).collect()
-            }
-        };
-
-        for binary in binaries {
+        for binary in asset_infos.typ.binaries() {
             match seen_bins.entry(binary) {
                 Entry::Occupied(clashing_pkg) => {
                     if pkg.manifest.name != clashing_pkg.get().name {
@@ -133,20 +160,32 @@ pub async fn install_pkgs(
         }
     }
 
+    // Binaries named after the extracted files are only known once extracted, so they are
+    // reserved during the installation to prevent two packages from exposing the same one
+    let reserved_bins = seen_bins
+        .into_iter()
+        .map(|(bin, manifest)| (bin.to_owned(), manifest.name.clone()))
+        .collect::<HashMap<_, _>>();
+
     let pkg_infos = to_install
         .iter()
-        .map(|(pkg, asset_infos)| {
+        .map(|(pkg, _)| {
             (
                 pkg.manifest.name.clone(),
                 ExtractionPkgInfo {
                     repo_name: pkg.repository.name.clone(),
                     is_dep: pkg.is_dep,
-                    binaries: match &asset_infos.typ {
-                        AssetType::Binary { copy_as } => vec![copy_as.clone()],
-                        AssetType::Archive { format: _, files } => {
-                            files.iter().map(|bin| bin.copy_as.clone()).collect()
-                        }
+                    // Keep the installation mode of already-installed packages
+                    link: match db.installed.get(&pkg.manifest.name) {
+                        Some(installed) => installed.linked_from.is_some(),
+                        None => link,
                     },
+                    // Packages installed side-by-side stay that way
+                    versioned: db
+                        .installed
+                        .get(&pkg.manifest.name)
+                        .is_some_and(|installed| installed.versioned)
+                        || (side_by_side && !pkg.is_dep),
                 },
             )
         })
@@ -154,6 +193,28 @@ pub async fn install_pkgs(
 
     let to_install_count = to_install.len();
 
+    let summary = InstallSummary {
+        planned: to_install
+            .iter()
+            .map(|(pkg, asset_infos)| PlannedInstall {
+                name: pkg.manifest.name.clone(),
+                from_version: db
+                    .installed
+                    .get(&pkg.manifest.name)
+                    .map(|installed| installed.version.clone()),
+                to_version: asset_infos.version.clone(),
+            })
+            .collect(),
+        up_to_date: phases
+            .untouched
+            .no_update_needed
+            .iter()
+            .map(|pkg| pkg.manifest.name.clone())
+            .collect(),
+    };
+
+    let finished = Arc::new(Mutex::new(HashSet::new()));
+
     let to_install = to_install
         .iter()
         .map(|(pkg, asset_infos)| (pkg.manifest.clone(), (*asset_infos).clone()))
@@ -162,10 +223,40 @@ pub async fn install_pkgs(
     let state = ExtractionState {
         pkg_infos: Arc::new(pkg_infos),
         bins_dir: db.bin_dir().to_owned(),
+        pkgs_dir: db.pkgs_dir(),
+        backups_dir: db.backups_dir(),
+        completions_dir: db.completions_dir(),
+        man_dir: db.man_dir(),
         db: Arc::new(RwLock::new(db)),
+        reserved_bins: Arc::new(Mutex::new(reserved_bins)),
+        finished: Arc::clone(&finished),
     };
 
-    let (tmp_dir, _) = download_assets_and(to_install, state, extract_and_install_binaries).await?;
+    let db = Arc::clone(&state.db);
+    let bins_dir = state.bins_dir.clone();
+
+    let result =
+        download_assets_and(to_install, keep_going, state, extract_and_install_binaries).await;
+
+    // Report what happened to each package, even if some of them failed
+    if !discreet {
+        display_install_summary(&summary, &finished.lock().unwrap());
+    }
+
+    // Binaries built for another platform aren't meant to be run from PATH
+    if !is_cross_target() {
+        let db = db.read().await;
+        let finished = finished.lock().unwrap();
+
+        let binaries = finished
+            .iter()
+            .flat_map(|name| db.installed[name].binaries.iter())
+            .collect::<Vec<_>>();
+
+        warn_shadowed_binaries(&binaries, &bins_dir);
+    }
+
+    let (tmp_dir, _) = result?;
 
     info!(
         "Successfully installed {} package(s) in {} second(s)!",
@@ -189,18 +280,68 @@ pub async fn install_pkgs(
     Ok(())
 }
 
+/// Warn about binaries which are hidden by another executable of the same name coming first in PATH
+fn warn_shadowed_binaries(binaries: &[&String], bins_dir: &Path) {
+    let Some(path) = env::var_os("PATH") else {
+        return;
+    };
+
+    let bins_dir = bins_dir
+        .canonicalize()
+        .unwrap_or_else(|_| bins_dir.to_owned());
+
+    let path_dirs = env::split_paths(&path)
+        .map(|dir| dir.canonicalize().unwrap_or(dir))
+        .collect::<Vec<_>>();
+
+    // Binaries can't be run by name anyway if the directory isn't in PATH
+    let Some(bins_dir_pos) = path_dirs.iter().position(|dir| *dir == bins_dir) else {
+        return;
+    };
+
+    for bin in binaries {
+        let Some(shadowing) = path_dirs[..bins_dir_pos]
+            .iter()
+            .map(|dir| dir.join(bin))
+            .find(|candidate| candidate.is_file())
+        else {
+            continue;
+        };
+
+        warn!(
+            "{} Binary {} is shadowed by {} which comes first in your PATH, so running it won't use the version installed by Fetchy. Move {} before {} in your PATH to fix this.",
+            "WARNING:".bright_red().bold(),
+            bin.bright_green(),
+            shadowing.display().to_string().bright_magenta(),
+            bins_dir.display().to_string().bright_magenta(),
+            shadowing.parent().unwrap().display().to_string().bright_magenta()
+        );
+    }
+}
+
 #[derive(Clone)]
 struct ExtractionState {
     pkg_infos: Arc<HashMap<String, ExtractionPkgInfo>>,
     bins_dir: PathBuf,
+    pkgs_dir: PathBuf,
+    backups_dir: PathBuf,
+    completions_dir: PathBuf,
+    man_dir: PathBuf,
     db: Arc<RwLock<Db>>,
+
+    // Package each binary exposed by installed or being-installed packages belongs to
+    reserved_bins: Arc<Mutex<HashMap<String, String>>>,
+
+    // Packages which were successfully installed
+    finished: Arc<Mutex<HashSet<String>>>,
 }
 
 #[derive(Clone)]
 struct ExtractionPkgInfo {
     repo_name: String,
     is_dep: bool,
-    binaries: Vec<String>,
+    link: bool,
+    versioned: bool,
 }
 
 async fn extract_and_install_binaries(
@@ -212,21 +353,385 @@ async fn extract_and_install_binaries(
 ) -> Result<()> {
     let pb_bis = pb.clone();
 
-    tokio::task::spawn_blocking(move || {
-        extract_asset(&asset_path, &asset_infos.typ, &state.bins_dir, pb)
+    let ExtractionPkgInfo {
+        repo_name,
+        is_dep,
+        link,
+        versioned,
+    } = state.pkg_infos.get(&manifest.name).unwrap().clone();
+
+    let previous = state.db.read().await.installed.get(&manifest.name).cloned();
+
+    let mut previous_files = previous
+        .as_ref()
+        .map(|previous| previous.installed_files.clone())
+        .unwrap_or_default();
+
+    let mut side_by_side = previous
+        .as_ref()
+        .map(|previous| previous.side_by_side.clone())
+        .unwrap_or_default();
+
+    // Directories of the previous version which are not used anymore
+    let mut stale_dirs = vec![];
+
+    // Reinstalling a kept version makes it the active one
+    if let Some(kept) = side_by_side.remove(&asset_infos.version) {
+        // Its files are overwritten when installing side-by-side, otherwise they are not used anymore
+        previous_files.extend(kept.installed_files);
+
+        if !versioned {
+            stale_dirs.extend(kept.linked_from);
+        }
+    }
+
+    // Backup of the previous version, only swapped in once the new one is deployed
+    let mut staged_backup = None;
+
+    // The binaries directory may have been removed since the database was opened
+    fs::create_dir_all(&state.bins_dir).await.with_context(|| {
+        format!(
+            "Failed to create binaries directory at: {}",
+            state.bins_dir.display()
+        )
+    })?;
+
+    // Keep a copy of the previous version before overwriting it
+    let backup = match previous {
+        None => None,
+
+        // Don't overwrite the backup when reinstalling the same version
+        Some(previous) if previous.version == asset_infos.version => previous.backup,
+
+        // Packages installed side-by-side keep their previous version instead of backing it up
+        Some(previous) if versioned => {
+            pb.set_message("keeping previous version...");
+
+            let bins_dir = state.bins_dir.clone();
+            let pkgs_dir = state.pkgs_dir.clone();
+            let kept_previous = previous.clone();
+
+            let kept = tokio::task::spawn_blocking(move || {
+                keep_version(&kept_previous, &bins_dir, &pkgs_dir)
+            })
+            .await
+            .context("Failed to wait on Tokio task")?
+            .context("Failed to keep previous version of the package")?;
+
+            previous_files.retain(|file| !kept.installed_files.contains(file));
+
+            stale_dirs.extend(previous.backup.map(|backup| backup.dir));
+            stale_dirs.extend(
+                previous
+                    .linked_from
+                    .filter(|dir| Some(dir) != kept.linked_from.as_ref()),
+            );
+
+            side_by_side.insert(kept.version.clone(), kept);
+
+            None
+        }
+
+        Some(mut previous) => {
+            pb.set_message("backing up previous version...");
+
+            let backup_dir = state.backups_dir.join(&manifest.name);
+
+            // The existing backup must be kept intact until the new version is deployed,
+            // otherwise a failed update would leave a backup which doesn't match what is installed
+            let staged_dir = state
+                .backups_dir
+                .join(format!(".{}.staging", manifest.name));
+
+            previous.backup = None;
+
+            let bins_dir = state.bins_dir.clone();
+            let backup_staged_dir = staged_dir.clone();
+
+            let previous = tokio::task::spawn_blocking(move || {
+                backup_binaries(&previous, &bins_dir, &backup_staged_dir).map(|()| previous)
+            })
+            .await
+            .context("Failed to wait on Tokio task")?
+            .context("Failed to backup previous version of the package")?;
+
+            staged_backup = Some((staged_dir, backup_dir.clone()));
+
+            Some(PackageBackup {
+                installed: Box::new(previous),
+                dir: backup_dir,
+            })
+        }
+    };
+
+    // Binaries are first extracted in a staging directory, so nothing is deployed if verification fails
+    let staging_dir = asset_path.with_extension("staging");
+
+    fs::create_dir_all(&staging_dir).await.with_context(|| {
+        format!(
+            "Failed to create staging directory at path: {}",
+            staging_dir.display()
+        )
+    })?;
+
+    let extract_dir = staging_dir.clone();
+    let extras_dir = asset_path.with_extension("extras");
+
+    let pkg_name = manifest.name.clone();
+    let typ = asset_infos.typ.clone();
+    let no_exe_suffix = manifest.no_exe_suffix;
+
+    let ExtractedAsset {
+        binaries,
+        extra_files,
+    } = tokio::task::spawn_blocking(move || {
+        extract_asset(
+            &pkg_name,
+            &asset_path,
+            &typ,
+            &extract_dir,
+            &extras_dir,
+            !no_exe_suffix,
+            pb,
+        )
     })
     .await
     .context("Failed to wait on Tokio task")?
     .context("Failed to extract downloaded asset")?;
 
-    let ExtractionPkgInfo {
-        repo_name,
-        is_dep,
-        binaries,
-    } = state.pkg_infos.get(&manifest.name).unwrap().clone();
+    // Binaries named after the extracted files can only be checked for clashes now
+    {
+        let mut reserved_bins = state.reserved_bins.lock().unwrap();
+
+        if let Some((binary, clashing_pkg)) = binaries.iter().find_map(|bin| {
+            reserved_bins
+                .get(bin)
+                .filter(|owner| **owner != manifest.name)
+                .map(|owner| (bin, owner))
+        }) {
+            bail!(
+                "Can't install package {} as it exposes the same binary {} than package {}",
+                manifest.name.bright_yellow(),
+                binary.bright_green(),
+                clashing_pkg.bright_yellow()
+            );
+        }
+
+        for bin in &binaries {
+            reserved_bins.insert(bin.clone(), manifest.name.clone());
+        }
+    }
+
+    // Binaries built for another platform can't be run to verify them
+    if let Some(verify) = manifest.verify.as_ref().filter(|_| !is_cross_target()) {
+        pb_bis.set_message("verifying...");
+
+        run_verify_command(verify, &binaries, &[&staging_dir, &state.bins_dir])
+            .await
+            .context("Verification of the installed binaries failed")?;
+    }
+
+    // In link mode, binaries are stored in a package-owned directory
+    // Packages installed side-by-side use a different directory for each version
+    let linked_from = if versioned {
+        Some(versioned_pkg_dir(
+            &state.pkgs_dir,
+            &manifest.name,
+            &asset_infos.version,
+        ))
+    } else {
+        link.then(|| state.pkgs_dir.join(&manifest.name))
+    };
+
+    if let Some(pkg_dir) = &linked_from {
+        fs::create_dir_all(pkg_dir).await.with_context(|| {
+            format!(
+                "Failed to create package directory at path: {}",
+                pkg_dir.display()
+            )
+        })?;
+    }
+
+    let bins_dir = state.bins_dir.clone();
+    let dest_dir = linked_from.clone();
+    let deployed = binaries.clone();
+    let version = asset_infos.version.clone();
+
+    let checksums = tokio::task::spawn_blocking(move || {
+        deployed.iter().try_for_each(|bin| match &dest_dir {
+            None => deploy_binary(&staging_dir.join(bin), &bins_dir.join(bin)),
+
+            Some(pkg_dir) => {
+                deploy_binary(&staging_dir.join(bin), &pkg_dir.join(bin))?;
+                link_binary(&pkg_dir.join(bin), &bins_dir.join(bin))?;
+
+                if versioned {
+                    link_binary(
+                        &pkg_dir.join(bin),
+                        &bins_dir.join(versioned_bin_name(bin, &version)),
+                    )?;
+                }
+
+                Ok(())
+            }
+        })?;
+
+        // Record the binaries' checksums to detect later modifications
+        deployed
+            .iter()
+            .map(|bin| {
+                let path = dest_dir.as_deref().unwrap_or(&bins_dir).join(bin);
+                sha256_file(&path).map(|checksum| (bin.clone(), checksum))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()
+    })
+    .await
+    .context("Failed to wait on Tokio task")?
+    .context("Failed to deploy binaries")?;
+
+    let mut installed_files = vec![];
+
+    for bin in &binaries {
+        match &linked_from {
+            None => installed_files.push(InstalledFile {
+                path: state.bins_dir.join(bin),
+                kind: InstalledFileKind::Binary,
+            }),
+
+            Some(pkg_dir) => {
+                installed_files.push(InstalledFile {
+                    path: pkg_dir.join(bin),
+                    kind: InstalledFileKind::Binary,
+                });
+
+                installed_files.push(InstalledFile {
+                    path: state.bins_dir.join(bin),
+                    kind: InstalledFileKind::Symlink,
+                });
+
+                if versioned {
+                    installed_files.push(InstalledFile {
+                        path: state
+                            .bins_dir
+                            .join(versioned_bin_name(bin, &asset_infos.version)),
+                        kind: InstalledFileKind::Symlink,
+                    });
+                }
+            }
+        }
+    }
+
+    // Package each already installed file belongs to
+    let owners = state
+        .db
+        .read()
+        .await
+        .installed
+        .values()
+        .flat_map(|installed| {
+            std::iter::once(installed)
+                .chain(installed.side_by_side.values())
+                .flat_map(|version| &version.installed_files)
+                .map(|file| (file.path.clone(), installed.manifest.name.clone()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut deployed_extra_files = vec![];
+
+    for file in extra_files {
+        let installed = installed_extra_file(&file, &state.completions_dir, &state.man_dir);
+
+        // Packages may ship completions or man pages with the same name
+        if let Some(owner) = owners
+            .get(&installed.path)
+            .filter(|owner| **owner != manifest.name)
+        {
+            warn!(
+                "{} File {} of package {} is already installed by package {}, skipping it.",
+                "WARNING:".bright_red().bold(),
+                installed.path.display().to_string().bright_magenta(),
+                manifest.name.bright_yellow(),
+                owner.bright_yellow()
+            );
+
+            continue;
+        }
+
+        // Completions installed in the shells' own directories may have been written by the user
+        // or by another tool, in which case they must be left untouched
+        if !owners.contains_key(&installed.path)
+            && !installed.path.starts_with(&state.completions_dir)
+            && fs::symlink_metadata(&installed.path).await.is_ok()
+        {
+            warn!(
+                "{} File {} of package {} already exists and wasn't installed by Fetchy, skipping it.",
+                "WARNING:".bright_red().bold(),
+                installed.path.display().to_string().bright_magenta(),
+                manifest.name.bright_yellow()
+            );
+
+            continue;
+        }
+
+        deployed_extra_files.push((file.staged, installed));
+    }
+
+    let extra_files = deployed_extra_files;
+
+    installed_files.extend(extra_files.iter().map(|(_, installed)| installed.clone()));
+
+    let deployed = installed_files.clone();
+
+    tokio::task::spawn_blocking(move || {
+        for (staged, InstalledFile { path, kind: _ }) in &extra_files {
+            std::fs::create_dir_all(path.parent().unwrap()).with_context(|| {
+                format!(
+                    "Failed to create directory at path: {}",
+                    path.parent().unwrap().display()
+                )
+            })?;
+
+            std::fs::copy(staged, path).with_context(|| {
+                format!(
+                    "Failed to copy file from '{}' to '{}'",
+                    staged.display(),
+                    path.display()
+                )
+            })?;
+        }
+
+        // Remove the files which don't exist in the new version
+        for InstalledFile { path, kind: _ } in previous_files
+            .iter()
+            .filter(|previous| !deployed.iter().any(|file| file.path == previous.path))
+        {
+            if path.symlink_metadata().is_ok() {
+                std::fs::remove_file(path).with_context(|| {
+                    format!("Failed to remove file at path: {}", path.display())
+                })?;
+            }
+        }
+
+        if let Some((staged_dir, backup_dir)) = &staged_backup {
+            swap_backup(staged_dir, backup_dir)?;
+        }
+
+        for dir in stale_dirs.iter().filter(|dir| dir.exists()) {
+            std::fs::remove_dir_all(dir).with_context(|| {
+                format!("Failed to remove directory at path: {}", dir.display())
+            })?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    })
+    .await
+    .context("Failed to wait on Tokio task")?
+    .context("Failed to deploy extra files")?;
 
     pb_bis.set_message("updating database...");
 
+    let pkg_name = manifest.name.clone();
+
     state
         .db
         .write()
@@ -239,19 +744,65 @@ async fn extract_and_install_binaries(
                 .unwrap_or(is_dep);
 
             db.installed.insert(
-                manifest.name.clone(),
+                pkg_name.clone(),
                 InstalledPackage {
                     manifest,
                     repo_name,
                     version: asset_infos.version,
+                    source_url: Some(asset_infos.url),
+                    platform: Some(target_platform()),
                     installed_as_dep,
                     binaries,
+                    checksums,
+                    installed_files,
                     at: Zoned::now(),
+                    linked_from,
+                    backup,
+                    versioned,
+                    side_by_side,
                 },
             );
         })
         .await
         .context("Failed to update database")?;
 
+    state.finished.lock().unwrap().insert(pkg_name);
+
     Ok(())
 }
+
+/// Compute where an extracted completion or man page should be installed
+fn installed_extra_file(
+    file: &ExtractedExtraFile,
+    completions_dir: &Path,
+    man_dir: &Path,
+) -> InstalledFile {
+    let ExtractedExtraFile {
+        staged: _,
+        kind,
+        name,
+    } = file;
+
+    match kind {
+        ExtraFileKind::Completion { shell } => InstalledFile {
+            path: shell_completion_path(shell, name)
+                .unwrap_or_else(|| completions_dir.join(shell).join(name)),
+            kind: InstalledFileKind::Completion,
+        },
+
+        ExtraFileKind::Man => {
+            // Man pages are named after their section (e.g. 'tool.1' or 'tool.1.gz')
+            let section = name
+                .trim_end_matches(".gz")
+                .rsplit_once('.')
+                .and_then(|(_, ext)| ext.chars().next())
+                .filter(char::is_ascii_digit)
+                .unwrap_or('1');
+
+            InstalledFile {
+                path: man_dir.join(format!("man{section}")).join(name),
+                kind: InstalledFileKind::ManPage,
+            }
+        }
+    }
+}
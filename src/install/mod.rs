@@ -1,10 +1,25 @@
+mod backup;
+mod checksums;
+mod completions;
 mod display;
 mod downloader;
 mod extract;
 mod fetch_infos;
 mod installer;
 mod phases;
+mod side_by_side;
+mod verify;
 
+pub use backup::restore_binaries;
+pub use checksums::sha256_file;
+pub use completions::{init_completions_mode, CompletionsMode};
 pub use display::display_pkg_phase;
+pub use downloader::download_assets_and;
+pub use extract::{
+    extract_asset, init_extraction_limits, init_format_detection, ExtractedAsset, ExtractionLimits,
+};
+pub use fetch_infos::fetch_pkgs_infos;
 pub use installer::install_pkgs;
 pub use phases::InstalledPackagesHandling;
+pub use side_by_side::{activate_version, keep_version};
+pub use verify::run_verify_command;
@@ -1,10 +1,15 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
 use anyhow::{bail, Result};
 use colored::Colorize;
+use log::warn;
 
 use crate::{
     db::{data::InstalledPackage, Db},
+    repos::{arch::target_platform, ast::Dependency},
     resolver::ResolvedPkg,
     sources::AssetInfos,
+    utils::join_iter,
 };
 
 use super::fetch_infos::fetch_resolved_pkg_infos;
@@ -178,5 +183,123 @@ pub async fn compute_install_phases<'a, 'b, 'c>(
         }
     }
 
+    check_version_constraints(&phases, db)?;
+
     Ok(phases)
 }
+
+/// Ensure the versions about to be installed satisfy the constraints of the packages depending on them
+///
+/// Constraints of already-installed packages are checked as well, so installing a package can't break them
+fn check_version_constraints(phases: &InstallPhases, db: &Db) -> Result<()> {
+    let PackagesToInstall {
+        missing_pkgs,
+        missing_deps,
+        needs_updating,
+        reinstall,
+    } = &phases.to_install;
+
+    let installing = missing_pkgs
+        .iter()
+        .chain(missing_deps)
+        .map(|(pkg, asset_infos)| (pkg, asset_infos))
+        .chain(
+            needs_updating
+                .iter()
+                .chain(reinstall)
+                .map(|(pkg, asset_infos, _)| (pkg, asset_infos)),
+        )
+        .collect::<Vec<_>>();
+
+    let changed = installing
+        .iter()
+        .map(|(pkg, _)| pkg.manifest.name.as_str())
+        .collect::<HashSet<_>>();
+
+    // Versions of the packages once the installation is complete
+    let mut versions = db
+        .installed
+        .values()
+        .map(|installed| (installed.manifest.name.as_str(), installed.version.as_str()))
+        .collect::<HashMap<_, _>>();
+
+    versions.extend(
+        installing
+            .iter()
+            .map(|(pkg, asset_infos)| (pkg.manifest.name.as_str(), asset_infos.version.as_str())),
+    );
+
+    let mut dependents = db
+        .installed
+        .values()
+        .map(|installed| (installed.manifest.name.as_str(), &installed.manifest))
+        .collect::<BTreeMap<_, _>>();
+
+    dependents.extend(
+        installing
+            .iter()
+            .map(|(pkg, _)| (pkg.manifest.name.as_str(), pkg.manifest)),
+    );
+
+    let mut unsatisfied = vec![];
+
+    for manifest in dependents.values() {
+        for Dependency {
+            name,
+            constraint,
+            system: _,
+        } in manifest
+            .depends_on
+            .iter()
+            .filter(|dep| dep.applies_to(target_platform().0))
+        {
+            let Some(constraint) = constraint else {
+                continue;
+            };
+
+            // Packages which are left untouched were already checked when they were installed
+            if !changed.contains(manifest.name.as_str()) && !changed.contains(name.as_str()) {
+                continue;
+            }
+
+            // Virtual packages don't have a version
+            let Some(version) = versions.get(name.as_str()) else {
+                continue;
+            };
+
+            match constraint.matches(version) {
+                Some(true) => {}
+
+                Some(false) => unsatisfied.push(format!(
+                    "* {} requires {} {} but version {} {}",
+                    manifest.name.bright_yellow(),
+                    name.bright_yellow(),
+                    constraint.to_string().bright_cyan(),
+                    version.bright_cyan(),
+                    if changed.contains(name.as_str()) {
+                        "would be installed"
+                    } else {
+                        "is installed"
+                    }
+                )),
+
+                None => warn!(
+                    "Cannot check if version {} of package {} satisfies the {} requirement of package {}",
+                    version.bright_cyan(),
+                    name.bright_yellow(),
+                    constraint.to_string().bright_cyan(),
+                    manifest.name.bright_yellow()
+                ),
+            }
+        }
+    }
+
+    if !unsatisfied.is_empty() {
+        bail!(
+            "Some version requirements are not satisfied:\n\n{}",
+            join_iter(unsatisfied.iter(), "\n")
+        );
+    }
+
+    Ok(())
+}
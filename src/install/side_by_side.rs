@@ -0,0 +1,110 @@
+//! This module is responsible for packages installed side-by-side (see `fetchy install --side-by-side`).
+//!
+//! Each version of such packages is stored in its own directory, and its binaries are exposed in
+//! the binaries directory under version-suffixed names (e.g. `node@18.0.0`). The active version
+//! additionally exposes its binaries under their unsuffixed names.
+//!
+//! Like extraction, it is a fully-blocking module.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::db::data::{InstalledFile, InstalledFileKind, InstalledPackage};
+
+use super::extract::link_binary;
+
+/// Name under which a binary of a specific version is exposed
+pub fn versioned_bin_name(bin: &str, version: &str) -> String {
+    match bin.strip_suffix(".exe") {
+        Some(stem) => format!("{stem}@{version}.exe"),
+        None => format!("{bin}@{version}"),
+    }
+}
+
+/// Directory storing the binaries of a specific version of a package
+pub fn versioned_pkg_dir(pkgs_dir: &Path, name: &str, version: &str) -> PathBuf {
+    pkgs_dir.join(format!("{name}@{version}"))
+}
+
+/// Turn an installed package into an inactive version, kept alongside the active one
+///
+/// Binaries are moved to the version's own directory if they aren't already, and exposed under
+/// their version-suffixed names. The returned package only owns these files: the unsuffixed
+/// binaries, completions and man pages are left to the active version.
+pub fn keep_version(
+    installed: &InstalledPackage,
+    bins_dir: &Path,
+    pkgs_dir: &Path,
+) -> Result<InstalledPackage> {
+    let version_dir = versioned_pkg_dir(pkgs_dir, &installed.manifest.name, &installed.version);
+    let source_dir = installed.linked_from.as_deref().unwrap_or(bins_dir);
+
+    fs::create_dir_all(&version_dir).with_context(|| {
+        format!(
+            "Failed to create package directory at path '{}'",
+            version_dir.display()
+        )
+    })?;
+
+    let mut installed_files = vec![];
+
+    for bin in &installed.binaries {
+        let stored = version_dir.join(bin);
+
+        if source_dir != version_dir {
+            fs::copy(source_dir.join(bin), &stored)
+                .with_context(|| format!("Failed to keep binary '{bin}'"))?;
+        }
+
+        let link = bins_dir.join(versioned_bin_name(bin, &installed.version));
+
+        link_binary(&stored, &link)?;
+
+        installed_files.push(InstalledFile {
+            path: stored,
+            kind: InstalledFileKind::Binary,
+        });
+
+        installed_files.push(InstalledFile {
+            path: link,
+            kind: InstalledFileKind::Symlink,
+        });
+    }
+
+    Ok(InstalledPackage {
+        installed_files,
+        linked_from: Some(version_dir),
+        backup: None,
+        side_by_side: Default::default(),
+        versioned: true,
+        ..installed.clone()
+    })
+}
+
+/// Expose the binaries of a kept version under their unsuffixed names
+///
+/// Returns the created links
+pub fn activate_version(kept: &InstalledPackage, bins_dir: &Path) -> Result<Vec<InstalledFile>> {
+    let version_dir = kept
+        .linked_from
+        .as_deref()
+        .context("Package version has no directory")?;
+
+    kept.binaries
+        .iter()
+        .map(|bin| {
+            let link = bins_dir.join(bin);
+
+            link_binary(&version_dir.join(bin), &link)?;
+
+            Ok(InstalledFile {
+                path: link,
+                kind: InstalledFileKind::Symlink,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,100 @@
+use std::{env, path::Path, process::Stdio, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use tokio::process::Command;
+
+/// Maximum duration of a verification command
+static VERIFY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Run a package's verification command for each of its binaries
+///
+/// The `{bin}` placeholder is replaced by the binary's name.
+/// If the command doesn't contain it, it is run only once.
+///
+/// Commands are run in an empty temporary directory, with an environment only made of `PATH`
+/// (starting with the provided directories) and `HOME` (pointing to the temporary directory).
+pub async fn run_verify_command(
+    template: &str,
+    binaries: &[String],
+    path_dirs: &[&Path],
+) -> Result<()> {
+    let template_args = shell_words::split(template)
+        .with_context(|| format!("Invalid verification command: {template}"))?;
+
+    let commands = if template.contains("{bin}") {
+        binaries
+            .iter()
+            .map(|bin| {
+                template_args
+                    .iter()
+                    .map(|arg| arg.replace("{bin}", bin))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    } else {
+        vec![template_args]
+    };
+
+    let path = env::join_paths(
+        path_dirs
+            .iter()
+            .map(|dir| dir.to_path_buf())
+            .chain(env::var_os("PATH").iter().flat_map(env::split_paths)),
+    )
+    .context("Failed to build PATH for the verification command")?;
+
+    let sandbox_dir =
+        tempfile::tempdir().context("Failed to create a directory for the verification command")?;
+
+    for args in commands {
+        let command = shell_words::join(&args);
+
+        let (program, args) = args
+            .split_first()
+            .context("Verification command is empty")?;
+
+        let mut cmd = Command::new(program);
+
+        cmd.args(args)
+            .env_clear()
+            .env("PATH", &path)
+            .env("HOME", sandbox_dir.path())
+            .current_dir(sandbox_dir.path())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        // Most programs can't start on Windows without this variable
+        if let Some(system_root) = env::var_os("SYSTEMROOT") {
+            cmd.env("SYSTEMROOT", system_root);
+        }
+
+        let child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to run verification command: {command}"))?;
+
+        let output = tokio::time::timeout(VERIFY_TIMEOUT, child.wait_with_output())
+            .await
+            .with_context(|| {
+                format!(
+                    "Verification command timed out after {} seconds: {command}",
+                    VERIFY_TIMEOUT.as_secs()
+                )
+            })?
+            .with_context(|| format!("Failed to wait on verification command: {command}"))?;
+
+        if !output.status.success() {
+            bail!(
+                "Verification command {} failed ({}):\n\n{}{}",
+                command.bright_cyan(),
+                output.status,
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+//! Fetchy's core, allowing other programs to manage packages programmatically
+//!
+//! Questions asked to the user can be answered by a custom [`utils::Prompter`], progress bars can be
+//! disabled with [`utils::disable_progress_bars`] (their messages are then emitted through the `log` crate).
+//!
+//! Global settings all have working defaults, which can be overridden once before performing any operation:
+//! [`http::init_http_client`], [`install::init_extraction_limits`], [`install::init_format_detection`],
+//! [`install::init_completions_mode`], [`repos::arch::init_target_platform`],
+//! [`sources::github::init_github_token`] and [`sources::github::init_github_cache`] (responses are not cached by default).
+
+#![forbid(unsafe_code)]
+#![forbid(unused_must_use)]
+#![warn(unused_crate_dependencies)]
+
+// Bundling a vendored version of OpenSSL to avoid cross-platform compilation problems
+// And avoid requiring OpenSSL on the client machine
+use openssl_sys as _;
+
+// Only used by the command-line interface
+use clap as _;
+use toml as _;
+
+pub mod db;
+pub mod fetch_repos;
+pub mod http;
+pub mod install;
+pub mod repos;
+pub mod resolver;
+pub mod sources;
+pub mod utils;
+pub mod validator;
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use fetchy::{
+    db::data::InstalledPackage,
+    repos::ast::{DownloadSource, Repository},
+    resolver::ResolvedPkg,
+};
+
+/// Stable list of installed packages, used to reproduce an installation on another machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LockedPackage {
+    pub name: String,
+    pub repo_name: String,
+    pub version: String,
+    pub source: DownloadSource,
+    pub installed_as_dep: bool,
+}
+
+impl Lockfile {
+    pub fn from_installed<'a>(installed: impl Iterator<Item = &'a InstalledPackage>) -> Self {
+        Self {
+            packages: installed
+                .map(|installed| LockedPackage {
+                    name: installed.manifest.name.clone(),
+                    repo_name: installed.repo_name.clone(),
+                    version: installed.version.clone(),
+                    source: installed.manifest.source.clone(),
+                    installed_as_dep: installed.installed_as_dep,
+                })
+                .collect(),
+        }
+    }
+
+    /// Install packages from the source they were exported with, which their repository may have changed since
+    pub fn apply_sources(&self, repos: &mut BTreeMap<String, Repository>) {
+        for locked in &self.packages {
+            let Some(manifest) = repos
+                .get_mut(&locked.repo_name)
+                .and_then(|repo| repo.packages.get_mut(&locked.name))
+            else {
+                continue;
+            };
+
+            // Sources contain patterns which can't be compared directly
+            if serde_json::to_value(&manifest.source).ok()
+                != serde_json::to_value(&locked.source).ok()
+            {
+                warn!(
+                    "Source of package {} changed in repository {} since it was exported, using the exported one",
+                    locked.name.bright_yellow(),
+                    locked.repo_name.bright_blue()
+                );
+
+                manifest.source = locked.source.clone();
+            }
+        }
+    }
+
+    pub fn resolve<'a>(
+        &'a self,
+        repos: &'a BTreeMap<String, Repository>,
+    ) -> Result<Vec<ResolvedPkg<'a, 'a>>> {
+        self.packages
+            .iter()
+            .map(|locked| {
+                let repository = repos.get(&locked.repo_name).with_context(|| {
+                    format!(
+                        "Package {} comes from repository {} which is not registered",
+                        locked.name.bright_yellow(),
+                        locked.repo_name.bright_blue()
+                    )
+                })?;
+
+                let manifest = repository.packages.get(&locked.name).with_context(|| {
+                    format!(
+                        "Package {} was not found in repository {}",
+                        locked.name.bright_yellow(),
+                        locked.repo_name.bright_blue()
+                    )
+                })?;
+
+                Ok(ResolvedPkg {
+                    manifest,
+                    repository,
+                    is_dep: locked.installed_as_dep,
+                    pinned_version: Some(&locked.version),
+                })
+            })
+            .collect()
+    }
+}
@@ -1,18 +1,43 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::{LazyLock, Mutex},
+};
+
+use anyhow::{Context, Result};
 use colored::Colorize;
-use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use jiff::Zoned;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use regex::Regex;
+
+static ANSI_ESCAPE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
 
 pub struct Logger {
     level: LevelFilter,
+    file: Option<Mutex<File>>,
 }
 
 impl Logger {
     pub fn new(level: LevelFilter) -> Self {
-        Self { level }
+        Self { level, file: None }
     }
 
-    pub fn init(self) -> Result<(), SetLoggerError> {
+    /// Install the logger, optionally appending all messages to a file as well
+    pub fn init(mut self, log_file: Option<&Path>) -> Result<()> {
+        if let Some(path) = log_file {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file at path: {}", path.display()))?;
+
+            self.file = Some(Mutex::new(file));
+        }
+
         log::set_max_level(self.level);
-        log::set_boxed_logger(Box::new(self))
+        log::set_boxed_logger(Box::new(self)).context("Failed to set up the logger")
     }
 }
 
@@ -28,6 +53,23 @@ impl Log for Logger {
 
         let msg = record.args().to_string();
 
+        if let Some(file) = &self.file {
+            // Messages may contain colored parts
+            let uncolored = ANSI_ESCAPE_REGEX.replace_all(&msg, "");
+
+            let mut file = file.lock().unwrap();
+
+            for line in uncolored.lines() {
+                // Failing to write logs shouldn't make the whole program fail
+                let _ = writeln!(
+                    file,
+                    "{} [{}] {line}",
+                    Zoned::now().strftime("%F %T"),
+                    record.level()
+                );
+            }
+        }
+
         let colored = match record.level() {
             Level::Error => msg.bright_red(),
             Level::Warn => msg.bright_yellow(),
@@ -39,5 +81,9 @@ impl Log for Logger {
         eprintln!("{colored}");
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
 }
@@ -2,60 +2,133 @@
 
 #![forbid(unsafe_code)]
 #![forbid(unused_must_use)]
-#![warn(unused_crate_dependencies)]
-// TODO: remove nightly feature
-#![feature(result_flattening)]
 
 use std::{
-    collections::{BTreeMap, BTreeSet, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
+    env,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
     process::ExitCode,
+    time::Duration,
 };
 
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser as _;
-use colored::Colorize;
-use comfy_table::{presets, Attribute, Cell, Color, ContentArrangement, Table};
-use log::{error, info, warn};
+use colored::{ColoredString, Colorize};
+use comfy_table::{presets, Attribute, Cell, Color, ContentArrangement};
+use indicatif::HumanBytes;
+use jiff::{SignedDuration, Timestamp, Zoned};
+use log::{error, info, warn, LevelFilter};
+use parsy::Parser as _;
 use rapidfuzz::distance::jaro_winkler::BatchComparator;
 use tokio::fs;
 
-// Bundling a vendored version of OpenSSL to avoid cross-platform compilation problems
-// And avoid requiring OpenSSL on the client machine
-use openssl_sys as _;
-
-use self::{
-    args::{Action, Args},
-    db::{data::SourcedRepository, Db},
+use fetchy::{
+    db::{
+        data::{AppData, InstalledFile, InstalledFileKind, InstalledPackage, SourcedRepository},
+        get_data_dir, Db,
+    },
     fetch_repos::{fetch_repositories, fetch_repository, RepositoryLocation, RepositorySource},
-    install::{display_pkg_phase, install_pkgs, InstalledPackagesHandling},
-    logger::Logger,
-    repos::ast::PackageManifest,
+    http::{init_http_client, redact_credentials, HttpClientOptions},
+    install::{
+        activate_version, display_pkg_phase, fetch_pkgs_infos, init_completions_mode,
+        init_extraction_limits, init_format_detection, install_pkgs, keep_version,
+        restore_binaries, run_verify_command, sha256_file, CompletionsMode, ExtractionLimits,
+        InstalledPackagesHandling,
+    },
+    repos::{
+        arch::{init_target_platform, target_platform, CPU_ARCH, SYSTEM},
+        ast::{DownloadSource, PackageManifest},
+        emitter::emit_repository,
+        parser::repository,
+    },
     resolver::{
         build_pkgs_reverse_deps_map, compute_no_longer_needed_deps, refresh_pkg,
-        resolve_installed_pkgs, resolve_installed_pkgs_by_name, resolve_pkgs_by_name_with_deps,
+        resolve_installed_pkgs, resolve_installed_pkgs_by_name, resolve_pkg_by_name,
+        resolve_pkgs_by_name_with_deps, resolve_pkgs_with_deps, satisfying_pkgs, ResolvedPkg,
+    },
+    sources::github::{
+        github_api_url, github_token_source, init_github_api_url, init_github_cache,
+        init_github_token, GitHubTokenSources,
+    },
+    sources::{
+        direct::DirectSource, github::GithubSource, http_index::HttpIndexSource, pattern::Pattern,
+        AssetType,
     },
-    utils::{confirm, join_iter},
+    utils::{
+        confirm, disable_colors, disable_progress_bars, join_iter, new_table, relative_time,
+        set_skip_confirmations,
+    },
+};
+
+use self::{
+    args::{
+        Action, Args, ConfigAction, DateFormat, PathAction, PkgSortBy, RepoFormat, SearchField,
+        Shell,
+    },
+    config::Config,
+    diagnostics::{diagnose_repository, Diagnostic, DiagnosticSpan},
+    doctor::doctor,
+    lockfile::Lockfile,
+    logger::Logger,
+    self_update::self_update,
 };
 
 mod args;
-mod db;
-mod fetch_repos;
-mod install;
+mod config;
+mod diagnostics;
+mod doctor;
+mod lockfile;
 mod logger;
-mod repos;
-mod resolver;
-mod sources;
-mod utils;
-mod validator;
+mod self_update;
 
 #[tokio::main]
 async fn main() -> ExitCode {
-    let Args { action, verbosity } = Args::parse();
+    let args = Args::parse();
+
+    // Load the configuration before setting up the logger as it may provide the verbosity level
+    let config = match get_data_dir(args.root.as_deref()) {
+        Ok(data_dir) => Config::load(&data_dir)
+            .await
+            .map(|config| (data_dir, config)),
+        Err(err) => Err(err),
+    };
+
+    let verbosity = args
+        .verbosity
+        .or_else(|| {
+            config
+                .as_ref()
+                .ok()
+                .and_then(|(_, config)| config.verbosity)
+        })
+        .unwrap_or(LevelFilter::Info);
+
+    // Styling is only useful when writing to a terminal
+    if args.no_color
+        || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+        || !io::stdout().is_terminal()
+        || !io::stderr().is_terminal()
+    {
+        disable_colors();
+    }
+
+    if args.no_progress || !io::stderr().is_terminal() {
+        disable_progress_bars();
+    }
 
     // Set up the logger
-    Logger::new(verbosity).init().unwrap();
+    if let Err(err) = Logger::new(verbosity).init(args.log_file.as_deref()) {
+        eprintln!("{}", format!("{err:?}").bright_red());
+        return ExitCode::FAILURE;
+    }
 
-    match inner(action).await {
+    let result = match config {
+        Ok((data_dir, config)) => inner(args, data_dir, config).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
         Ok(()) => ExitCode::SUCCESS,
 
         Err(err) => {
@@ -65,51 +138,390 @@ async fn main() -> ExitCode {
     }
 }
 
-async fn inner(action: Action) -> Result<()> {
-    let data_dir = dirs::state_dir()
-        .or_else(dirs::data_local_dir)
-        .context("Failed to get path to the user's app state directory")?
-        .join("fetchy");
+async fn inner(args: Args, data_dir: PathBuf, config: Config) -> Result<()> {
+    let Args {
+        action,
+        verbosity: _,
+        proxy,
+        timeout,
+        retries,
+        concurrency,
+        max_rate,
+        bin_dir,
+        root,
+        yes,
+        log_file: _,
+        no_color: _,
+        no_progress: _,
+        refresh,
+        offline,
+        max_extract_size,
+        max_total_extract_size,
+        no_format_detection,
+        completions,
+        os,
+        arch,
+        keep_going,
+        github_token,
+    } = args;
+
+    // Command-line flags take precedence over the configuration file
+    let Config {
+        bin_dir: config_bin_dir,
+        concurrency: config_concurrency,
+        max_rate: config_max_rate,
+        github_api_url: config_github_api_url,
+        github_token: config_github_token,
+        github_tokens: config_github_tokens,
+        github_token_helper,
+        proxy: config_proxy,
+        timeout: config_timeout,
+        retries: config_retries,
+        verbosity: _,
+        skip_confirmations,
+        link_binaries,
+        offline: config_offline,
+        stale_repos_days,
+        auto_refresh: config_auto_refresh,
+        max_extract_size: config_max_extract_size,
+        max_total_extract_size: config_max_total_extract_size,
+        format_detection,
+        completions: config_completions,
+    } = config;
+
+    // Everything is kept inside the root directory when there is one
+    let bin_dir = if root.is_some() {
+        None
+    } else {
+        bin_dir.or(config_bin_dir)
+    };
+
+    let bin_dir = match bin_dir {
+        Some(bin_dir) => std::path::absolute(&bin_dir).with_context(|| {
+            format!(
+                "Failed to get absolute path of binaries directory: {}",
+                bin_dir.display()
+            )
+        })?,
 
-    let bin_dir = data_dir.join("bin");
+        None => data_dir.join("bin"),
+    };
 
     // Short-circuit before opening (and parsing) the database to make things quicker
     // This is especially important given that this action may be called on each user shell's startup
-    if matches!(action, Action::BinPath) {
-        println!("{}", bin_dir.display());
+    match action {
+        Action::BinPath => {
+            println!("{}", bin_dir.display());
+            return Ok(());
+        }
+
+        Action::Path {
+            action: PathAction::Export { shell },
+        } => return path_export(shell, root.as_deref()),
+
+        Action::Config {
+            action: ConfigAction::Path,
+        } => {
+            println!("{}", Config::path(&data_dir).display());
+            return Ok(());
+        }
+
+        // Doesn't require the database, which allows converting repositories while another instance is running
+        Action::ConvertRepo { path, to } => return convert_repo(path, to).await,
+
+        Action::ValidateRepo {
+            path,
+            json_repo,
+            json,
+        } => return validate_repo(path, json_repo, json).await,
+
+        _ => {}
+    }
+
+    let proxy = proxy.or(config_proxy);
+    let timeout = timeout.or(config_timeout).unwrap_or(30);
+    let retries = retries.or(config_retries).unwrap_or(3);
+    let concurrency = concurrency.or(config_concurrency);
+    let max_rate = max_rate.or(config_max_rate);
+    let offline = offline || config_offline.unwrap_or(false);
+    let skip_confirmations = yes || skip_confirmations.unwrap_or(false);
+    let max_extract_size = max_extract_size.or(config_max_extract_size).unwrap_or(512);
+    let max_total_extract_size = max_total_extract_size
+        .or(config_max_total_extract_size)
+        .unwrap_or(2048);
+    let format_detection = !no_format_detection && format_detection.unwrap_or(true);
+
+    // Like binaries, completions stay inside the root directory when there is one
+    let completions = if root.is_some() {
+        CompletionsMode::Off
+    } else {
+        completions
+            .or(config_completions)
+            .unwrap_or(CompletionsMode::Off)
+    };
+
+    init_http_client(HttpClientOptions {
+        proxy: proxy.clone(),
+        timeout: Duration::from_secs(timeout),
+        retries,
+        concurrency,
+        max_rate,
+        offline,
+    })?;
+
+    if let Some(github_api_url) = &config_github_api_url {
+        init_github_api_url(github_api_url)?;
+    }
+
+    init_github_token(GitHubTokenSources {
+        from_flag: github_token,
+        from_config: config_github_token,
+        per_host: config_github_tokens.unwrap_or_default(),
+        helper: github_token_helper,
+    });
+    init_github_cache(data_dir.join("cache").join("github"), refresh);
+
+    set_skip_confirmations(skip_confirmations);
+
+    // Limits are provided in megabytes, huge values meaning there is no limit
+    init_extraction_limits(ExtractionLimits {
+        max_file_size: max_extract_size.saturating_mul(1024 * 1024),
+        max_total_size: max_total_extract_size.saturating_mul(1024 * 1024),
+    });
+
+    init_format_detection(format_detection);
+    init_completions_mode(completions);
+
+    // Doesn't require the database either, but requires network access
+    if let Action::SelfUpdate = action {
+        return self_update().await;
+    }
+
+    // Self-updating always targets the current platform
+    init_target_platform(os, arch);
+
+    let link_binaries = link_binaries.unwrap_or(false);
+    let stale_repos_days = stale_repos_days.unwrap_or(7);
+
+    // Doesn't require the database, so it works even if the database is broken
+    if let Action::Env = action {
+        let (target_system, target_cpu_arch) = target_platform();
+        let config_path = Config::path(&data_dir);
+
+        let entries = [
+            ("Fetchy version", env!("CARGO_PKG_VERSION").to_owned()),
+            ("Platform", format!("{SYSTEM}[{CPU_ARCH}]")),
+            (
+                "Target platform",
+                format!("{target_system}[{target_cpu_arch}]"),
+            ),
+            ("Data directory", data_dir.display().to_string()),
+            ("Binaries directory", bin_dir.display().to_string()),
+            (
+                "Configuration file",
+                format!(
+                    "{} ({})",
+                    config_path.display(),
+                    if config_path.is_file() {
+                        "found"
+                    } else {
+                        "not found"
+                    }
+                ),
+            ),
+            (
+                "Proxy",
+                proxy.map_or_else(
+                    || "from environment variables".to_owned(),
+                    |proxy| redact_credentials(&proxy),
+                ),
+            ),
+            ("Timeout", format!("{timeout}s")),
+            ("Retries", retries.to_string()),
+            (
+                "Concurrency",
+                concurrency.map_or("unlimited".to_owned(), |concurrency| {
+                    concurrency.to_string()
+                }),
+            ),
+            (
+                "Maximum download rate",
+                max_rate.map_or("unlimited".to_owned(), |max_rate| {
+                    format!("{}/s", HumanBytes(max_rate))
+                }),
+            ),
+            ("Offline", offline.to_string()),
+            ("Skip confirmations", skip_confirmations.to_string()),
+            ("Link binaries", link_binaries.to_string()),
+            ("Stale repositories days", stale_repos_days.to_string()),
+            (
+                "Auto refresh",
+                config_auto_refresh.unwrap_or(false).to_string(),
+            ),
+            ("Maximum extract size", format!("{max_extract_size} MB")),
+            (
+                "Maximum total extract size",
+                format!("{max_total_extract_size} MB"),
+            ),
+            ("Format detection", format_detection.to_string()),
+            ("Completions", completions.to_string()),
+            ("GitHub API", github_api_url().to_owned()),
+            (
+                "GitHub token",
+                match github_token_source() {
+                    Some(source) => format!("provided (from {source})"),
+                    None => "none".to_owned(),
+                },
+            ),
+        ];
+
+        let width = entries.iter().map(|(label, _)| label.len()).max().unwrap();
+
+        for (label, value) in entries {
+            println!("{:width$}  {value}", format!("{label}:"), width = width + 1);
+        }
+
         return Ok(());
     }
 
-    let mut db = Db::open_data_dir(data_dir, bin_dir).await?;
+    // Doesn't modify the database, so it doesn't wait for another instance to release the lock
+    if let Action::Update {
+        names,
+        verify_only: true,
+    } = action
+    {
+        let data = Db::read_data_dir(&data_dir, &bin_dir).await?;
+        return verify_installed_pkgs(&data, &bin_dir, &names).await;
+    }
 
-    let repos = db
-        .repositories
-        .iter()
-        .map(|(name, repo)| (name.clone(), repo.content.clone()))
-        .collect::<BTreeMap<_, _>>();
+    let mut db = Db::open_data_dir(data_dir.clone(), bin_dir).await?;
 
     match action {
         Action::Install {
             names,
             check_updates,
             discreet,
+            link,
+            with_optional,
+            repo,
+            auto_refresh,
+            no_deprecated,
+            side_by_side,
+            pin,
+            from_file,
         } => {
-            let pkgs = resolve_pkgs_by_name_with_deps(names.as_slice(), &repos)?;
+            let listed = match &from_file {
+                Some(path) => read_pkgs_list(path).await?,
+                None => vec![],
+            };
+
+            let mut seen = HashSet::new();
+
+            let names = names
+                .iter()
+                .chain(listed.iter().map(|(_, name)| name))
+                .filter(|name| seen.insert(name.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if pin.is_some() && names.len() > 1 {
+                bail!("A version can only be pinned when installing a single package");
+            }
+
+            let stale = stale_repositories(&db, stale_repos_days);
+
+            if !stale.is_empty() {
+                if auto_refresh || config_auto_refresh.unwrap_or(false) {
+                    info!("Updating outdated repositories...");
+                    for (name, update) in update_repositories(&mut db, &stale).await? {
+                        if let RepositoryUpdate::Failed(err) = update {
+                            return Err(err.context(format!(
+                                "Failed to update repository {}",
+                                name.bright_blue()
+                            )));
+                        }
+                    }
+                } else {
+                    warn_stale_repositories(&db, &stale, true);
+                }
+            }
+
+            let repos = db.cloned_repositories();
+            let priorities = db.repositories_priorities();
+
+            // Report the location of packages which can't be found in the list
+            if let Some(path) = &from_file {
+                for (line, name) in &listed {
+                    resolve_pkg_by_name(name, &repos, &priorities, repo.as_deref()).with_context(
+                        || format!("Invalid package at line {line} of file {}", path.display()),
+                    )?;
+                }
+            }
+
+            let pkgs = resolve_pkgs_by_name_with_deps(
+                names.as_slice(),
+                &repos,
+                &priorities,
+                repo.as_deref(),
+                with_optional,
+                &db.installed,
+                target_platform().0,
+            )
+            .await?;
+
+            let pkgs = pkgs
+                .into_iter()
+                .map(|pkg| ResolvedPkg {
+                    pinned_version: if pkg.is_dep { None } else { pin.as_deref() },
+                    ..pkg
+                })
+                .collect::<Vec<_>>();
+
+            let deprecated = pkgs
+                .iter()
+                .filter(|pkg| !db.installed.contains_key(&pkg.manifest.name))
+                .filter_map(|pkg| {
+                    pkg.manifest
+                        .deprecated
+                        .as_ref()
+                        .map(|message| (&pkg.manifest.name, message))
+                })
+                .collect::<Vec<_>>();
+
+            for (name, message) in &deprecated {
+                warn!(
+                    "{} Package {} is deprecated: {message}",
+                    "WARNING:".bright_red().bold(),
+                    name.bright_yellow()
+                );
+            }
+
+            if no_deprecated && !deprecated.is_empty() {
+                bail!("Refusing to install deprecated packages");
+            }
 
             install_pkgs(
                 pkgs,
                 if check_updates {
                     InstalledPackagesHandling::CheckUpdates
+                } else if side_by_side {
+                    // Install the requested version even if another one is already installed
+                    InstalledPackagesHandling::Update
                 } else {
                     InstalledPackagesHandling::Ignore
                 },
                 db,
                 discreet,
+                link || link_binaries,
+                side_by_side,
+                keep_going,
             )
             .await?;
         }
 
         Action::Reinstall { names } => {
+            let repos = db.cloned_repositories();
+
             let pkgs = resolve_installed_pkgs_by_name(&names, &db.installed, &repos)?;
 
             let pkgs = pkgs
@@ -118,10 +530,24 @@ async fn inner(action: Action) -> Result<()> {
                 .map(refresh_pkg)
                 .collect::<Result<Vec<_>, _>>()?;
 
-            install_pkgs(pkgs, InstalledPackagesHandling::Reinstall, db, false).await?;
+            install_pkgs(
+                pkgs,
+                InstalledPackagesHandling::Reinstall,
+                db,
+                false,
+                link_binaries,
+                false,
+                keep_going,
+            )
+            .await?;
         }
 
-        Action::Update { names } => {
+        Action::Update {
+            names,
+            verify_only: false,
+        } => {
+            let repos = db.cloned_repositories();
+
             let pkgs = if !names.is_empty() {
                 resolve_installed_pkgs_by_name(&names, &db.installed, &repos)?
             } else {
@@ -134,10 +560,273 @@ async fn inner(action: Action) -> Result<()> {
                 .map(refresh_pkg)
                 .collect::<Result<Vec<_>, _>>()?;
 
-            install_pkgs(pkgs, InstalledPackagesHandling::Update, db, false).await?;
+            install_pkgs(
+                pkgs,
+                InstalledPackagesHandling::Update,
+                db,
+                false,
+                link_binaries,
+                false,
+                keep_going,
+            )
+            .await?;
+        }
+
+        Action::Rollback { name } => {
+            let installed = db
+                .installed
+                .get(&name)
+                .with_context(|| format!("Package {} is not installed", name.bright_yellow()))?;
+
+            let backup = installed.backup.clone().with_context(|| {
+                format!(
+                    "No previous version of package {} is available",
+                    name.bright_yellow()
+                )
+            })?;
+
+            warn!(
+                "Do you want to roll back package {} from version {} to version {}?\n",
+                name.bright_yellow(),
+                installed.version.bright_cyan(),
+                backup.installed.version.bright_cyan()
+            );
+
+            if !confirm().await? {
+                return Ok(());
+            }
+
+            // Remove files which don't exist in the previous version
+            // Completions and man pages are not backed up, so the ones which still exist are kept
+            for InstalledFile { path, kind: _ } in installed.installed_files.iter().filter(|file| {
+                !backup
+                    .installed
+                    .installed_files
+                    .iter()
+                    .any(|previous| previous.path == file.path)
+            }) {
+                if path.symlink_metadata().is_ok() {
+                    fs::remove_file(path).await.with_context(|| {
+                        format!(
+                            "Failed to remove file at path: {}",
+                            path.to_string_lossy().bright_magenta()
+                        )
+                    })?;
+                }
+            }
+
+            let bin_dir = db.bin_dir().to_owned();
+
+            let restored = backup.clone();
+
+            tokio::task::spawn_blocking(move || restore_binaries(&restored, &bin_dir))
+                .await
+                .context("Failed to wait on Tokio task")?
+                .context("Failed to restore previous version of the package")?;
+
+            let version = backup.installed.version.clone();
+
+            // Whether the package was installed as a dependency and its inactive versions don't depend on the backup
+            let restored = InstalledPackage {
+                installed_as_dep: installed.installed_as_dep,
+                side_by_side: installed.side_by_side.clone(),
+                ..*backup.installed
+            };
+
+            db.update(|db| {
+                db.installed.insert(name.clone(), restored);
+            })
+            .await?;
+
+            info!(
+                "Successfully rolled back package {} to version {}!",
+                name.bright_yellow(),
+                version.bright_cyan()
+            );
+        }
+
+        Action::Use { name, version } => {
+            let installed = db
+                .installed
+                .get(&name)
+                .with_context(|| format!("Package {} is not installed", name.bright_yellow()))?;
+
+            if installed.version == version {
+                info!(
+                    "Version {} of package {} is already in use",
+                    version.bright_cyan(),
+                    name.bright_yellow()
+                );
+
+                return Ok(());
+            }
+
+            let target = installed.side_by_side.get(&version).with_context(|| {
+                if installed.side_by_side.is_empty() {
+                    format!(
+                        "Package {} has no other version installed (see {})",
+                        name.bright_yellow(),
+                        "fetchy install --side-by-side".bright_cyan()
+                    )
+                } else {
+                    format!(
+                        "Version {} of package {} is not installed, available versions are: {}",
+                        version.bright_cyan(),
+                        name.bright_yellow(),
+                        join_iter(
+                            installed
+                                .side_by_side
+                                .keys()
+                                .map(|version| version.bright_cyan()),
+                            ", "
+                        )
+                    )
+                }
+            })?;
+
+            let current = installed.clone();
+            let target = target.clone();
+            let bin_dir = db.bin_dir().to_owned();
+            let pkgs_dir = db.pkgs_dir();
+
+            let (kept, activated) = tokio::task::spawn_blocking(move || {
+                let kept = keep_version(&current, &bin_dir, &pkgs_dir)?;
+
+                // Remove the unsuffixed binaries of the current version, which may not exist in the target one
+                for bin in &current.binaries {
+                    let path = bin_dir.join(bin);
+
+                    if path.symlink_metadata().is_ok() {
+                        std::fs::remove_file(&path).with_context(|| {
+                            format!("Failed to remove file at path: {}", path.display())
+                        })?;
+                    }
+                }
+
+                let activated = activate_version(&target, &bin_dir)?;
+
+                Ok::<_, anyhow::Error>((kept, activated))
+            })
+            .await
+            .context("Failed to wait on Tokio task")?
+            .context("Failed to switch package version")?;
+
+            db.update(|db| {
+                let current = db.installed.remove(&name).unwrap();
+
+                let mut side_by_side = current.side_by_side;
+                let mut target = side_by_side.remove(&version).unwrap();
+
+                // Completions and man pages are not versioned, so they are kept as-is
+                let extra_files = current.installed_files.into_iter().filter(|file| {
+                    matches!(
+                        file.kind,
+                        InstalledFileKind::Completion | InstalledFileKind::ManPage
+                    )
+                });
+
+                target.installed_files.extend(activated);
+                target.installed_files.extend(extra_files);
+                target.installed_as_dep = current.installed_as_dep;
+
+                side_by_side.insert(kept.version.clone(), kept);
+                target.side_by_side = side_by_side;
+
+                db.installed.insert(name.clone(), target);
+            })
+            .await?;
+
+            info!(
+                "Now using version {} of package {}!",
+                version.bright_cyan(),
+                name.bright_yellow()
+            );
+        }
+
+        Action::PruneVersions { names } => {
+            for name in &names {
+                if !db.installed.contains_key(name) {
+                    bail!("Package {} is not installed", name.bright_yellow());
+                }
+            }
+
+            let to_prune = db
+                .installed
+                .values()
+                .filter(|installed| names.is_empty() || names.contains(&installed.manifest.name))
+                .flat_map(|installed| installed.side_by_side.values())
+                .collect::<Vec<_>>();
+
+            if to_prune.is_empty() {
+                info!("No inactive version to remove!");
+                return Ok(());
+            }
+
+            info!(
+                "The following inactive versions will be removed:\n\n{}\n",
+                join_iter(
+                    to_prune.iter().map(|kept| format!(
+                        "* {} {}",
+                        kept.manifest.name.bright_yellow(),
+                        kept.version.bright_cyan()
+                    )),
+                    "\n"
+                )
+            );
+
+            warn!(
+                "Do you want to remove {} version(s)?\n",
+                to_prune.len().to_string().bright_red()
+            );
+
+            if !confirm().await? {
+                return Ok(());
+            }
+
+            for kept in &to_prune {
+                for file in &kept.installed_files {
+                    if file.path.symlink_metadata().is_ok() {
+                        fs::remove_file(&file.path).await.with_context(|| {
+                            format!(
+                                "Failed to remove file from package {} (at path: {})",
+                                kept.manifest.name.bright_yellow(),
+                                file.path.to_string_lossy().bright_magenta()
+                            )
+                        })?;
+                    }
+                }
+
+                if let Some(version_dir) = kept.linked_from.as_ref().filter(|dir| dir.exists()) {
+                    fs::remove_dir_all(version_dir).await.with_context(|| {
+                        format!(
+                            "Failed to remove directory of package {} (at path: {})",
+                            kept.manifest.name.bright_yellow(),
+                            version_dir.to_string_lossy().bright_magenta()
+                        )
+                    })?;
+                }
+            }
+
+            let count = to_prune.len();
+
+            db.update(|db| {
+                for installed in db.installed.values_mut() {
+                    if names.is_empty() || names.contains(&installed.manifest.name) {
+                        installed.side_by_side.clear();
+                    }
+                }
+            })
+            .await?;
+
+            info!(
+                "Successfully removed {} inactive versions.",
+                count.to_string().bright_yellow()
+            );
         }
 
         Action::Uninstall { names, deps } => {
+            let repos = db.cloned_repositories();
+
             let installed = resolve_installed_pkgs(db.installed.values(), &repos)?;
 
             let reverse_deps_map = build_pkgs_reverse_deps_map(
@@ -147,13 +836,37 @@ async fn inner(action: Action) -> Result<()> {
             let to_uninstall = resolve_installed_pkgs_by_name(&names, &db.installed, &repos)?;
             let to_uninstall_names = HashSet::from_iter(names.iter().map(String::as_str));
 
+            // Packages which will still be installed afterwards
+            let remaining = installed
+                .iter()
+                .map(|(resolved, _)| resolved.manifest)
+                .filter(|manifest| !to_uninstall_names.contains(manifest.name.as_str()))
+                .collect::<Vec<_>>();
+
             for (resolved, _) in &to_uninstall {
                 let Some(deps_of) = reverse_deps_map.get(resolved.manifest.name.as_str()) else {
                     continue;
                 };
 
+                // Removing an optional dependency doesn't break the packages relying on it,
+                // and virtual packages may still be provided by another installed package
                 let would_break = deps_of
                     .difference(&to_uninstall_names)
+                    .filter(|name| {
+                        let dependent = &db.installed[**name].manifest;
+
+                        dependent
+                            .depends_on
+                            .iter()
+                            .filter(|dep| dep.applies_to(target_platform().0))
+                            .any(|dep| {
+                                (dep.name == resolved.manifest.name
+                                    || resolved.manifest.provides.contains(&dep.name))
+                                    && !satisfying_pkgs(dependent, &dep.name, &remaining)
+                                        .into_iter()
+                                        .any(|name| remaining.iter().any(|pkg| pkg.name == name))
+                            })
+                    })
                     .collect::<BTreeSet<_>>();
 
                 if !would_break.is_empty() {
@@ -205,61 +918,61 @@ async fn inner(action: Action) -> Result<()> {
                 return Ok(());
             }
 
-            let bin_dir = db.bin_dir();
-
-            let bin_paths = to_uninstall
+            let to_uninstall = to_uninstall
                 .into_iter()
-                .flat_map(|(_, installed)| {
-                    installed
-                        .binaries
-                        .iter()
-                        .map(move |bin| (bin_dir.join(bin), bin, installed))
-                })
+                .map(|(_, installed)| installed.manifest.name.clone())
                 .collect::<Vec<_>>();
 
-            if let Some((bin_path, bin_name, installed)) = bin_paths
-                .iter()
-                .find(|(bin_path, _, _)| !bin_path.is_file())
-            {
-                bail!(
-                    "Binary {} from package {} is missing (at path: {})",
-                    bin_name.bright_green(),
-                    installed.manifest.name.bright_yellow(),
-                    bin_path.to_string_lossy().bright_magenta()
-                );
+            uninstall_pkgs(&mut db, &to_uninstall).await?;
+        }
+
+        Action::Autoremove => {
+            let repos = db.cloned_repositories();
+
+            let installed = resolve_installed_pkgs(db.installed.values(), &repos)?;
+
+            let reverse_deps_map = build_pkgs_reverse_deps_map(
+                installed.iter().map(|(resolved, _)| resolved.manifest),
+            );
+
+            let unneeded =
+                compute_no_longer_needed_deps(&installed, &HashSet::new(), &reverse_deps_map);
+
+            if unneeded.is_empty() {
+                info!("No unneeded dependency to remove!");
+                return Ok(());
             }
 
-            for (bin_path, bin_name, installed) in &bin_paths {
-                fs::remove_file(&bin_path).await.with_context(|| {
-                    format!(
-                        "Faile dto remove binary {} from package {} is missing (at path: {})",
-                        bin_name.bright_green(),
-                        installed.manifest.name.bright_yellow(),
-                        bin_path.to_string_lossy().bright_magenta()
-                    )
-                })?;
+            display_pkg_phase(
+                "The following unneeded dependencies will be UNINSTALLED",
+                unneeded.iter().map(|(p, _)| *p),
+            );
+
+            warn!(
+                "Do you want to want to uninstall {} package(s)?\n",
+                unneeded.len().to_string().bright_red()
+            );
+
+            if !confirm().await? {
+                return Ok(());
             }
 
-            let to_uninstall = bin_paths
+            let unneeded = unneeded
                 .into_iter()
-                .map(|(_, _, installed)| installed.manifest.name.clone())
+                .map(|(_, installed)| installed.manifest.name.clone())
                 .collect::<Vec<_>>();
 
-            db.update(|db| {
-                for pkg_name in &to_uninstall {
-                    assert!(db.installed.remove(pkg_name).is_some());
-                }
-            })
-            .await?;
-
-            info!(
-                "Successfully removed {} packages!",
-                to_uninstall.len().to_string().bright_yellow()
-            );
+            uninstall_pkgs(&mut db, &unneeded).await?;
         }
 
-        Action::List {} => {
-            let mut table = Table::new();
+        Action::List {
+            sort,
+            reverse,
+            date,
+            verbose,
+            since,
+        } => {
+            let mut table = new_table();
 
             table
                 // Disable borders
@@ -268,38 +981,98 @@ async fn inner(action: Action) -> Result<()> {
                 .set_content_arrangement(ContentArrangement::Dynamic)
                 // Add header
                 .set_header(
-                    ["Name", "Version", "Repository", "Binaries", "Install date"]
-                        .into_iter()
-                        .map(|header| {
-                            Cell::new(header)
-                                .add_attribute(Attribute::Bold)
-                                .add_attribute(Attribute::Underlined)
-                        }),
+                    [
+                        "Name",
+                        "Version",
+                        "Repository",
+                        "Binaries",
+                        "Size",
+                        "Install date",
+                    ]
+                    .into_iter()
+                    .chain(verbose.then_some("Source URL"))
+                    .map(|header| {
+                        Cell::new(header)
+                            .add_attribute(Attribute::Bold)
+                            .add_attribute(Attribute::Underlined)
+                    }),
                 );
 
-            // TODO: add options to sort results
-            let mut pkgs = db.installed.values().collect::<Vec<_>>();
-
-            pkgs.sort_by(|a, b| {
-                a.repo_name
-                    .cmp(&b.repo_name)
-                    .then_with(|| a.manifest.name.cmp(&b.manifest.name))
-            });
+            let mut pkgs = vec![];
 
-            table.add_rows(pkgs.iter().map(|installed| {
-                [
-                    Cell::new(&installed.manifest.name).fg(Color::Yellow),
-                    Cell::new(&installed.version).fg(Color::DarkCyan),
-                    Cell::new(&installed.repo_name).fg(Color::Blue),
-                    Cell::new(join_iter(installed.binaries.iter(), " ")).fg(Color::Green),
-                    Cell::new(installed.at.strftime("%F %T")),
-                ]
-            }));
+            for installed in db.installed.values() {
+                if since.is_some_and(|since| installed.at.timestamp() < since) {
+                    continue;
+                }
 
-            println!("{table}");
-        }
+                let size = installed_size(installed).await;
+
+                if size.is_none() {
+                    warn!(
+                        "Package {} has missing files, you may want to repair it",
+                        installed.manifest.name.bright_yellow()
+                    );
+                }
+
+                pkgs.push((installed, size));
+            }
+
+            match sort {
+                None => pkgs.sort_by(|(a, _), (b, _)| {
+                    a.repo_name
+                        .cmp(&b.repo_name)
+                        .then_with(|| a.manifest.name.cmp(&b.manifest.name))
+                }),
+
+                Some(PkgSortBy::Name) => {
+                    pkgs.sort_by(|(a, _), (b, _)| a.manifest.name.cmp(&b.manifest.name))
+                }
+
+                // Oldest packages first
+                Some(PkgSortBy::InstallDate) => pkgs.sort_by(|(a, _), (b, _)| a.at.cmp(&b.at)),
+
+                // Largest packages first
+                Some(PkgSortBy::Size) => pkgs.sort_by(|(_, a), (_, b)| b.cmp(a)),
+            }
+
+            if reverse {
+                pkgs.reverse();
+            }
+
+            let total_size = pkgs.iter().filter_map(|(_, size)| *size).sum::<u64>();
+
+            table.add_rows(pkgs.iter().map(|(installed, size)| {
+                [
+                    Cell::new(&installed.manifest.name).fg(Color::Yellow),
+                    Cell::new(&installed.version).fg(Color::DarkCyan),
+                    Cell::new(&installed.repo_name).fg(Color::Blue),
+                    Cell::new(join_iter(installed.binaries.iter(), " ")).fg(Color::Green),
+                    Cell::new(match size {
+                        Some(size) => HumanBytes(*size).to_string(),
+                        None => "-".to_owned(),
+                    })
+                    .fg(Color::Magenta),
+                    Cell::new(match date {
+                        DateFormat::Relative => relative_time(&installed.at),
+                        DateFormat::Absolute => installed.at.strftime("%F %T").to_string(),
+                    }),
+                ]
+                .into_iter()
+                .chain(verbose.then(|| {
+                    Cell::new(installed.source_url.as_deref().unwrap_or("-")).fg(Color::Magenta)
+                }))
+            }));
+
+            println!("{table}");
+            println!(
+                "\nTotal size: {}",
+                HumanBytes(total_size).to_string().bright_magenta()
+            );
+        }
 
         Action::Repair { names } => {
+            let repos = db.cloned_repositories();
+
             let installed = if !names.is_empty() {
                 resolve_installed_pkgs_by_name(&names, &db.installed, &repos)?
             } else {
@@ -310,9 +1083,9 @@ async fn inner(action: Action) -> Result<()> {
                 .iter()
                 .filter(|(_, installed)| {
                     installed
-                        .binaries
+                        .installed_files
                         .iter()
-                        .any(|bin| !db.bin_dir().join(bin).is_file())
+                        .any(|file| !file.path.is_file())
                 })
                 .collect::<Vec<_>>();
 
@@ -337,33 +1110,168 @@ async fn inner(action: Action) -> Result<()> {
                 .map(|(resolved, _)| refresh_pkg(*resolved))
                 .collect::<Result<Vec<_>, _>>()?;
 
-            install_pkgs(broken, InstalledPackagesHandling::Reinstall, db, false).await?;
+            install_pkgs(
+                broken,
+                InstalledPackagesHandling::Reinstall,
+                db,
+                false,
+                link_binaries,
+                false,
+                keep_going,
+            )
+            .await?;
+        }
+
+        Action::Doctor => doctor(&db, &stale_repositories(&db, stale_repos_days)).await?,
+
+        Action::Verify { names } => {
+            let pkgs = if names.is_empty() {
+                db.installed.values().cloned().collect::<Vec<_>>()
+            } else {
+                names
+                    .iter()
+                    .map(|name| {
+                        db.installed.get(name).cloned().with_context(|| {
+                            format!("Package {} is not installed", name.bright_yellow())
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            };
+
+            let bin_dir = db.bin_dir().to_owned();
+
+            let (mismatches, unchecked) = tokio::task::spawn_blocking(move || {
+                let mut mismatches = vec![];
+                let mut unchecked = vec![];
+
+                // Versions installed side-by-side are verified as well
+                let pkgs = pkgs.iter().flat_map(|installed| {
+                    std::iter::once(installed).chain(installed.side_by_side.values())
+                });
+
+                for installed in pkgs {
+                    for bin in &installed.binaries {
+                        let Some(expected) = installed.checksums.get(bin) else {
+                            unchecked.push(installed.manifest.name.clone());
+                            break;
+                        };
+
+                        let path = installed
+                            .linked_from
+                            .as_deref()
+                            .unwrap_or(&bin_dir)
+                            .join(bin);
+
+                        let problem = if !path.is_file() {
+                            "missing".to_owned()
+                        } else {
+                            match sha256_file(&path) {
+                                Ok(actual) if &actual == expected => continue,
+                                Ok(_) => "modified".to_owned(),
+                                Err(err) => format!("{err:#}"),
+                            }
+                        };
+
+                        mismatches.push((
+                            installed.manifest.name.clone(),
+                            installed.version.clone(),
+                            path,
+                            problem,
+                        ));
+                    }
+                }
+
+                (mismatches, unchecked)
+            })
+            .await
+            .context("Failed to wait on Tokio task")?;
+
+            if !unchecked.is_empty() {
+                warn!(
+                    "No checksum was recorded for package(s) {}, reinstall them to be able to verify them",
+                    join_iter(unchecked.iter().map(|name| name.bright_yellow()), ", ")
+                );
+            }
+
+            if mismatches.is_empty() {
+                info!("All binaries are intact!");
+                return Ok(());
+            }
+
+            for (name, version, path, problem) in &mismatches {
+                error!(
+                    "{} {} (version {}): {} ({})",
+                    "✗".bright_red(),
+                    name.bright_yellow(),
+                    version.bright_cyan(),
+                    path.display().to_string().bright_magenta(),
+                    problem.bright_red()
+                );
+            }
+
+            bail!(
+                "Found {} modified or missing binaries, use {} to restore them",
+                mismatches.len().to_string().bright_red(),
+                "fetchy reinstall".bright_cyan()
+            );
         }
 
         Action::Search {
             pattern,
+            regex,
+            exact,
+            ignore_case,
             in_repos,
             show_installed,
+            check,
+            field,
+            tag,
+            limit,
+            offset,
         } => {
             if db.repositories.is_empty() {
                 warn!("No registered repository");
                 return Ok(());
             }
 
-            let mut repos = repos;
+            let pattern_str = pattern.unwrap_or_default();
 
-            if !in_repos.is_empty() {
-                let in_repos = HashSet::<_>::from_iter(in_repos.iter());
-                repos.retain(|name, _| in_repos.contains(name));
+            let pattern = if regex {
+                Pattern::new(&pattern_str, ignore_case).with_context(|| {
+                    format!("Invalid regular expression: {}", pattern_str.bright_cyan())
+                })?
+            } else {
+                Pattern::from_glob(&pattern_str, ignore_case)
             };
 
-            let mut results = repos
-                .values()
-                .flat_map(|repo| {
-                    repo.packages
-                        .iter()
-                        .filter(|(_, manifest)| pattern.is_match(&manifest.name))
-                        .map(|(_, manifest)| (&repo.name, manifest))
+            warn_stale_repositories(&db, &stale_repositories(&db, stale_repos_days), false);
+
+            let in_repos = HashSet::<_>::from_iter(in_repos.iter());
+
+            let mut results = db
+                .repositories
+                .iter()
+                .filter(|(name, _)| in_repos.is_empty() || in_repos.contains(name))
+                .flat_map(|(_, repo)| {
+                    repo.content
+                        .packages
+                        .values()
+                        .filter(|manifest| tag.iter().all(|tag| manifest.tags.contains(tag)))
+                        .filter_map(|manifest| {
+                            let matched = if exact {
+                                let is_exact = if ignore_case {
+                                    manifest.name.eq_ignore_ascii_case(&pattern_str)
+                                } else {
+                                    manifest.name == pattern_str
+                                };
+
+                                is_exact.then_some(SearchMatch::Name(&manifest.name))
+                            } else {
+                                search_pkg(manifest, &pattern, field)
+                            };
+
+                            matched.map(|matched| (&repo.content.name, manifest, matched))
+                        })
                 })
                 .collect::<Vec<_>>();
 
@@ -379,7 +1287,7 @@ async fn inner(action: Action) -> Result<()> {
                     })
                     .collect::<HashSet<_>>();
 
-                results.retain(|(repo_name, manifest)| {
+                results.retain(|(repo_name, manifest, _)| {
                     !installed.contains(&(repo_name.as_str(), manifest.name.as_str()))
                 });
             }
@@ -389,41 +1297,490 @@ async fn inner(action: Action) -> Result<()> {
                 return Ok(());
             }
 
-            let comparator = BatchComparator::new(pattern.to_string().chars());
+            let comparator = BatchComparator::new(pattern_str.chars());
 
-            let relevance = |manifest: &PackageManifest| {
-                (comparator.distance(manifest.name.chars()) * 1_000_000_000.0) as u128
-            };
+            // Compute the relevance of each result only once, as distances are costly to compute
+            let mut results = results
+                .into_iter()
+                .map(|(repo_name, manifest, matched)| {
+                    let relevance = comparator.distance(matched.value().chars());
+                    (relevance, repo_name, manifest, matched)
+                })
+                .collect::<Vec<_>>();
 
             // Sort results by relevance, then by name
-            results.sort_by(|(_, a), (_, b)| {
-                relevance(a)
-                    .cmp(&relevance(b))
+            results.sort_by(|(a_relevance, _, a, _), (b_relevance, _, b, _)| {
+                a_relevance
+                    .total_cmp(b_relevance)
                     .then_with(|| a.name.cmp(&b.name))
             });
 
-            let mut table = Table::new();
+            let total = results.len();
+
+            if offset >= total {
+                warn!(
+                    "No result to show past offset {} ({} result(s) in total)",
+                    offset.to_string().bright_yellow(),
+                    total.to_string().bright_yellow()
+                );
+
+                return Ok(());
+            }
+
+            let shown = if limit == 0 {
+                total - offset
+            } else {
+                limit.min(total - offset)
+            };
+
+            let results = results
+                .into_iter()
+                .skip(offset)
+                .take(shown)
+                .map(|(_, repo_name, manifest, matched)| {
+                    let installed = db
+                        .installed
+                        .get(&manifest.name)
+                        .filter(|installed| installed.repo_name == *repo_name);
+
+                    (repo_name, manifest, matched, installed)
+                })
+                .collect::<Vec<_>>();
+
+            // Only fetch the latest versions of the installed packages being displayed
+            let latest_versions = if check {
+                fetch_pkgs_infos(
+                    results
+                        .iter()
+                        .filter(|(_, _, _, installed)| installed.is_some())
+                        .map(|(_, manifest, _, _)| (*manifest, None))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                )
+                .await?
+                .into_iter()
+                .map(|(manifest, asset_infos)| (manifest.name, asset_infos.version))
+                .collect::<HashMap<_, _>>()
+            } else {
+                HashMap::new()
+            };
+
+            let mut headers = vec![
+                "Package name",
+                "Repository",
+                "Description",
+                "Tags",
+                "Matched",
+            ];
+
+            if show_installed {
+                headers.push("Installed");
+            }
+
+            let mut table = new_table();
 
             table
                 // Disable borders
                 .load_preset(presets::NOTHING)
-                .set_header(["Package name", "Repository"].into_iter().map(|header| {
+                .set_header(headers.into_iter().map(|header| {
                     Cell::new(header)
                         .add_attribute(Attribute::Bold)
                         .add_attribute(Attribute::Underlined)
                 }));
 
-            table.add_rows(results.into_iter().map(|(repo_name, manifest)| {
+            table.add_rows(
+                results
+                    .into_iter()
+                    .map(|(repo_name, manifest, matched, installed)| {
+                        let mut row = vec![
+                            match manifest.deprecated {
+                                None => Cell::new(&manifest.name).fg(Color::Yellow),
+                                Some(_) => Cell::new(format!("{} (deprecated)", manifest.name))
+                                    .fg(Color::DarkGrey),
+                            },
+                            Cell::new(repo_name).fg(Color::Blue),
+                            Cell::new(manifest.description.as_deref().unwrap_or("")),
+                            Cell::new(join_iter(manifest.tags.iter(), " ")).fg(Color::Cyan),
+                            Cell::new(match matched {
+                                SearchMatch::Name(_) => "name".to_owned(),
+                                SearchMatch::Description(_) => "description".to_owned(),
+                                SearchMatch::Binary(bin) => format!("binary '{bin}'"),
+                            }),
+                        ];
+
+                        if show_installed {
+                            row.push(match installed {
+                                None => Cell::new("no"),
+                                Some(installed) => match latest_versions.get(&manifest.name) {
+                                    Some(latest) if *latest != installed.version => {
+                                        Cell::new(format!(
+                                            "{} (outdated, {latest} available)",
+                                            installed.version
+                                        ))
+                                        .fg(Color::Red)
+                                    }
+                                    _ => Cell::new(&installed.version).fg(Color::Green),
+                                },
+                            });
+                        }
+
+                        row
+                    }),
+            );
+
+            println!("{table}");
+
+            if shown < total {
+                info!(
+                    "Showing results {}-{} of {}",
+                    (offset + 1).to_string().bright_yellow(),
+                    (offset + shown).to_string().bright_yellow(),
+                    total.to_string().bright_yellow()
+                );
+            }
+
+            if offset + shown < total {
+                info!(
+                    "Use a more specific pattern to narrow the results, or use {} and {} to see more",
+                    "--limit".bright_cyan(),
+                    "--offset".bright_cyan()
+                );
+            }
+        }
+
+        Action::Export {} => {
+            let lockfile = Lockfile::from_installed(db.installed.values());
+
+            let json = serde_json::to_string_pretty(&lockfile)
+                .context("Failed to serialize the packages list")?;
+
+            println!("{json}");
+        }
+
+        Action::Import { path } => {
+            let mut repos = db.cloned_repositories();
+
+            let content = fs::read_to_string(&path)
+                .await
+                .context("Failed to read the provided packages list")?;
+
+            let lockfile = serde_json::from_str::<Lockfile>(&content)
+                .context("Failed to parse the provided packages list")?;
+
+            lockfile.apply_sources(&mut repos);
+
+            let pkgs = resolve_pkgs_with_deps(
+                &lockfile.resolve(&repos)?,
+                false,
+                true,
+                &db.installed,
+                target_platform().0,
+            )
+            .await?;
+
+            install_pkgs(
+                pkgs,
+                InstalledPackagesHandling::Update,
+                db,
+                false,
+                link_binaries,
+                false,
+                keep_going,
+            )
+            .await?;
+        }
+
+        Action::Info { name } => {
+            let repos = db.cloned_repositories();
+            let priorities = db.repositories_priorities();
+
+            let ResolvedPkg {
+                manifest,
+                repository,
+                is_dep: _,
+                pinned_version: _,
+            } = resolve_pkg_by_name(&name, &repos, &priorities, None)?;
+
+            let (source, mut platforms, current) = match &manifest.source {
+                DownloadSource::Direct(DirectSource {
+                    urls,
+                    hardcoded_version,
+                    minisign_key: _,
+                    checksums: _,
+                }) => (
+                    format!("Direct (hardcoded version {hardcoded_version})"),
+                    urls.keys().copied().collect::<Vec<_>>(),
+                    urls.get_for_current_platform()
+                        .ok()
+                        .map(|(urls, typ)| (urls.to_string(), typ)),
+                ),
+
+                DownloadSource::GitHub(GithubSource {
+                    author,
+                    repo_name,
+                    asset,
+                    version,
+                    minisign_key: _,
+                }) => (
+                    format!("GitHub {author}/{repo_name} (version from {version:?})"),
+                    asset.keys().copied().collect::<Vec<_>>(),
+                    asset
+                        .get_for_current_platform()
+                        .ok()
+                        .map(|(pattern, typ)| (pattern.to_string(), typ)),
+                ),
+
+                DownloadSource::HttpIndex(HttpIndexSource {
+                    url,
+                    versioned_url: _,
+                    version,
+                    asset,
+                    minisign_key: _,
+                }) => (
+                    format!("HTTP index {url} (version at {version})"),
+                    asset.keys().copied().collect::<Vec<_>>(),
+                    asset
+                        .get_for_current_platform()
+                        .ok()
+                        .map(|(pointer, typ)| (pointer.to_string(), typ)),
+                ),
+            };
+
+            platforms.sort();
+
+            let latest_version = match &current {
+                None => "<unsupported on this platform>".bright_red(),
+                Some(_) => match manifest.source.fetch_infos(None).await {
+                    Ok(infos) => infos.version.bright_cyan(),
+                    Err(err) => format!("<failed to fetch: {err}>").bright_red(),
+                },
+            };
+
+            let (asset, binaries) = match current {
+                None => ("-".to_owned(), "-".to_owned()),
+                Some((asset, typ)) => (
+                    match typ {
+                        AssetType::Binary { copy_as: _ } => format!("{asset} (binary)"),
+                        AssetType::Archive {
+                            format,
+                            files: _,
+                            extra_files: _,
+                            strip_components: _,
+                        } => {
+                            format!("{asset} (archive {format})")
+                        }
+                    },
+                    join_iter(typ.binaries().into_iter(), " "),
+                ),
+            };
+
+            let installed_pkg = db
+                .installed
+                .get(&manifest.name)
+                .filter(|installed| installed.repo_name == repository.name);
+
+            let installed = match db.installed.get(&manifest.name) {
+                Some(installed) if installed.repo_name == repository.name => format!(
+                    "{}{}{}",
+                    installed.version.bright_cyan(),
+                    if installed.installed_as_dep {
+                        " (as a dependency)"
+                    } else {
+                        ""
+                    },
+                    if installed.side_by_side.is_empty() {
+                        String::new()
+                    } else {
+                        format!(
+                            " (also installed side-by-side: {})",
+                            join_iter(
+                                installed
+                                    .side_by_side
+                                    .keys()
+                                    .map(|version| version.bright_cyan()),
+                                ", "
+                            )
+                        )
+                    }
+                ),
+                Some(installed) => format!(
+                    "no (a package of the same name is installed from repository {})",
+                    installed.repo_name.bright_blue()
+                ),
+                None => "no".to_owned(),
+            };
+
+            let mut table = new_table();
+
+            table
+                // Disable borders
+                .load_preset(presets::NOTHING)
+                // Enable dynamic sizing for columns
+                .set_content_arrangement(ContentArrangement::Dynamic);
+
+            let label = |name| Cell::new(name).add_attribute(Attribute::Bold);
+
+            table.add_rows([
+                [label("Name"), Cell::new(&manifest.name).fg(Color::Yellow)],
                 [
-                    Cell::new(&manifest.name).fg(Color::Yellow),
-                    Cell::new(repo_name).fg(Color::Blue),
-                ]
-            }));
+                    label("Repository"),
+                    Cell::new(&repository.name).fg(Color::Blue),
+                ],
+                [
+                    label("Description"),
+                    Cell::new(manifest.description.as_deref().unwrap_or("-")),
+                ],
+                [
+                    label("Deprecated"),
+                    Cell::new(manifest.deprecated.as_deref().unwrap_or("no")),
+                ],
+                [
+                    label("Tags"),
+                    Cell::new(join_iter(manifest.tags.iter(), " ")).fg(Color::Cyan),
+                ],
+                [label("Source"), Cell::new(source).fg(Color::Magenta)],
+                [
+                    label("Dependencies"),
+                    Cell::new(join_iter(manifest.depends_on.iter(), " ")).fg(Color::Yellow),
+                ],
+                [
+                    label("Optional dependencies"),
+                    Cell::new(join_iter(manifest.optional_deps.iter(), " ")).fg(Color::Yellow),
+                ],
+                [
+                    label("Provides"),
+                    Cell::new(join_iter(manifest.provides.iter(), " ")).fg(Color::Yellow),
+                ],
+                [
+                    label("Platforms"),
+                    Cell::new(join_iter(
+                        platforms
+                            .iter()
+                            .map(|(system, cpu_arch)| format!("{system}[{cpu_arch}]")),
+                        " ",
+                    )),
+                ],
+                [label("Asset"), Cell::new(asset)],
+                [label("Binaries"), Cell::new(binaries).fg(Color::Green)],
+                [label("Latest version"), Cell::new(latest_version)],
+                [label("Installed"), Cell::new(installed)],
+            ]);
+
+            if let Some(source_url) =
+                installed_pkg.and_then(|installed| installed.source_url.as_ref())
+            {
+                table.add_row([
+                    label("Downloaded from"),
+                    Cell::new(source_url).fg(Color::Magenta),
+                ]);
+            }
+
+            if let Some((system, cpu_arch)) = installed_pkg.and_then(|installed| installed.platform)
+            {
+                table.add_row([
+                    label("Installed for"),
+                    Cell::new(format!("{system}[{cpu_arch}]")),
+                ]);
+            }
 
             println!("{table}");
         }
 
-        Action::AddRepo { path, json, ignore } => {
+        Action::Deps { name, reverse } => {
+            let repos = db.cloned_repositories();
+            let priorities = db.repositories_priorities();
+
+            let resolved = resolve_pkg_by_name(&name, &repos, &priorities, None)?;
+            let repository = resolved.repository;
+
+            let children: HashMap<&str, Vec<&str>> = if reverse {
+                build_pkgs_reverse_deps_map(repository.packages.values())
+                    .into_iter()
+                    .map(|(name, deps_of)| {
+                        let mut deps_of = deps_of.into_iter().collect::<Vec<_>>();
+                        deps_of.sort();
+                        (name, deps_of)
+                    })
+                    .collect()
+            } else {
+                // Surface the same conflicts the installer would hit, but show all the providers
+                // of virtual packages instead of asking which one to use
+                let resolved_pkgs = resolve_pkgs_with_deps(
+                    &[resolved],
+                    false,
+                    false,
+                    &db.installed,
+                    target_platform().0,
+                )
+                .await?;
+
+                let manifests = resolved_pkgs
+                    .iter()
+                    .map(|pkg| pkg.manifest)
+                    .collect::<Vec<_>>();
+
+                manifests
+                    .iter()
+                    .map(|manifest| {
+                        (
+                            manifest.name.as_str(),
+                            manifest
+                                .depends_on
+                                .iter()
+                                .filter(|dep| dep.applies_to(target_platform().0))
+                                .flat_map(|dep| satisfying_pkgs(manifest, &dep.name, &manifests))
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            };
+
+            // Count how many different packages of the tree depend on each package
+            let mut parents = HashMap::<&str, HashSet<&str>>::new();
+            let mut queue = vec![resolved.manifest.name.as_str()];
+
+            while let Some(name) = queue.pop() {
+                for child in children.get(name).into_iter().flatten() {
+                    if parents.entry(child).or_default().insert(name) {
+                        queue.push(child);
+                    }
+                }
+            }
+
+            let mut lines = vec![];
+
+            display_deps_tree(
+                resolved.manifest.name.as_str(),
+                "",
+                &children,
+                &mut vec![],
+                &|name| {
+                    let mut annotations = vec![];
+
+                    if let Some(installed) = db.installed.get(name) {
+                        if installed.repo_name == repository.name {
+                            annotations.push(format!("installed: {}", installed.version).green());
+                        }
+                    }
+
+                    if !reverse && parents.get(name).is_some_and(|parents| parents.len() > 1) {
+                        annotations.push("shared".bright_magenta());
+                    }
+
+                    annotations
+                },
+                &mut lines,
+            );
+
+            println!("{}", lines.join("\n"));
+        }
+
+        Action::AddRepo {
+            path,
+            json,
+            ignore,
+            priority,
+        } => {
             let path = fs::canonicalize(&path)
                 .await
                 .context("Failed to canonicalize repository path")?;
@@ -464,6 +1821,8 @@ async fn inner(action: Action) -> Result<()> {
                     SourcedRepository {
                         content: repo,
                         source,
+                        priority,
+                        last_fetched: Zoned::now(),
                     },
                 );
             })
@@ -475,50 +1834,122 @@ async fn inner(action: Action) -> Result<()> {
             );
         }
 
-        Action::UpdateRepos {} => {
+        Action::UpdateRepos { names } => {
             if db.repositories.is_empty() {
                 warn!("No registered repository");
                 return Ok(());
             }
 
-            let fetched =
-                fetch_repositories(db.repositories.values().map(|repo| repo.source.clone()))
-                    .await?;
+            if let Some(not_found) = names
+                .iter()
+                .find(|name| !db.repositories.contains_key(*name))
+            {
+                bail!("Repository {} was not found", not_found.bright_blue());
+            }
 
-            db.update(|db| {
-                let mut fetched = fetched.into_iter();
+            let names = if names.is_empty() {
+                db.repositories.keys().cloned().collect::<Vec<_>>()
+            } else {
+                names
+                    .into_iter()
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect()
+            };
 
-                for (_, repo) in db.repositories.iter_mut() {
-                    let fetched = fetched.next().unwrap();
+            let updates = update_repositories(&mut db, &names).await?;
 
-                    // Just to be safe
-                    assert_eq!(repo.content.name, fetched.name);
+            report_repositories_updates(&updates)?;
+        }
+
+        Action::RemoveRepos { names } => {
+            let names = HashSet::<_>::from_iter(names.iter());
+            let repos_names = HashSet::<_>::from_iter(db.repositories.keys());
 
-                    repo.content = fetched;
+            if let Some(not_found) = names.difference(&repos_names).next() {
+                bail!("Repository {} was not found", not_found.bright_blue());
+            }
+
+            db.update(|db| {
+                for name in names {
+                    assert!(db.repositories.remove(name).is_some());
                 }
             })
             .await?;
+        }
+
+        Action::PruneRepos => {
+            // Side-by-side versions and backups may still need their repository
+            let used = db
+                .installed
+                .values()
+                .flat_map(|installed| {
+                    std::iter::once(installed)
+                        .chain(installed.side_by_side.values())
+                        .chain(installed.backup.as_ref().map(|backup| &*backup.installed))
+                })
+                .map(|installed| installed.repo_name.as_str())
+                .collect::<HashSet<_>>();
+
+            let unused = db
+                .repositories
+                .keys()
+                .filter(|name| !used.contains(name.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if unused.is_empty() {
+                info!("No unused repository to remove!");
+                return Ok(());
+            }
 
             info!(
-                "Successfully updated {} repositories.",
-                repos.len().to_string().bright_yellow()
+                "No installed package comes from the following repositories:\n\n{}\n",
+                join_iter(
+                    unused
+                        .iter()
+                        .map(|name| format!("* {}", name.bright_blue())),
+                    "\n"
+                )
             );
-        }
 
-        Action::RemoveRepos { names } => {
-            let names = HashSet::<_>::from_iter(names.iter());
-            let repos_names = HashSet::<_>::from_iter(repos.keys());
+            warn!(
+                "Do you want to remove {} repository(ies)?\n",
+                unused.len().to_string().bright_red()
+            );
 
-            if let Some(not_found) = names.difference(&repos_names).next() {
-                bail!("Repository {} was not found", not_found.bright_blue());
+            if !confirm().await? {
+                return Ok(());
             }
 
             db.update(|db| {
-                for name in names {
+                for name in &unused {
                     assert!(db.repositories.remove(name).is_some());
                 }
             })
             .await?;
+
+            info!(
+                "Successfully removed {} repositories.",
+                unused.len().to_string().bright_yellow()
+            );
+        }
+
+        Action::SetRepoPriority { name, priority } => {
+            if !db.repositories.contains_key(&name) {
+                bail!("Repository {} was not found", name.bright_blue());
+            }
+
+            db.update(|db| {
+                db.repositories.get_mut(&name).unwrap().priority = priority;
+            })
+            .await?;
+
+            info!(
+                "Priority of repository {} set to {}",
+                name.bright_blue(),
+                priority.to_string().bright_yellow()
+            );
         }
 
         Action::ListRepos {} => {
@@ -527,14 +1958,99 @@ async fn inner(action: Action) -> Result<()> {
                 return Ok(());
             }
 
-            let mut table = Table::new();
+            let mut table = new_table();
 
             table
                 // Disable borders
                 .load_preset(presets::NOTHING)
                 // Add header
                 .set_header(
-                    ["Repository name", "Packages", "Source"]
+                    [
+                        "Repository name",
+                        "Packages",
+                        "Priority",
+                        "Source",
+                        "Last updated",
+                    ]
+                    .into_iter()
+                    .map(|header| {
+                        Cell::new(header)
+                            .add_attribute(Attribute::Bold)
+                            .add_attribute(Attribute::Underlined)
+                    }),
+                );
+
+            table.add_rows(db.repositories.values().map(|repo| {
+                [
+                    Cell::new(&repo.content.name).fg(Color::Blue),
+                    Cell::new(repo.content.packages.len().to_string()).fg(Color::Yellow),
+                    Cell::new(repo.priority.to_string()).fg(Color::Cyan),
+                    Cell::new(&repo.source.location).fg(Color::Magenta),
+                    Cell::new(relative_time(&repo.last_fetched)),
+                ]
+            }));
+
+            println!("{table}");
+        }
+
+        Action::ShowRepo { name } => {
+            let SourcedRepository {
+                content,
+                source,
+                priority,
+                last_fetched,
+            } = db
+                .repositories
+                .get(&name)
+                .with_context(|| format!("Repository {} was not found", name.bright_blue()))?;
+
+            let mut table = new_table();
+
+            table
+                // Disable borders
+                .load_preset(presets::NOTHING)
+                // Enable dynamic sizing for columns
+                .set_content_arrangement(ContentArrangement::Dynamic);
+
+            let label = |name| Cell::new(name).add_attribute(Attribute::Bold);
+
+            table.add_rows([
+                [label("Name"), Cell::new(&content.name).fg(Color::Blue)],
+                [label("Description"), Cell::new(&content.description)],
+                [
+                    label("Source"),
+                    Cell::new(&source.location).fg(Color::Magenta),
+                ],
+                [
+                    label("Priority"),
+                    Cell::new(priority.to_string()).fg(Color::Cyan),
+                ],
+                [
+                    label("Last updated"),
+                    Cell::new(relative_time(last_fetched)),
+                ],
+                [
+                    label("Packages"),
+                    Cell::new(content.packages.len().to_string()).fg(Color::Yellow),
+                ],
+            ]);
+
+            println!("{table}");
+
+            if content.packages.is_empty() {
+                return Ok(());
+            }
+
+            let mut pkgs = content.packages.values().collect::<Vec<_>>();
+            pkgs.sort_by(|a, b| a.name.cmp(&b.name));
+
+            let mut table = new_table();
+
+            table
+                // Disable borders
+                .load_preset(presets::NOTHING)
+                .set_header(
+                    ["Package name", "Description", "Tags"]
                         .into_iter()
                         .map(|header| {
                             Cell::new(header)
@@ -543,19 +2059,760 @@ async fn inner(action: Action) -> Result<()> {
                         }),
                 );
 
-            table.add_rows(db.repositories.values().map(|repo| {
+            table.add_rows(pkgs.into_iter().map(|manifest| {
                 [
-                    Cell::new(&repo.content.name).fg(Color::Blue),
-                    Cell::new(repo.content.packages.len().to_string()).fg(Color::Yellow),
-                    Cell::new(&repo.source.location).fg(Color::Magenta),
+                    Cell::new(&manifest.name).fg(Color::Yellow),
+                    Cell::new(manifest.description.as_deref().unwrap_or("")),
+                    Cell::new(join_iter(manifest.tags.iter(), " ")).fg(Color::Cyan),
                 ]
             }));
 
-            println!("{table}");
+            println!("\n{table}");
+        }
+
+        // Handled before opening the database
+        Action::BinPath
+        | Action::Update {
+            verify_only: true, ..
+        }
+        | Action::Path { .. }
+        | Action::Config { .. }
+        | Action::ConvertRepo { .. }
+        | Action::ValidateRepo { .. }
+        | Action::SelfUpdate
+        | Action::Env => unreachable!(),
+    }
+
+    Ok(())
+}
+
+fn path_export(shell: Option<Shell>, root: Option<&Path>) -> Result<()> {
+    let shell = match shell {
+        Some(shell) => shell,
+        None => {
+            let path = env::var_os("SHELL").with_context(|| {
+                format!(
+                    "Failed to detect your shell as $SHELL is not set, please provide it with {}",
+                    "--shell".bright_cyan()
+                )
+            })?;
+
+            match Path::new(&path).file_name().and_then(|name| name.to_str()) {
+                Some("bash") => Shell::Bash,
+                Some("zsh") => Shell::Zsh,
+                Some("fish") => Shell::Fish,
+                _ => bail!(
+                    "Unsupported shell {}, please provide one with {}",
+                    path.to_string_lossy().bright_magenta(),
+                    "--shell".bright_cyan()
+                ),
+            }
+        }
+    };
+
+    // The directory is resolved on each shell startup, so it follows configuration changes
+    let bin_path = match root {
+        None => "fetchy bin-path".to_owned(),
+        Some(root) => {
+            let root = root.display().to_string();
+
+            // Single quotes can't be escaped inside single-quoted strings in POSIX shells
+            let root = match shell {
+                Shell::Bash | Shell::Zsh => root.replace('\'', r"'\''"),
+                Shell::Fish => root.replace('\\', r"\\").replace('\'', r"\'"),
+            };
+
+            format!("fetchy --root '{root}' bin-path")
+        }
+    };
+
+    println!("# Add binaries installed by Fetchy to PATH");
+
+    match shell {
+        Shell::Bash | Shell::Zsh => println!("export PATH=\"$({bin_path}):$PATH\""),
+        Shell::Fish => println!("set -gx PATH ({bin_path}) $PATH"),
+    }
+
+    Ok(())
+}
+
+async fn convert_repo(path: PathBuf, to: RepoFormat) -> Result<()> {
+    let source = RepositorySource {
+        location: RepositoryLocation::File(path),
+        json: to == RepoFormat::Fetchy,
+    };
+
+    // This also validates the repository
+    let repo = fetch_repository(&source).await?;
+
+    let converted = match to {
+        RepoFormat::Json => {
+            serde_json::to_string_pretty(&repo).context("Failed to serialize repository as JSON")?
+        }
+
+        RepoFormat::Fetchy => {
+            let emitted = emit_repository(&repo)
+                .context("Failed to convert repository to Fetchy's format")?;
+
+            // Ensure the emitted repository can be read back
+            repository().parse_str(&emitted).map_err(|err| {
+                anyhow!("Emitted repository is invalid, please report this bug: {err:?}")
+            })?;
+
+            emitted
+        }
+    };
+
+    println!("{}", converted.trim_end());
+
+    Ok(())
+}
+
+async fn validate_repo(path: PathBuf, json_repo: bool, json: bool) -> Result<()> {
+    let content = fs::read_to_string(&path)
+        .await
+        .with_context(|| format!("Failed to read repository file at path: {}", path.display()))?;
+
+    if json {
+        // Messages must not contain color codes
+        colored::control::set_override(false);
+    }
+
+    let diagnostics = diagnose_repository(&content, &path, json_repo);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&diagnostics)
+                .context("Failed to serialize diagnostics")?
+        );
+    } else {
+        for Diagnostic {
+            severity: _,
+            message,
+            package: _,
+            span,
+        } in &diagnostics
+        {
+            match span {
+                Some(DiagnosticSpan {
+                    line,
+                    column,
+                    len: _,
+                }) => println!(
+                    "{} {message} {}",
+                    "*".bright_yellow(),
+                    format!("(line {line}, column {column})").bright_black()
+                ),
+                None => println!("{} {message}", "*".bright_yellow()),
+            }
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        bail!("Found {} issue(s) in the repository", diagnostics.len());
+    }
+
+    if !json {
+        info!("Repository is valid!");
+    }
+
+    Ok(())
+}
+
+/// Fetch some repositories again and update their content, returning the outcome for each of them
+async fn update_repositories(
+    db: &mut Db,
+    names: &[String],
+) -> Result<Vec<(String, RepositoryUpdate)>> {
+    let fetched = fetch_repositories(
+        names
+            .iter()
+            .map(|name| db.repositories[name].source.clone())
+            .collect::<Vec<_>>()
+            .into_iter(),
+    )
+    .await?;
+
+    let mut updates = vec![];
+    let mut to_update = vec![];
+
+    // Repositories may have been renamed in their source file
+    let mut new_names = HashSet::new();
+
+    for (name, fetched) in names.iter().zip(fetched) {
+        let fetched = match fetched {
+            Ok(fetched) => fetched,
+            Err(err) => {
+                updates.push((name.clone(), RepositoryUpdate::Failed(err)));
+                continue;
+            }
+        };
+
+        if fetched.name != *name {
+            if db.repositories.contains_key(&fetched.name)
+                || !new_names.insert(fetched.name.clone())
+            {
+                warn!(
+                    "{} Repository {} was renamed to {} in its source file, but another repository already has this name, skipping it.",
+                    "WARNING:".bright_red().bold(),
+                    name.bright_blue(),
+                    fetched.name.bright_blue()
+                );
+
+                updates.push((name.clone(), RepositoryUpdate::Skipped));
+                continue;
+            }
+
+            warn!(
+                "Repository {} was renamed to {} in its source file, renaming it.",
+                name.bright_blue(),
+                fetched.name.bright_blue()
+            );
+        }
+
+        updates.push((
+            name.clone(),
+            RepositoryUpdate::Updated {
+                old_pkgs: db.repositories[name]
+                    .content
+                    .packages
+                    .keys()
+                    .cloned()
+                    .collect(),
+                new_pkgs: fetched.packages.keys().cloned().collect(),
+            },
+        ));
+
+        to_update.push((name, fetched));
+    }
+
+    db.update(|db| {
+        let now = Zoned::now();
+
+        for (name, fetched) in to_update {
+            let repo = db.repositories.remove(name).unwrap();
+
+            if fetched.name != *name {
+                for installed in db.installed.values_mut() {
+                    // Versions installed side-by-side and backups remember their repository as well
+                    let repo_names = std::iter::once(&mut installed.repo_name)
+                        .chain(
+                            installed
+                                .side_by_side
+                                .values_mut()
+                                .map(|kept| &mut kept.repo_name),
+                        )
+                        .chain(
+                            installed
+                                .backup
+                                .as_mut()
+                                .map(|backup| &mut backup.installed.repo_name),
+                        );
+
+                    for repo_name in repo_names {
+                        if repo_name == name {
+                            *repo_name = fetched.name.clone();
+                        }
+                    }
+                }
+            }
+
+            db.repositories.insert(
+                fetched.name.clone(),
+                SourcedRepository {
+                    content: fetched,
+                    last_fetched: now.clone(),
+                    ..repo
+                },
+            );
+        }
+    })
+    .await?;
+
+    Ok(updates)
+}
+
+/// Outcome of fetching a repository again
+enum RepositoryUpdate {
+    /// Content was updated, with the repository's packages before and after the update
+    Updated {
+        old_pkgs: BTreeSet<String>,
+        new_pkgs: BTreeSet<String>,
+    },
+
+    /// Repository was renamed in its source file to an already existing name
+    Skipped,
+
+    Failed(anyhow::Error),
+}
+
+/// Display what changed in the updated repositories
+fn report_repositories_updates(updates: &[(String, RepositoryUpdate)]) -> Result<()> {
+    let mut table = new_table();
+
+    table
+        // Disable borders
+        .load_preset(presets::NOTHING)
+        // Add header
+        .set_header(
+            ["Repository", "Packages", "Added", "Removed"]
+                .into_iter()
+                .map(|header| {
+                    Cell::new(header)
+                        .add_attribute(Attribute::Bold)
+                        .add_attribute(Attribute::Underlined)
+                }),
+        )
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    table.add_rows(updates.iter().map(|(name, update)| {
+        let name = Cell::new(name).fg(Color::Blue);
+
+        match update {
+            RepositoryUpdate::Updated { old_pkgs, new_pkgs } => [
+                name,
+                if old_pkgs.len() == new_pkgs.len() {
+                    Cell::new(new_pkgs.len())
+                } else {
+                    Cell::new(format!("{} → {}", old_pkgs.len(), new_pkgs.len())).fg(Color::Cyan)
+                },
+                Cell::new(join_iter(new_pkgs.difference(old_pkgs), " ")).fg(Color::Green),
+                Cell::new(join_iter(old_pkgs.difference(new_pkgs), " ")).fg(Color::Red),
+            ],
+
+            RepositoryUpdate::Skipped => [
+                name,
+                Cell::new("skipped").fg(Color::Yellow),
+                Cell::new(""),
+                Cell::new(""),
+            ],
+
+            RepositoryUpdate::Failed(_) => [
+                name,
+                Cell::new("FAILED").fg(Color::Red),
+                Cell::new(""),
+                Cell::new(""),
+            ],
         }
+    }));
 
-        Action::BinPath => println!("{}", db.bin_dir().display()),
+    println!("{table}");
+
+    let updated = updates
+        .iter()
+        .filter(|(_, update)| matches!(update, RepositoryUpdate::Updated { .. }))
+        .count();
+
+    let failures = updates
+        .iter()
+        .filter_map(|(name, update)| match update {
+            RepositoryUpdate::Failed(err) => Some((name, err)),
+            RepositoryUpdate::Updated { .. } | RepositoryUpdate::Skipped => None,
+        })
+        .collect::<Vec<_>>();
+
+    if failures.is_empty() {
+        info!(
+            "Successfully updated {} repositories.",
+            updated.to_string().bright_yellow()
+        );
+
+        return Ok(());
     }
 
+    for (name, err) in &failures {
+        error!("\n{} {}: {err:#}", "✗".bright_red(), name.bright_blue());
+    }
+
+    bail!(
+        "{} repositories were updated, but {} failed to update",
+        updated.to_string().bright_yellow(),
+        failures.len().to_string().bright_red()
+    );
+}
+
+/// Read a list of package names (one per line), along with their line number
+async fn read_pkgs_list(path: &Path) -> Result<Vec<(usize, String)>> {
+    let content = fs::read_to_string(path).await.with_context(|| {
+        format!(
+            "Failed to read packages list at path: {}",
+            path.display().to_string().bright_magenta()
+        )
+    })?;
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap().trim()))
+        .filter(|(_, name)| !name.is_empty())
+        .map(|(line, name)| (line, name.to_owned()))
+        .collect())
+}
+
+/// Remove the files of the provided installed packages, then remove them from the database
+async fn uninstall_pkgs(db: &mut Db, names: &[String]) -> Result<()> {
+    let to_uninstall = names
+        .iter()
+        .map(|name| &db.installed[name])
+        .collect::<Vec<_>>();
+
+    // Versions installed side-by-side are uninstalled as well
+    let to_uninstall = to_uninstall
+        .into_iter()
+        .flat_map(|installed| std::iter::once(installed).chain(installed.side_by_side.values()))
+        .collect::<Vec<_>>();
+
+    let mut files = vec![];
+
+    for installed in &to_uninstall {
+        for file in &installed.installed_files {
+            // Links are removed even if their target doesn't exist anymore
+            let exists = match file.kind {
+                InstalledFileKind::Symlink => file.path.symlink_metadata().is_ok(),
+                _ => file.path.is_file(),
+            };
+
+            if exists {
+                files.push((file, installed));
+                continue;
+            }
+
+            // Completions and man pages may have been removed by hand, which shouldn't prevent uninstalling
+            if matches!(
+                file.kind,
+                InstalledFileKind::Completion | InstalledFileKind::ManPage
+            ) {
+                warn!(
+                    "{} File from package {} is already missing (at path: {}), skipping it.",
+                    "WARNING:".bright_red().bold(),
+                    installed.manifest.name.bright_yellow(),
+                    file.path.to_string_lossy().bright_magenta()
+                );
+
+                continue;
+            }
+
+            bail!(
+                "File from package {} is missing (at path: {})",
+                installed.manifest.name.bright_yellow(),
+                file.path.to_string_lossy().bright_magenta()
+            );
+        }
+    }
+
+    for (file, installed) in &files {
+        fs::remove_file(&file.path).await.with_context(|| {
+            format!(
+                "Failed to remove file from package {} (at path: {})",
+                installed.manifest.name.bright_yellow(),
+                file.path.to_string_lossy().bright_magenta()
+            )
+        })?;
+    }
+
+    for installed in &to_uninstall {
+        let pkg_dirs = installed
+            .linked_from
+            .iter()
+            .chain(installed.backup.as_ref().map(|backup| &backup.dir));
+
+        for pkg_dir in pkg_dirs.filter(|dir| dir.exists()) {
+            // The package's files are already gone, so it must be removed from the database in all cases
+            if let Err(err) = fs::remove_dir_all(pkg_dir).await {
+                warn!(
+                    "{} Failed to remove directory of package {} (at path: {}): {err}",
+                    "WARNING:".bright_red().bold(),
+                    installed.manifest.name.bright_yellow(),
+                    pkg_dir.to_string_lossy().bright_magenta()
+                );
+            }
+        }
+    }
+
+    db.update(|db| {
+        for pkg_name in names {
+            assert!(db.installed.remove(pkg_name).is_some());
+        }
+    })
+    .await?;
+
+    info!(
+        "Successfully removed {} packages!",
+        names.len().to_string().bright_yellow()
+    );
+
     Ok(())
 }
+
+/// Run the verification commands of installed packages, without touching the network or the database
+async fn verify_installed_pkgs(data: &AppData, bin_dir: &Path, names: &[String]) -> Result<()> {
+    let pkgs = if names.is_empty() {
+        data.installed.values().collect::<Vec<_>>()
+    } else {
+        names
+            .iter()
+            .map(|name| {
+                data.installed
+                    .get(name)
+                    .with_context(|| format!("Package {} is not installed", name.bright_yellow()))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let mut results = vec![];
+
+    for installed in pkgs {
+        let Some(verify) = &installed.manifest.verify else {
+            continue;
+        };
+
+        // Binaries built for another platform can't be run
+        if installed
+            .platform
+            .is_some_and(|platform| platform != (SYSTEM, CPU_ARCH))
+        {
+            continue;
+        }
+
+        let bins_dir = installed.linked_from.as_deref().unwrap_or(bin_dir);
+
+        // Each binary is checked separately to report which ones are broken
+        let binaries = if verify.contains("{bin}") {
+            installed
+                .binaries
+                .iter()
+                .map(|bin| vec![bin.clone()])
+                .collect()
+        } else {
+            vec![installed.binaries.clone()]
+        };
+
+        for binaries in binaries {
+            let result = run_verify_command(verify, &binaries, &[bins_dir, bin_dir]).await;
+            results.push((installed, binaries, result));
+        }
+    }
+
+    if results.is_empty() {
+        warn!("No installed package declares a verification command");
+        return Ok(());
+    }
+
+    let mut table = new_table();
+
+    table
+        // Disable borders
+        .load_preset(presets::NOTHING)
+        // Add header
+        .set_header(["Package", "Binaries", "Result"].into_iter().map(|header| {
+            Cell::new(header)
+                .add_attribute(Attribute::Bold)
+                .add_attribute(Attribute::Underlined)
+        }));
+
+    table.add_rows(results.iter().map(|(installed, binaries, result)| {
+        [
+            Cell::new(&installed.manifest.name).fg(Color::Yellow),
+            Cell::new(binaries.join(" ")).fg(Color::Green),
+            match result {
+                Ok(()) => Cell::new("ok").fg(Color::Green),
+                Err(_) => Cell::new("FAILED").fg(Color::Red),
+            },
+        ]
+    }));
+
+    println!("{table}");
+
+    let failures = results
+        .iter()
+        .filter_map(|(installed, _, result)| result.as_ref().err().map(|err| (installed, err)))
+        .collect::<Vec<_>>();
+
+    if failures.is_empty() {
+        info!("All verification commands succeeded!");
+        return Ok(());
+    }
+
+    for (installed, err) in &failures {
+        error!(
+            "\n{} {}: {err:#}",
+            "✗".bright_red(),
+            installed.manifest.name.bright_yellow()
+        );
+    }
+
+    bail!(
+        "{} verification(s) failed, use {} to reinstall the broken packages",
+        failures.len().to_string().bright_red(),
+        "fetchy reinstall".bright_cyan()
+    );
+}
+
+/// Get the repositories which weren't updated for more than the provided number of days
+///
+/// Staleness detection is disabled if the number of days is zero.
+fn stale_repositories(db: &Db, max_age_days: u64) -> Vec<String> {
+    if max_age_days == 0 {
+        return vec![];
+    }
+
+    let max_age = SignedDuration::from_secs(
+        i64::try_from(max_age_days.saturating_mul(24 * 60 * 60)).unwrap_or(i64::MAX),
+    );
+    let now = Timestamp::now();
+
+    db.repositories
+        .iter()
+        .filter(|(_, repo)| now.duration_since(repo.last_fetched.timestamp()) > max_age)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Warn about stale repositories
+///
+/// `--auto-refresh` is only suggested when the current command supports it.
+fn warn_stale_repositories(db: &Db, stale: &[String], can_auto_refresh: bool) {
+    if stale.is_empty() {
+        return;
+    }
+
+    for name in stale {
+        warn!(
+            "Repository {} was last updated {}, packages informations may be outdated",
+            name.bright_blue(),
+            relative_time(&db.repositories[name].last_fetched)
+        );
+    }
+
+    if can_auto_refresh {
+        warn!(
+            "Use {} to update them (or {} to do it automatically)",
+            "fetchy update-repos".bright_cyan(),
+            "--auto-refresh".bright_cyan()
+        );
+    } else {
+        warn!("Use {} to update them", "fetchy update-repos".bright_cyan());
+    }
+}
+
+/// Field of a package matched by a search pattern
+enum SearchMatch<'a> {
+    Name(&'a str),
+    Description(&'a str),
+    Binary(&'a str),
+}
+
+impl SearchMatch<'_> {
+    fn value(&self) -> &str {
+        match self {
+            SearchMatch::Name(value)
+            | SearchMatch::Description(value)
+            | SearchMatch::Binary(value) => value,
+        }
+    }
+}
+
+/// Match a package against a search pattern, giving priority to its name
+fn search_pkg<'a>(
+    manifest: &'a PackageManifest,
+    pattern: &Pattern,
+    field: SearchField,
+) -> Option<SearchMatch<'a>> {
+    if matches!(field, SearchField::Name | SearchField::All) && pattern.is_match(&manifest.name) {
+        return Some(SearchMatch::Name(&manifest.name));
+    }
+
+    if matches!(field, SearchField::Desc | SearchField::All) {
+        if let Some(description) = manifest
+            .description
+            .as_deref()
+            .filter(|description| pattern.is_match(description))
+        {
+            return Some(SearchMatch::Description(description));
+        }
+    }
+
+    if matches!(field, SearchField::Bin | SearchField::All) {
+        if let Some(bin) = manifest
+            .binaries()
+            .into_iter()
+            .find(|bin| pattern.is_match(bin))
+        {
+            return Some(SearchMatch::Binary(bin));
+        }
+    }
+
+    None
+}
+
+/// Compute the disk space used by a package's files, or `None` if some of them are missing
+async fn installed_size(installed: &InstalledPackage) -> Option<u64> {
+    let mut size = 0;
+
+    for InstalledFile { path, kind } in &installed.installed_files {
+        // Symbolic links point to files which are already counted
+        if *kind == InstalledFileKind::Symlink {
+            continue;
+        }
+
+        size += fs::metadata(path).await.ok()?.len();
+    }
+
+    Some(size)
+}
+
+fn display_deps_tree<'a>(
+    name: &'a str,
+    prefix: &str,
+    children: &HashMap<&'a str, Vec<&'a str>>,
+    stack: &mut Vec<&'a str>,
+    annotate: &impl Fn(&str) -> Vec<ColoredString>,
+    out: &mut Vec<String>,
+) {
+    let mut annotations = annotate(name);
+
+    let is_cycle = stack.contains(&name);
+
+    if is_cycle {
+        annotations.push("cycle".bright_red());
+    }
+
+    let line = if annotations.is_empty() {
+        name.bright_yellow().to_string()
+    } else {
+        format!(
+            "{} ({})",
+            name.bright_yellow(),
+            join_iter(annotations.iter(), ", ")
+        )
+    };
+
+    if stack.is_empty() {
+        out.push(line);
+    } else {
+        out.last_mut().unwrap().push_str(&line);
+    }
+
+    if is_cycle {
+        return;
+    }
+
+    let Some(deps) = children.get(name) else {
+        return;
+    };
+
+    stack.push(name);
+
+    for (i, dep) in deps.iter().enumerate() {
+        let is_last = i + 1 == deps.len();
+
+        out.push(format!("{prefix}{}", if is_last { "└── " } else { "├── " }));
+
+        let prefix = format!("{prefix}{}", if is_last { "    " } else { "│   " });
+
+        display_deps_tree(dep, &prefix, children, stack, annotate, out);
+    }
+
+    stack.pop();
+}
@@ -1,4 +1,11 @@
-use std::{collections::HashMap, fmt, marker::PhantomData, ops::Deref};
+use std::{
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+    sync::OnceLock,
+};
 
 use anyhow::{Context, Result};
 use serde::{
@@ -23,6 +30,20 @@ macro_rules! supported_platforms {
                 }
             }
 
+            impl FromStr for $enum_name {
+                type Err = String;
+
+                fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+                    match s {
+                        $( stringify!($value) => Ok(Self::$value), )+
+                        _ => Err(format!(
+                            "unknown value '{s}', expected one of: {}",
+                            [$( stringify!($value) ),+].join(", ")
+                        )),
+                    }
+                }
+            }
+
             $(
                 #[cfg($cfg_name = $value:snake)]
                 pub static [<$enum_name:snake:upper>]: $enum_name = $enum_name::$value;
@@ -37,6 +58,26 @@ supported_platforms!(target_arch as CpuArch => x86_64, aarch64);
 // List of all supported target OSes
 supported_platforms!(target_os as System => linux, windows);
 
+static TARGET_PLATFORM: OnceLock<(System, CpuArch)> = OnceLock::new();
+
+/// Override the platform assets are selected for (defaults to the current one)
+pub fn init_target_platform(system: Option<System>, cpu_arch: Option<CpuArch>) {
+    TARGET_PLATFORM
+        .set((system.unwrap_or(SYSTEM), cpu_arch.unwrap_or(CPU_ARCH)))
+        .unwrap();
+}
+
+/// Platform assets are selected for
+pub fn target_platform() -> (System, CpuArch) {
+    TARGET_PLATFORM.get().copied().unwrap_or((SYSTEM, CPU_ARCH))
+}
+
+/// Check if assets are selected for another platform than the current one,
+/// in which case the installed binaries can't be run locally
+pub fn is_cross_target() -> bool {
+    target_platform() != (SYSTEM, CPU_ARCH)
+}
+
 // Platform-dependent value
 #[derive(Debug, Clone)]
 pub struct PlatformDependent<T>(HashMap<(System, CpuArch), T>);
@@ -63,11 +104,13 @@ impl<T> PlatformDependent<T> {
     pub fn get_for(&self, system: System, cpu_arch: CpuArch) -> Result<&T> {
         self.0
             .get(&(system, cpu_arch))
-            .with_context(|| format!("No value found for provided platform ({CPU_ARCH}, {SYSTEM})"))
+            .with_context(|| format!("No value found for provided platform ({cpu_arch}, {system})"))
     }
 
+    /// Get the value for the target platform (see [`target_platform`])
     pub fn get_for_current_platform(&self) -> Result<&T> {
-        self.get_for(SYSTEM, CPU_ARCH)
+        let (system, cpu_arch) = target_platform();
+        self.get_for(system, cpu_arch)
     }
 }
 
@@ -79,6 +122,12 @@ impl<T> Deref for PlatformDependent<T> {
     }
 }
 
+impl<T> DerefMut for PlatformDependent<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 impl<T: Serialize> Serialize for PlatformDependent<T> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let Self(entries) = self;
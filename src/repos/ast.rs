@@ -1,8 +1,18 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::sources::{direct::DirectSource, github::GithubSource};
+use anyhow::Result;
+
+use crate::sources::{
+    direct::DirectSource, github::GithubSource, http_index::HttpIndexSource, AssetInfos,
+    AssetSource,
+};
+
+use super::{arch::System, constraint::VersionConstraint};
 
 #[macro_export]
 macro_rules! ast_friendly {
@@ -20,6 +30,25 @@ macro_rules! ast_friendly {
 pub struct Repository {
     pub name: String,
     pub description: String,
+
+    // Oldest version of Fetchy able to understand this repository
+    #[serde(default)]
+    pub min_fetchy_version: Option<String>,
+
+    // Files whose packages are merged into this repository, relative to the repository's file
+    #[serde(default)]
+    pub includes: Vec<String>,
+
+    pub packages: HashMap<String, PackageManifest>,
+}
+
+/// Part of a repository stored in a separate file, see [`Repository::includes`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepositoryFragment {
+    #[serde(default)]
+    pub includes: Vec<String>,
+
     pub packages: HashMap<String, PackageManifest>,
 }
 
@@ -28,7 +57,165 @@ pub struct Repository {
 pub struct PackageManifest {
     pub name: String,
     pub source: DownloadSource,
-    pub depends_on: Vec<String>,
+    pub depends_on: Vec<Dependency>,
+
+    // Dependencies which are only installed on demand
+    #[serde(default)]
+    pub optional_deps: Vec<String>,
+
+    // Virtual packages this package can stand for when used as a dependency
+    #[serde(default)]
+    pub provides: Vec<String>,
+
+    pub verify: Option<String>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    // Message explaining why the package shouldn't be used anymore
+    #[serde(default)]
+    pub deprecated: Option<String>,
+
+    // Don't append '.exe' to binaries extracted on Windows
+    #[serde(default)]
+    pub no_exe_suffix: bool,
+}
+
+impl PackageManifest {
+    /// Iterate over both required and optional dependencies
+    pub fn all_deps(&self) -> impl Iterator<Item = &String> {
+        self.depends_on
+            .iter()
+            .map(|dep| &dep.name)
+            .chain(&self.optional_deps)
+    }
+
+    /// Names of the binaries exposed by the package on any platform
+    ///
+    /// Binaries named after the file they are extracted from are not included
+    pub fn binaries(&self) -> BTreeSet<&str> {
+        let asset_types = match &self.source {
+            DownloadSource::Direct(DirectSource {
+                urls,
+                hardcoded_version: _,
+                minisign_key: _,
+                checksums: _,
+            }) => urls.values().map(|(_, typ)| typ).collect::<Vec<_>>(),
+
+            DownloadSource::GitHub(GithubSource {
+                author: _,
+                repo_name: _,
+                asset,
+                version: _,
+                minisign_key: _,
+            }) => asset.values().map(|(_, typ)| typ).collect(),
+
+            DownloadSource::HttpIndex(HttpIndexSource {
+                url: _,
+                versioned_url: _,
+                version: _,
+                asset,
+                minisign_key: _,
+            }) => asset.values().map(|(_, typ)| typ).collect(),
+        };
+
+        asset_types
+            .into_iter()
+            .flat_map(|typ| typ.binaries())
+            .collect()
+    }
+}
+
+/// Required dependency of a package, optionally restricted to some versions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "DependencyRepr", into = "DependencyRepr")]
+pub struct Dependency {
+    pub name: String,
+    pub constraint: Option<VersionConstraint>,
+
+    // Only required when installing for this system (e.g. `windows: "foo"`)
+    pub system: Option<System>,
+}
+
+impl Dependency {
+    /// Check if the dependency is required when installing for the provided system
+    pub fn applies_to(&self, system: System) -> bool {
+        self.system.is_none_or(|only_on| only_on == system)
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(system) = self.system {
+            write!(f, "{system}: ")?;
+        }
+
+        match &self.constraint {
+            None => write!(f, "{}", self.name),
+            Some(constraint) => write!(f, "{} {constraint}", self.name),
+        }
+    }
+}
+
+// Unconstrained dependencies are stored as plain names, like they were before constraints existed
+#[derive(Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum DependencyRepr {
+    Name(String),
+    Detailed {
+        name: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        constraint: Option<VersionConstraint>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        system: Option<System>,
+    },
+}
+
+impl From<DependencyRepr> for Dependency {
+    fn from(value: DependencyRepr) -> Self {
+        match value {
+            DependencyRepr::Name(name) => Self {
+                name,
+                constraint: None,
+                system: None,
+            },
+
+            DependencyRepr::Detailed {
+                name,
+                constraint,
+                system,
+            } => Self {
+                name,
+                constraint,
+                system,
+            },
+        }
+    }
+}
+
+impl From<Dependency> for DependencyRepr {
+    fn from(value: Dependency) -> Self {
+        let Dependency {
+            name,
+            constraint,
+            system,
+        } = value;
+
+        if constraint.is_none() && system.is_none() {
+            Self::Name(name)
+        } else {
+            Self::Detailed {
+                name,
+                constraint,
+                system,
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,4 +223,15 @@ pub struct PackageManifest {
 pub enum DownloadSource {
     Direct(DirectSource),
     GitHub(GithubSource),
+    HttpIndex(HttpIndexSource),
+}
+
+impl DownloadSource {
+    pub async fn fetch_infos(&self, version: Option<&str>) -> Result<AssetInfos> {
+        match self {
+            DownloadSource::Direct(params) => params.fetch_infos(version).await,
+            DownloadSource::GitHub(params) => params.fetch_infos(version).await,
+            DownloadSource::HttpIndex(params) => params.fetch_infos(version).await,
+        }
+    }
 }
@@ -0,0 +1,283 @@
+//! Version constraints that can be put on dependencies (e.g. `>=13` or `^8`)
+//!
+//! Versions are made of dot-separated integers, missing components being considered as zeroes
+//! (so `1.2` and `1.2.0` are the same version).
+
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::validator::parse_version;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VersionConstraint {
+    pub op: ConstraintOp,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintOp {
+    /// `=1.2.3`
+    Exact,
+    /// `>1.2.3`
+    Greater,
+    /// `>=1.2.3`
+    GreaterOrEqual,
+    /// `<1.2.3`
+    Lower,
+    /// `<=1.2.3`
+    LowerOrEqual,
+    /// `^1.2.3`, compatible versions (up to the next breaking version, like Cargo does)
+    Caret,
+    /// `~1.2.3`, only patch updates (or minor updates if only a major version is provided)
+    Tilde,
+}
+
+impl ConstraintOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            ConstraintOp::Exact => "=",
+            ConstraintOp::Greater => ">",
+            ConstraintOp::GreaterOrEqual => ">=",
+            ConstraintOp::Lower => "<",
+            ConstraintOp::LowerOrEqual => "<=",
+            ConstraintOp::Caret => "^",
+            ConstraintOp::Tilde => "~",
+        }
+    }
+}
+
+/// All operators, two-characters ones coming first so they are parsed in priority
+pub static CONSTRAINT_OPS: [ConstraintOp; 7] = [
+    ConstraintOp::GreaterOrEqual,
+    ConstraintOp::LowerOrEqual,
+    ConstraintOp::Exact,
+    ConstraintOp::Greater,
+    ConstraintOp::Lower,
+    ConstraintOp::Caret,
+    ConstraintOp::Tilde,
+];
+
+impl VersionConstraint {
+    fn parsed_version(&self) -> Vec<u64> {
+        // Validity of the version is ensured when parsing the constraint
+        parse_version(&self.version).unwrap()
+    }
+
+    /// Range of the versions satisfying this constraint
+    pub fn range(&self) -> VersionRange {
+        let version = self.parsed_version();
+
+        let bound = |version: Vec<u64>, inclusive: bool| Some(Bound { version, inclusive });
+
+        match self.op {
+            ConstraintOp::Exact => VersionRange {
+                lower: bound(version.clone(), true),
+                upper: bound(version, true),
+            },
+
+            ConstraintOp::Greater => VersionRange {
+                lower: bound(version, false),
+                upper: None,
+            },
+
+            ConstraintOp::GreaterOrEqual => VersionRange {
+                lower: bound(version, true),
+                upper: None,
+            },
+
+            ConstraintOp::Lower => VersionRange {
+                lower: None,
+                upper: bound(version, false),
+            },
+
+            ConstraintOp::LowerOrEqual => VersionRange {
+                lower: None,
+                upper: bound(version, true),
+            },
+
+            ConstraintOp::Caret => {
+                // First non-zero component (or the last one if they are all zeroes)
+                let breaking = version
+                    .iter()
+                    .position(|&component| component != 0)
+                    .unwrap_or(version.len() - 1);
+
+                VersionRange {
+                    upper: bound(bump_component(&version, breaking), false),
+                    lower: bound(version, true),
+                }
+            }
+
+            ConstraintOp::Tilde => VersionRange {
+                upper: bound(bump_component(&version, version.len().min(2) - 1), false),
+                lower: bound(version, true),
+            },
+        }
+    }
+
+    /// Check if a version satisfies this constraint
+    ///
+    /// Returns `None` if the version doesn't follow the expected format (e.g. `1.2.3-beta`)
+    pub fn matches(&self, version: &str) -> Option<bool> {
+        let version = parse_version(version.strip_prefix('v').unwrap_or(version))?;
+
+        Some(self.range().contains(&version))
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.op.symbol(), self.version)
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (op, version) = CONSTRAINT_OPS
+            .iter()
+            .find_map(|op| s.strip_prefix(op.symbol()).map(|version| (*op, version)))
+            .ok_or_else(|| {
+                format!("Version constraint '{s}' should start with one of: = > >= < <= ^ ~")
+            })?;
+
+        let version = version.trim();
+
+        if parse_version(version).is_none() {
+            return Err(format!(
+                "Invalid version '{version}' in constraint (expected e.g. '1.2.3')"
+            ));
+        }
+
+        Ok(Self {
+            op,
+            version: version.to_owned(),
+        })
+    }
+}
+
+impl TryFrom<String> for VersionConstraint {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<VersionConstraint> for String {
+    fn from(value: VersionConstraint) -> Self {
+        value.to_string()
+    }
+}
+
+/// Set of versions between two (optional) bounds
+#[derive(Debug, Clone)]
+pub struct VersionRange {
+    lower: Option<Bound>,
+    upper: Option<Bound>,
+}
+
+#[derive(Debug, Clone)]
+struct Bound {
+    version: Vec<u64>,
+    inclusive: bool,
+}
+
+impl VersionRange {
+    /// Range containing every version
+    pub fn any() -> Self {
+        Self {
+            lower: None,
+            upper: None,
+        }
+    }
+
+    /// Range of the versions contained in both ranges
+    pub fn intersect(&self, other: &Self) -> Self {
+        let lower = match (&self.lower, &other.lower) {
+            (Some(a), Some(b)) => Some(
+                match compare_versions(&a.version, &b.version) {
+                    Ordering::Less => b,
+                    Ordering::Greater => a,
+                    Ordering::Equal if a.inclusive => b,
+                    Ordering::Equal => a,
+                }
+                .clone(),
+            ),
+            (a, b) => a.as_ref().or(b.as_ref()).cloned(),
+        };
+
+        let upper = match (&self.upper, &other.upper) {
+            (Some(a), Some(b)) => Some(
+                match compare_versions(&a.version, &b.version) {
+                    Ordering::Less => a,
+                    Ordering::Greater => b,
+                    Ordering::Equal if a.inclusive => b,
+                    Ordering::Equal => a,
+                }
+                .clone(),
+            ),
+            (a, b) => a.as_ref().or(b.as_ref()).cloned(),
+        };
+
+        Self { lower, upper }
+    }
+
+    /// Check if no version is contained in this range
+    pub fn is_empty(&self) -> bool {
+        let (Some(lower), Some(upper)) = (&self.lower, &self.upper) else {
+            return false;
+        };
+
+        match compare_versions(&lower.version, &upper.version) {
+            Ordering::Less => false,
+            Ordering::Equal => !(lower.inclusive && upper.inclusive),
+            Ordering::Greater => true,
+        }
+    }
+
+    pub fn contains(&self, version: &[u64]) -> bool {
+        let above_lower = self.lower.as_ref().is_none_or(|lower| {
+            match compare_versions(version, &lower.version) {
+                Ordering::Less => false,
+                Ordering::Equal => lower.inclusive,
+                Ordering::Greater => true,
+            }
+        });
+
+        let below_upper = self.upper.as_ref().is_none_or(|upper| {
+            match compare_versions(version, &upper.version) {
+                Ordering::Less => true,
+                Ordering::Equal => upper.inclusive,
+                Ordering::Greater => false,
+            }
+        });
+
+        above_lower && below_upper
+    }
+}
+
+/// Compare two versions, missing components being considered as zeroes
+pub fn compare_versions(a: &[u64], b: &[u64]) -> Ordering {
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            a.get(i)
+                .copied()
+                .unwrap_or(0)
+                .cmp(&b.get(i).copied().unwrap_or(0))
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Increment a version's component, dropping the following ones (e.g. `1.2.3` => `1.3`)
+fn bump_component(version: &[u64], index: usize) -> Vec<u64> {
+    let mut bumped = version[..=index].to_vec();
+    bumped[index] += 1;
+    bumped
+}
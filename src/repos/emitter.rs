@@ -0,0 +1,362 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+
+use crate::sources::{
+    direct::{AssetUrls, ChecksumsFile, DirectSource},
+    github::{GitHubVersionExtraction, GithubSource},
+    http_index::{HttpIndexSource, JsonPointer},
+    pattern::Pattern,
+    AssetType, BinaryInArchive, ExtraFileInArchive, ExtraFileKind,
+};
+
+use super::{
+    arch::PlatformDependent,
+    ast::{Dependency, DownloadSource, PackageManifest, Repository},
+};
+
+/// Write a repository using Fetchy's format
+///
+/// The output can be parsed back with [`super::parser::repository`]
+pub fn emit_repository(repo: &Repository) -> Result<String> {
+    let Repository {
+        name,
+        description,
+        min_fetchy_version,
+        includes,
+        packages,
+    } = repo;
+
+    let mut out = String::new();
+
+    writeln!(out, "name {}", string(name)?)?;
+    writeln!(out, "description {}", string(description)?)?;
+
+    if let Some(min_fetchy_version) = min_fetchy_version {
+        writeln!(out, "min_fetchy_version {}", string(min_fetchy_version)?)?;
+    }
+
+    for include in includes {
+        writeln!(out, "include {}", string(include)?)?;
+    }
+
+    writeln!(out, "packages {{")?;
+
+    let mut packages = packages.values().collect::<Vec<_>>();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for (i, manifest) in packages.into_iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+
+        emit_package(manifest, &mut out)?;
+    }
+
+    writeln!(out, "}}")?;
+
+    Ok(out)
+}
+
+fn emit_package(manifest: &PackageManifest, out: &mut String) -> Result<()> {
+    let PackageManifest {
+        name,
+        source,
+        depends_on,
+        optional_deps,
+        provides,
+        verify,
+        description,
+        tags,
+        deprecated,
+        no_exe_suffix,
+    } = manifest;
+
+    write!(out, "  {}", string(name)?)?;
+
+    if !depends_on.is_empty() {
+        let deps = depends_on
+            .iter()
+            .map(
+                |Dependency {
+                     name,
+                     constraint,
+                     system,
+                 }| {
+                    let dep = match constraint {
+                        None => string(name)?,
+                        Some(constraint) => format!("{} {constraint}", string(name)?),
+                    };
+
+                    Ok(match system {
+                        None => dep,
+                        Some(system) => format!("{system}: {dep}"),
+                    })
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
+        write!(out, " (requires {})", deps.join(", "))?;
+    }
+
+    for (clause, names) in [("optionally", optional_deps), ("provides", provides)] {
+        if !names.is_empty() {
+            write!(out, " ({clause} {})", strings(names)?)?;
+        }
+    }
+
+    if let Some(verify) = verify {
+        write!(out, " (verify {})", string(verify)?)?;
+    }
+
+    if let Some(description) = description {
+        write!(out, " (description {})", string(description)?)?;
+    }
+
+    if !tags.is_empty() {
+        write!(out, " (tags {})", strings(tags)?)?;
+    }
+
+    if let Some(deprecated) = deprecated {
+        write!(out, " (deprecated {})", string(deprecated)?)?;
+    }
+
+    if *no_exe_suffix {
+        write!(out, " (no_exe_suffix)")?;
+    }
+
+    write!(out, ": ")?;
+
+    match source {
+        DownloadSource::Direct(DirectSource {
+            urls,
+            hardcoded_version,
+            minisign_key,
+            checksums,
+        }) => {
+            write!(out, "Direct version({}) ", string(hardcoded_version)?)?;
+
+            if let Some(minisign_key) = minisign_key {
+                write!(out, "minisign({}) ", string(minisign_key)?)?;
+            }
+
+            if let Some(ChecksumsFile { url, filename }) = checksums {
+                match filename {
+                    None => write!(out, "sha256sums({}) ", string(url)?)?,
+                    Some(filename) => {
+                        write!(out, "sha256sums({}, {}) ", string(url)?, string(filename)?)?
+                    }
+                }
+            }
+
+            emit_assets(urls, |AssetUrls(urls)| strings(urls), out)?;
+        }
+
+        DownloadSource::GitHub(GithubSource {
+            author,
+            repo_name,
+            asset,
+            version,
+            minisign_key,
+        }) => {
+            let version = match version {
+                GitHubVersionExtraction::TagName => "TagName",
+                GitHubVersionExtraction::ReleaseTitle => "ReleaseTitle",
+            };
+
+            write!(
+                out,
+                "GitHub {} version({version}) ",
+                string(&format!("{author}/{repo_name}"))?
+            )?;
+
+            if let Some(minisign_key) = minisign_key {
+                write!(out, "minisign({}) ", string(minisign_key)?)?;
+            }
+
+            emit_assets(asset, pattern, out)?;
+        }
+
+        DownloadSource::HttpIndex(HttpIndexSource {
+            url,
+            versioned_url,
+            version,
+            asset,
+            minisign_key,
+        }) => {
+            write!(out, "HttpIndex {} ", string(url)?)?;
+
+            if let Some(versioned_url) = versioned_url {
+                write!(out, "versioned({}) ", string(versioned_url)?)?;
+            }
+
+            write!(out, "version({}) ", string(&version.0)?)?;
+
+            if let Some(minisign_key) = minisign_key {
+                write!(out, "minisign({}) ", string(minisign_key)?)?;
+            }
+
+            emit_assets(asset, |JsonPointer(pointer)| string(pointer), out)?;
+        }
+    }
+
+    writeln!(out)?;
+
+    Ok(())
+}
+
+fn emit_assets<T>(
+    assets: &PlatformDependent<(T, AssetType)>,
+    emit_location: impl Fn(&T) -> Result<String>,
+    out: &mut String,
+) -> Result<()> {
+    if assets.is_empty() {
+        write!(out, "{{}}")?;
+        return Ok(());
+    }
+
+    let mut assets = assets.iter().collect::<Vec<_>>();
+    assets.sort_by_key(|(platform, _)| **platform);
+
+    writeln!(out, "{{")?;
+
+    for (i, ((system, cpu_arch), (location, typ))) in assets.iter().enumerate() {
+        write!(
+            out,
+            "    {system}[{cpu_arch}] {} {}",
+            emit_location(location)?,
+            asset_type(typ)?
+        )?;
+
+        writeln!(out, "{}", if i + 1 < assets.len() { "," } else { "" })?;
+    }
+
+    write!(out, "  }}")?;
+
+    Ok(())
+}
+
+fn asset_type(typ: &AssetType) -> Result<String> {
+    match typ {
+        AssetType::Binary { copy_as } => Ok(format!("as {}", strings(copy_as)?)),
+
+        AssetType::Archive {
+            format,
+            files,
+            extra_files,
+            strip_components,
+        } => {
+            let extra_files =
+                extra_files
+                    .iter()
+                    .map(|ExtraFileInArchive { path_matcher, kind }| {
+                        let path_matcher = pattern(path_matcher)?;
+
+                        Ok(match kind {
+                            ExtraFileKind::Completion { shell } => {
+                                format!("completion {path_matcher} for {}", string(shell)?)
+                            }
+                            ExtraFileKind::Man => format!("man {path_matcher}"),
+                        })
+                    });
+
+            let files = files
+                .iter()
+                .map(
+                    |BinaryInArchive {
+                         path_matcher,
+                         copy_as,
+                     }| {
+                        let mut file = format!("bin {}", pattern(path_matcher)?);
+
+                        if let Some(copy_as) = copy_as {
+                            write!(file, " as {}", string(copy_as)?)?;
+                        }
+
+                        Ok(file)
+                    },
+                )
+                .chain(extra_files)
+                .collect::<Result<Vec<_>>>()?;
+
+            let strip_components = if *strip_components > 0 {
+                format!(", strip_components {strip_components}")
+            } else {
+                String::new()
+            };
+
+            Ok(format!(
+                "archive({format}{strip_components}) {{ {} }}",
+                files.join(", ")
+            ))
+        }
+    }
+}
+
+fn strings(strings: &[String]) -> Result<String> {
+    Ok(strings
+        .iter()
+        .map(|value| string(value))
+        .collect::<Result<Vec<_>>>()?
+        .join(", "))
+}
+
+fn pattern(pattern: &Pattern) -> Result<String> {
+    match pattern.strip_case_insensitive_flag() {
+        Some(pattern) => Ok(format!("ci{}", raw_string(pattern)?)),
+        None => raw_string(pattern.as_str()),
+    }
+}
+
+/// Represent a regular expression, whose backslashes are kept as they are by the parser
+fn raw_string(value: &str) -> Result<String> {
+    if value.is_empty() {
+        bail!("Empty strings can't be represented in Fetchy's format");
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+    let mut after_backslash = false;
+
+    escaped.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' if !after_backslash => escaped.push_str("\\\""),
+            // Newlines have the same meaning for the regex engine when escaped
+            '\n' => escaped.push_str(if after_backslash { "n" } else { "\\n" }),
+            '\r' => escaped.push_str(if after_backslash { "r" } else { "\\r" }),
+            _ => escaped.push(c),
+        }
+
+        after_backslash = c == '\\' && !after_backslash;
+    }
+
+    escaped.push('"');
+
+    Ok(escaped)
+}
+
+fn string(value: &str) -> Result<String> {
+    if value.is_empty() {
+        bail!("Empty strings can't be represented in Fetchy's format");
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+
+    escaped.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+
+    Ok(escaped)
+}
@@ -1,3 +1,5 @@
 pub mod arch;
 pub mod ast;
+pub mod constraint;
+pub mod emitter;
 pub mod parser;
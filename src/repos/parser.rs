@@ -1,34 +1,67 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use parsy::{char, choice, filter, just, newline, whitespaces, Parser};
 use regex::Regex;
 
-use crate::sources::{
-    direct::DirectSource,
-    github::{GitHubVersionExtraction, GithubSource},
-    pattern::Pattern,
-    ArchiveFormat, AssetType, BinaryInArchive,
+use crate::{
+    sources::{
+        direct::{AssetUrls, ChecksumsFile, DirectSource},
+        github::{GitHubVersionExtraction, GithubSource},
+        http_index::{HttpIndexSource, JsonPointer},
+        pattern::{Pattern, CASE_INSENSITIVE_FLAG},
+        ArchiveFormat, AssetType, BinaryInArchive, ExtraFileInArchive, ExtraFileKind,
+    },
+    validator::parse_version,
 };
 
 use super::{
     arch::{CpuArch, PlatformDependent, PlatformDependentEntry, System},
-    ast::{DownloadSource, PackageManifest, Repository},
+    ast::{Dependency, DownloadSource, PackageManifest, Repository, RepositoryFragment},
+    constraint::{ConstraintOp, VersionConstraint},
 };
 
 pub fn repository() -> impl Parser<Repository> {
+    parsers().0
+}
+
+/// Parser for files included by a repository
+pub fn repository_fragment() -> impl Parser<RepositoryFragment> {
+    parsers().1
+}
+
+fn parsers() -> (impl Parser<Repository>, impl Parser<RepositoryFragment>) {
+    // Comments run until the end of the line
+    let comment = char('#').ignore_then(filter(|c| c != '\n' && c != '\r').repeated());
+
     let ms = whitespaces().no_newline();
-    let msnl = whitespaces();
+    let msnl = whitespaces().ignore_then(comment.then_ignore(whitespaces()).repeated());
     let s = ms.at_least_one();
 
-    let string = char('"')
-        .ignore_then(
-            filter(|c| c != '\n' && c != '\r' && c != '"')
-                .repeated()
-                .at_least(1)
-                .collect_string()
-                .critical("expected a string"),
-        )
-        .then_ignore(char('"').critical("expected a closing quote after the string"));
+    // Escape sequences are decoded as either one or two characters
+    let escape_sequence = char('\\').ignore_then(
+        choice::<(char, Option<char>), _>((
+            char('"').to(('"', None)),
+            char('\\').to(('\\', None)),
+            char('n').to(('\n', None)),
+            char('r').to(('\r', None)),
+            char('t').to(('\t', None)),
+            // Unknown escape sequences are kept as they are, so regular expressions like '\.' keep working
+            filter(|c| c != '\n' && c != '\r').map(|c| ('\\', Some(c))),
+        ))
+        .critical("expected an escaped character"),
+    );
+
+    let string = quoted_string(escape_sequence);
+
+    // Patterns keep their backslashes as they are, so they have the same meaning for the regex engine
+    // (e.g. '\\' is an escaped backslash), while '\"' still allows them to contain a quote
+    let raw_string = quoted_string(
+        char('\\')
+            .ignore_then(
+                filter(|c| c != '\n' && c != '\r').critical("expected an escaped character"),
+            )
+            .map(|c| ('\\', Some(c))),
+    );
 
     let system = choice::<System, _>((
         just("linux").to(System::linux),
@@ -47,81 +80,229 @@ pub fn repository() -> impl Parser<Repository> {
         .then(cpu_arch)
         .then_ignore(char(']').critical_with_no_message());
 
-    let pattern = string.and_then_or_str_err(|string| {
-        Regex::new(&string)
-            .map(Pattern)
-            .map_err(|err| format!("Invalid regex {string:?} provided: {err}"))
-    });
+    // Patterns prefixed with 'ci' are case-insensitive
+    let pattern =
+        just("ci")
+            .or_not()
+            .then(raw_string)
+            .and_then_or_str_err(|(case_insensitive, string)| {
+                let regex = match case_insensitive {
+                    Some(_) => format!("{CASE_INSENSITIVE_FLAG}{string}"),
+                    None => string,
+                };
+
+                Regex::new(&regex)
+                    .map(Pattern)
+                    .map_err(|err| format!("Invalid regex {regex:?} provided: {err}"))
+            });
 
     let single_file_extraction = just("bin")
         .ignore_then(s.critical_with_no_message())
         .ignore_then(pattern.critical("expected a pattern"))
-        .then_ignore(s)
-        .then_ignore(just("as"))
-        .then_ignore(s.critical_with_no_message())
-        .then(string.critical("expected a name for the binary file"))
+        .then(
+            s.ignore_then(just("as"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(string.critical("expected a name for the binary file"))
+                .or_not(),
+        )
         .map(|(path_matcher, copy_as)| BinaryInArchive {
             path_matcher,
             copy_as,
         });
 
+    let completion_extraction = just("completion")
+        .ignore_then(s.critical_with_no_message())
+        .ignore_then(pattern.critical("expected a pattern"))
+        .then_ignore(s.critical_with_no_message())
+        .then_ignore(just("for").critical_with_no_message())
+        .then_ignore(s.critical_with_no_message())
+        .then(string.critical("expected a shell name"))
+        .map(|(path_matcher, shell)| ExtraFileInArchive {
+            path_matcher,
+            kind: ExtraFileKind::Completion { shell },
+        });
+
+    let man_extraction = just("man")
+        .ignore_then(s.critical_with_no_message())
+        .ignore_then(pattern.critical("expected a pattern"))
+        .map(|path_matcher| ExtraFileInArchive {
+            path_matcher,
+            kind: ExtraFileKind::Man,
+        });
+
+    let archive_entry = choice::<ArchiveEntry, _>((
+        single_file_extraction.map(ArchiveEntry::Binary),
+        completion_extraction.map(ArchiveEntry::Extra),
+        man_extraction.map(ArchiveEntry::Extra),
+    ));
+
     let archive_format = choice::<ArchiveFormat, _>((
-        just("archive(TarGz)").to(ArchiveFormat::TarGz),
-        just("archive(TarXz)").to(ArchiveFormat::TarXz),
-        just("archive(Zip)").to(ArchiveFormat::Zip),
+        just("TarGz").to(ArchiveFormat::TarGz),
+        just("TarXz").to(ArchiveFormat::TarXz),
+        just("TarBz2").to(ArchiveFormat::TarBz2),
+        just("TarZst").to(ArchiveFormat::TarZst),
+        just("Zip").to(ArchiveFormat::Zip),
     ))
     .atomic_err("expected a valid archive format");
 
-    let asset_content = choice::<AssetType, _>((
+    let strip_components = just("strip_components")
+        .ignore_then(s.critical_with_no_message())
+        .ignore_then(
+            filter(|c| c.is_ascii_digit())
+                .repeated()
+                .at_least(1)
+                .collect_string()
+                .and_then_or_str_err(|number| {
+                    number
+                        .parse::<usize>()
+                        .map_err(|err| format!("invalid number of components: {err}"))
+                })
+                .critical("expected a number of components to strip"),
+        );
+
+    // The format can be omitted to inherit the package's or repository's default one
+    let archive = just("archive")
+        .ignore_then(
+            char('(')
+                .ignore_then(
+                    choice::<(Option<ArchiveFormat>, Option<usize>), _>((
+                        archive_format.map(Some).then(
+                            char(',')
+                                .ignore_then(ms)
+                                .ignore_then(strip_components)
+                                .or_not(),
+                        ),
+                        strip_components.map(|strip_components| (None, Some(strip_components))),
+                    ))
+                    .critical("expected a valid archive format"),
+                )
+                .then_ignore(char(')').critical("expected a closing parenthesis"))
+                .or_not(),
+        )
+        .map(|params| params.unwrap_or((None, None)));
+
+    // Assets are paired with a boolean indicating if their archive format is missing
+    let asset_content = choice::<(AssetType, bool), _>((
         just("as")
             .ignore_then(s.critical_with_no_message())
-            .ignore_then(string.critical("expected a binary filename"))
-            .map(|copy_as| AssetType::Binary { copy_as }),
-        archive_format
+            .ignore_then(
+                string
+                    // Commas are also used to separate assets, so only consume the ones followed by a name
+                    .separated_by(char(',').padded_by(ms).followed_by(char('"')))
+                    .at_least(1)
+                    .critical("expected a binary filename"),
+            )
+            .map(|copy_as| (AssetType::Binary { copy_as }, false)),
+        archive
             .then_ignore(ms)
             .then_ignore(char('{').critical_with_no_message())
             .then(
-                single_file_extraction
+                archive_entry
                     .padded_by(msnl)
-                    .separated_by(char(','))
+                    .separated_by(list_separator(msnl, '}'))
                     .at_least(1)
+                    .then_ignore(trailing_comma(msnl))
                     .critical("expected at least one file extraction for the archive"),
             )
             .then_ignore(char('}').critical_with_no_message())
-            .map(|(format, files)| AssetType::Archive { format, files }),
+            .map(|((format, strip_components), entries)| {
+                let mut files = vec![];
+                let mut extra_files = vec![];
+
+                for entry in entries {
+                    match entry {
+                        ArchiveEntry::Binary(file) => files.push(file),
+                        ArchiveEntry::Extra(file) => extra_files.push(file),
+                    }
+                }
+
+                let typ = AssetType::Archive {
+                    // Replaced by the inherited format when missing (see [`fill_missing_formats`])
+                    format: format.unwrap_or(ArchiveFormat::TarGz),
+                    files,
+                    extra_files,
+                    strip_components: strip_components.unwrap_or(0),
+                };
+
+                (typ, format.is_none())
+            }),
     ));
 
     let direct_asset = platform
         .then_ignore(s.critical_with_no_message())
-        .then(string.critical("expected an URL"))
+        .then(
+            string
+                // Mirrors are separated by commas, like assets, so only consume the ones followed by an URL
+                .separated_by(char(',').padded_by(ms).followed_by(char('"')))
+                .at_least(1)
+                .critical("expected an URL"),
+        )
         .then_ignore(s.critical_with_no_message())
         .then(asset_content.critical("expected a file extraction"))
-        .map::<PlatformDependentEntry<(String, AssetType)>, _>(
-            |(((system, cpu_arch), asset_pattern), file_extraction)| {
-                PlatformDependentEntry::new(system, cpu_arch, (asset_pattern, file_extraction))
+        .map::<PlatformDependentEntry<(AssetUrls, AssetType, bool)>, _>(
+            |(((system, cpu_arch), urls), (typ, missing_format))| {
+                PlatformDependentEntry::new(
+                    system,
+                    cpu_arch,
+                    (AssetUrls(urls), typ, missing_format),
+                )
             },
         );
 
+    let minisign_key = just("minisign(")
+        .ignore_then(string.critical("expected a minisign public key"))
+        .then_ignore(char(')').critical_with_no_message());
+
+    let checksums_file = just("sha256sums(")
+        .ignore_then(string.critical("expected the URL of a checksums file"))
+        .then(
+            char(',')
+                .padded_by(ms)
+                .ignore_then(string.critical("expected the asset's name in the checksums file"))
+                .or_not(),
+        )
+        .then_ignore(char(')').critical_with_no_message())
+        .map(|(url, filename)| ChecksumsFile { url, filename });
+
     let direct_source_params = just("version")
         .critical_with_no_message()
         .ignore_then(char('(').critical_with_no_message())
         .ignore_then(string.critical("expected a hardcoded version string"))
         .then_ignore(char(')').critical_with_no_message())
         .then_ignore(s.critical_with_no_message())
+        .then(
+            minisign_key
+                .then_ignore(s.critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            checksums_file
+                .then_ignore(s.critical_with_no_message())
+                .or_not(),
+        )
         .then_ignore(char('{').critical_with_no_message())
         .then(
             direct_asset
                 .padded_by(msnl)
-                .separated_by(char(','))
+                .separated_by(list_separator(msnl, '}'))
                 .at_least(1)
+                .then_ignore(trailing_comma(msnl))
                 .critical("expected at least 1 downloadable asset")
-                .map(PlatformDependent::new),
+                .map(split_missing_formats),
         )
         .then_ignore(char('}').critical_with_no_message())
-        .map(|(hardcoded_version, urls)| DirectSource {
-            urls,
-            hardcoded_version,
-        });
+        .map(
+            |(((hardcoded_version, minisign_key), checksums), (urls, missing_formats))| {
+                let source = DirectSource {
+                    urls,
+                    hardcoded_version,
+                    minisign_key,
+                    checksums,
+                };
+
+                (source, missing_formats)
+            },
+        );
 
     let github_asset = platform
         .critical("expected a binary platform")
@@ -129,9 +310,9 @@ pub fn repository() -> impl Parser<Repository> {
         .then(pattern.critical("expected an asset pattern"))
         .then_ignore(s.critical_with_no_message())
         .then(asset_content.critical("expected a file extraction"))
-        .map::<PlatformDependentEntry<(Pattern, AssetType)>, _>(
-            |(((system, cpu_arch), asset_pattern), file_extraction)| {
-                PlatformDependentEntry::new(system, cpu_arch, (asset_pattern, file_extraction))
+        .map::<PlatformDependentEntry<(Pattern, AssetType, bool)>, _>(
+            |(((system, cpu_arch), asset_pattern), (typ, missing_format))| {
+                PlatformDependentEntry::new(system, cpu_arch, (asset_pattern, typ, missing_format))
             },
         );
 
@@ -159,19 +340,129 @@ pub fn repository() -> impl Parser<Repository> {
         )
         .then_ignore(char(')').critical_with_no_message())
         .then_ignore(ms)
+        .then(minisign_key.then_ignore(ms).or_not())
         .then_ignore(char('{').critical_with_no_message())
         .then(
             github_asset
                 .padded_by(msnl)
-                .separated_by(char(','))
-                .map(PlatformDependent::new),
+                .separated_by(list_separator(msnl, '}'))
+                .at_least(1)
+                .then_ignore(trailing_comma(msnl))
+                // Lists may be empty, but can't only contain a comma
+                .or_not()
+                .map(Option::unwrap_or_default)
+                .map(split_missing_formats),
+        )
+        .then_ignore(char('}').critical_with_no_message())
+        .map(
+            |((((author, repo_name), version), minisign_key), (asset, missing_formats))| {
+                let source = GithubSource {
+                    author,
+                    repo_name,
+                    version,
+                    asset,
+                    minisign_key,
+                };
+
+                (source, missing_formats)
+            },
+        );
+
+    let http_index_asset = platform
+        .critical("expected a binary platform")
+        .then_ignore(ms)
+        .then(string.critical("expected a JSON pointer to the asset's URL"))
+        .then_ignore(s.critical_with_no_message())
+        .then(asset_content.critical("expected a file extraction"))
+        .map::<PlatformDependentEntry<(JsonPointer, AssetType, bool)>, _>(
+            |(((system, cpu_arch), pointer), (typ, missing_format))| {
+                PlatformDependentEntry::new(
+                    system,
+                    cpu_arch,
+                    (JsonPointer(pointer), typ, missing_format),
+                )
+            },
+        );
+
+    let http_index_source_params = string
+        .critical("expected the URL of the release index")
+        .then_ignore(s.critical_with_no_message())
+        .then(
+            just("versioned(")
+                .ignore_then(string.critical("expected the URL of the versioned index"))
+                .then_ignore(char(')').critical_with_no_message())
+                .then_ignore(s.critical_with_no_message())
+                .or_not(),
+        )
+        .then_ignore(just("version(").critical_with_no_message())
+        .then(
+            string
+                .critical("expected a JSON pointer to the version")
+                .map(JsonPointer),
+        )
+        .then_ignore(char(')').critical_with_no_message())
+        .then_ignore(ms)
+        .then(minisign_key.then_ignore(ms).or_not())
+        .then_ignore(char('{').critical_with_no_message())
+        .then(
+            http_index_asset
+                .padded_by(msnl)
+                .separated_by(list_separator(msnl, '}'))
+                .at_least(1)
+                .then_ignore(trailing_comma(msnl))
+                .critical("expected at least 1 asset")
+                .map(split_missing_formats),
         )
         .then_ignore(char('}').critical_with_no_message())
-        .map(|(((author, repo_name), version), asset)| GithubSource {
-            author,
-            repo_name,
-            version,
-            asset,
+        .map(
+            |((((url, versioned_url), version), minisign_key), (asset, missing_formats))| {
+                let source = HttpIndexSource {
+                    url,
+                    versioned_url,
+                    version,
+                    asset,
+                    minisign_key,
+                };
+
+                (source, missing_formats)
+            },
+        );
+
+    // Dependencies can be restricted to some versions (e.g. `"ripgrep" >= 13`)
+    let version_constraint = choice::<ConstraintOp, _>((
+        just(">=").to(ConstraintOp::GreaterOrEqual),
+        just("<=").to(ConstraintOp::LowerOrEqual),
+        just("=").to(ConstraintOp::Exact),
+        just(">").to(ConstraintOp::Greater),
+        just("<").to(ConstraintOp::Lower),
+        just("^").to(ConstraintOp::Caret),
+        just("~").to(ConstraintOp::Tilde),
+    ))
+    .then_ignore(ms)
+    .then(
+        filter(|c| c.is_ascii_digit() || c == '.')
+            .repeated()
+            .at_least(1)
+            .collect_string()
+            .and_then_or_str_err(|version| match parse_version(&version) {
+                Some(_) => Ok(version),
+                None => Err(format!("Invalid version '{version}' in constraint")),
+            })
+            .critical("expected a valid version (e.g. '1.2.3')"),
+    )
+    .map(|(op, version)| VersionConstraint { op, version });
+
+    // Dependencies can also be restricted to a system (e.g. `windows: "foo"`)
+    let dependency = system
+        .then_ignore(char(':').critical_with_no_message())
+        .then_ignore(ms)
+        .or_not()
+        .then(string)
+        .then(ms.ignore_then(version_constraint).or_not())
+        .map(|((system, name), constraint)| Dependency {
+            name,
+            constraint,
+            system,
         });
 
     let package = string
@@ -179,39 +470,166 @@ pub fn repository() -> impl Parser<Repository> {
             s.ignore_then(just("(requires"))
                 .ignore_then(s.critical_with_no_message())
                 .ignore_then(
-                    string
-                        .separated_by(char(',').padded_by(ms))
+                    dependency
+                        .separated_by(list_separator(ms, ')'))
+                        .at_least(1)
+                        .then_ignore(trailing_comma(ms))
+                        .or_not()
+                        .map(Option::unwrap_or_default)
                         .critical("expected a list of dependencies"),
                 )
                 .then_ignore(char(')').critical_with_no_message())
                 .or_not(),
         )
+        .then(
+            s.ignore_then(just("(optionally"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(
+                    string
+                        .separated_by(list_separator(ms, ')'))
+                        .at_least(1)
+                        .then_ignore(trailing_comma(ms))
+                        .or_not()
+                        .map(Option::unwrap_or_default)
+                        .critical("expected a list of optional dependencies"),
+                )
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            s.ignore_then(just("(provides"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(
+                    string
+                        .separated_by(list_separator(ms, ')'))
+                        .at_least(1)
+                        .then_ignore(trailing_comma(ms))
+                        .or_not()
+                        .map(Option::unwrap_or_default)
+                        .critical("expected a list of virtual packages"),
+                )
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            s.ignore_then(just("(verify"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(string.critical("expected a verification command"))
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            s.ignore_then(just("(description"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(string.critical("expected a package description"))
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            s.ignore_then(just("(tags"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(
+                    string
+                        .separated_by(list_separator(ms, ')'))
+                        .at_least(1)
+                        .then_ignore(trailing_comma(ms))
+                        .or_not()
+                        .map(Option::unwrap_or_default)
+                        .critical("expected a list of tags"),
+                )
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            s.ignore_then(just("(deprecated"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(string.critical("expected a deprecation message"))
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(
+            s.ignore_then(just("(default_format"))
+                .ignore_then(s.critical_with_no_message())
+                .ignore_then(archive_format.critical_with_no_message())
+                .then_ignore(char(')').critical_with_no_message())
+                .or_not(),
+        )
+        .then(s.ignore_then(just("(no_exe_suffix)")).or_not())
         .then_ignore(char(':').critical_with_no_message())
         .then_ignore(msnl)
         .then(
-            choice::<DownloadSource, _>((
+            choice::<(DownloadSource, MissingFormats), _>((
                 just("Direct")
                     .ignore_then(s.critical_with_no_message())
                     .ignore_then(
                         direct_source_params
                             .critical("expected to find valid direct source parameters"),
                     )
-                    .map(DownloadSource::Direct),
+                    .map(|(params, missing_formats)| {
+                        (DownloadSource::Direct(params), missing_formats)
+                    }),
                 just("GitHub")
                     .ignore_then(s.critical_with_no_message())
                     .ignore_then(
                         github_source_params
                             .critical("expected to find valid GitHub source parameters"),
                     )
-                    .map(DownloadSource::GitHub),
+                    .map(|(params, missing_formats)| {
+                        (DownloadSource::GitHub(params), missing_formats)
+                    }),
+                just("HttpIndex")
+                    .ignore_then(s.critical_with_no_message())
+                    .ignore_then(
+                        http_index_source_params
+                            .critical("expected to find valid HTTP index source parameters"),
+                    )
+                    .map(|(params, missing_formats)| {
+                        (DownloadSource::HttpIndex(params), missing_formats)
+                    }),
             ))
             .critical("expected a valid download source"),
         )
-        .map(|((name, depends_on), source)| PackageManifest {
-            name,
-            depends_on: depends_on.unwrap_or_default(),
-            source,
-        });
+        .map(
+            |(
+                (
+                    (
+                        (
+                            (
+                                (
+                                    ((((name, depends_on), optional_deps), provides), verify),
+                                    description,
+                                ),
+                                tags,
+                            ),
+                            deprecated,
+                        ),
+                        default_format,
+                    ),
+                    no_exe_suffix,
+                ),
+                (mut source, mut missing_formats),
+            )| {
+                if let Some(default_format) = default_format {
+                    fill_missing_formats(&mut source, &missing_formats, default_format);
+                    missing_formats.clear();
+                }
+
+                let manifest = PackageManifest {
+                    name,
+                    depends_on: depends_on.unwrap_or_default(),
+                    optional_deps: optional_deps.unwrap_or_default(),
+                    provides: provides.unwrap_or_default(),
+                    verify,
+                    description,
+                    tags: tags.unwrap_or_default(),
+                    deprecated,
+                    no_exe_suffix: no_exe_suffix.is_some(),
+                    source,
+                };
+
+                (manifest, missing_formats)
+            },
+        );
 
     let name = just("name")
         .ignore_then(s.critical_with_no_message())
@@ -221,18 +639,33 @@ pub fn repository() -> impl Parser<Repository> {
         .ignore_then(s.critical_with_no_message())
         .ignore_then(string);
 
-    let newlines = newline().repeated().at_least(1);
+    let newlines = ms
+        .ignore_then(comment.or_not())
+        .ignore_then(newline())
+        .repeated()
+        .at_least(1);
+
+    let min_fetchy_version = just("min_fetchy_version")
+        .ignore_then(s.critical_with_no_message())
+        .ignore_then(string.critical("expected a version number"))
+        .then_ignore(newlines.critical_with_no_message());
+
+    let default_format = just("default_format")
+        .ignore_then(s.critical_with_no_message())
+        .ignore_then(archive_format.critical_with_no_message())
+        .then_ignore(newlines.critical_with_no_message());
 
+    let include = just("include")
+        .ignore_then(s.critical_with_no_message())
+        .ignore_then(string.critical("expected a path to include"))
+        .then_ignore(newlines.critical_with_no_message());
+
+    // Packages may all come from included files
     let packages = just("packages")
         .ignore_then(ms)
         .ignore_then(char('{').critical_with_no_message())
-        .ignore_then(
-            package
-                .padded_by(msnl)
-                .repeated_vec()
-                .at_least(1)
-                .critical("expected at least 1 package in repository"),
-        )
+        .ignore_then(package.padded_by(msnl).repeated_vec())
+        .then_ignore(msnl)
         .then_ignore(char('}').critical_with_no_message());
 
     let repository = name
@@ -240,17 +673,173 @@ pub fn repository() -> impl Parser<Repository> {
         .then_ignore(newlines.critical_with_no_message())
         .then(description.critical("expected a repository description"))
         .then_ignore(newlines.critical_with_no_message())
+        .then(min_fetchy_version.or_not())
+        .then(default_format.or_not())
+        .then(include.repeated_vec())
         .then(packages.critical("expected a list of packages"))
-        .map(|((name, description), packages)| Repository {
-            name,
-            description,
-            packages: packages
-                .into_iter()
-                .map(|pkg| (pkg.name.clone(), pkg))
-                .collect::<HashMap<_, _>>(),
+        .and_then_or_str_err(
+            |(
+                ((((name, description), min_fetchy_version), default_format), includes),
+                packages,
+            )| {
+                Ok(Repository {
+                    name,
+                    description,
+                    min_fetchy_version,
+                    includes,
+                    packages: resolve_default_format(packages, default_format)?,
+                })
+            },
+        );
+
+    // The default format only applies to the packages of the file it is declared in
+    let fragment = default_format
+        .or_not()
+        .then(include.repeated_vec())
+        .then(packages.critical("expected a list of packages"))
+        .and_then_or_str_err(|((default_format, includes), packages)| {
+            Ok(RepositoryFragment {
+                includes,
+                packages: resolve_default_format(packages, default_format)?,
+            })
         });
 
-    repository.padded_by(msnl).full()
+    (
+        repository.padded_by(msnl).full(),
+        fragment.padded_by(msnl).full(),
+    )
+}
+
+/// Platforms whose archive asset doesn't specify a format
+type MissingFormats = HashSet<(System, CpuArch)>;
+
+/// Separate the assets from the platforms whose archive format is missing
+fn split_missing_formats<T>(
+    entries: Vec<PlatformDependentEntry<(T, AssetType, bool)>>,
+) -> (PlatformDependent<(T, AssetType)>, MissingFormats) {
+    let mut missing_formats = MissingFormats::new();
+
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            let PlatformDependentEntry {
+                system,
+                cpu_arch,
+                value: (asset, typ, missing_format),
+            } = entry;
+
+            // When a platform is declared multiple times, the last entry wins
+            if missing_format {
+                missing_formats.insert((system, cpu_arch));
+            } else {
+                missing_formats.remove(&(system, cpu_arch));
+            }
+
+            PlatformDependentEntry::new(system, cpu_arch, (asset, typ))
+        })
+        .collect::<Vec<_>>();
+
+    (PlatformDependent::new(entries), missing_formats)
+}
+
+/// Set the format of the archives which don't specify one
+fn fill_missing_formats(
+    source: &mut DownloadSource,
+    missing_formats: &MissingFormats,
+    default_format: ArchiveFormat,
+) {
+    for platform in missing_formats {
+        let typ = match source {
+            DownloadSource::Direct(params) => params.urls.get_mut(platform).map(|(_, typ)| typ),
+            DownloadSource::GitHub(params) => params.asset.get_mut(platform).map(|(_, typ)| typ),
+            DownloadSource::HttpIndex(params) => params.asset.get_mut(platform).map(|(_, typ)| typ),
+        };
+
+        if let Some(AssetType::Archive {
+            format,
+            files: _,
+            extra_files: _,
+            strip_components: _,
+        }) = typ
+        {
+            *format = default_format;
+        }
+    }
+}
+
+/// Apply a file's default archive format to its packages, ensuring every archive has a format
+fn resolve_default_format(
+    packages: Vec<(PackageManifest, MissingFormats)>,
+    default_format: Option<ArchiveFormat>,
+) -> Result<HashMap<String, PackageManifest>, String> {
+    packages
+        .into_iter()
+        .map(|(mut manifest, missing_formats)| {
+            if !missing_formats.is_empty() {
+                let Some(default_format) = default_format else {
+                    let mut platforms = missing_formats
+                        .iter()
+                        .map(|(system, cpu_arch)| format!("{cpu_arch}, {system}"))
+                        .collect::<Vec<_>>();
+
+                    platforms.sort();
+
+                    return Err(format!(
+                        "Package '{}' has archives without a format (for platforms: {}), either specify it or declare a default format",
+                        manifest.name,
+                        platforms.join(" / ")
+                    ));
+                };
+
+                fill_missing_formats(&mut manifest.source, &missing_formats, default_format);
+            }
+
+            Ok((manifest.name.clone(), manifest))
+        })
+        .collect()
+}
+
+/// File extracted from an archive
+enum ArchiveEntry {
+    Binary(BinaryInArchive),
+    Extra(ExtraFileInArchive),
+}
+
+/// Non-empty string between double quotes, with escape sequences decoded by the provided parser
+fn quoted_string(
+    escape_sequence: impl Parser<(char, Option<char>)> + Copy,
+) -> impl Parser<String> + Copy {
+    char('"')
+        .ignore_then(
+            choice::<(char, Option<char>), _>((
+                escape_sequence,
+                filter(|c| c != '\n' && c != '\r' && c != '"' && c != '\\').map(|c| (c, None)),
+            ))
+            .repeated_vec()
+            .at_least(1)
+            .map(|chars| {
+                chars
+                    .into_iter()
+                    .flat_map(|(c, next)| std::iter::once(c).chain(next))
+                    .collect::<String>()
+            })
+            .critical("expected a string"),
+        )
+        .then_ignore(char('"').critical("expected a closing quote after the string"))
+}
+
+/// Separator for a list's items, which doesn't match the list's trailing comma (if any)
+fn list_separator<P>(padding: impl Parser<P> + Copy, closing: char) -> impl Parser<()> + Copy {
+    char(',')
+        .padded_by(padding)
+        .to(())
+        // Separators must be followed by an item
+        .not_followed_by(filter(move |c| c == closing || c == ','))
+}
+
+/// Optional trailing comma at the end of a list
+fn trailing_comma<P>(padding: impl Parser<P> + Copy) -> impl Parser<()> + Copy {
+    char(',').then_ignore(padding).to(()).or_not().to(())
 }
 
 // Usage: .debug(simple_debug) after any parser
@@ -258,3 +847,340 @@ pub fn repository() -> impl Parser<Repository> {
 fn simple_debug<T: std::fmt::Debug>(d: parsy::chainings::DebugType<'_, '_, T>) {
     println!("{d:#?}");
 }
+
+#[cfg(test)]
+mod tests {
+    use parsy::Parser;
+
+    use crate::{
+        repos::{
+            arch::{CpuArch, System},
+            ast::{DownloadSource, Repository},
+        },
+        sources::AssetType,
+    };
+
+    use super::repository;
+
+    fn parse(content: &str) -> Option<Repository> {
+        repository()
+            .parse_str(content)
+            .ok()
+            .map(|parsed| parsed.data)
+    }
+
+    fn direct_urls(repo: &Repository, pkg: &str) -> Vec<String> {
+        match &repo.packages[pkg].source {
+            DownloadSource::Direct(source) => {
+                source.urls[&(System::linux, CpuArch::x86_64)].0 .0.clone()
+            }
+            _ => panic!("package '{pkg}' doesn't use a direct source"),
+        }
+    }
+
+    #[test]
+    fn comments_between_packages() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+# Packages list
+packages {
+  # First package
+  "a": Direct version("1.0") { linux[x86_64] "https://example.com/a" as "a" }
+
+  # Second package
+  # (on two lines)
+  "b": Direct version("1.0") { linux[x86_64] "https://example.com/b" as "b" }
+  # Nothing after this
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(repo.packages.len(), 2);
+    }
+
+    #[test]
+    fn comments_inside_asset_lists() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": Direct version("1.0") {
+    # Mirrors come first
+    linux[x86_64] "https://example.com/a" as "a" # Trailing comment
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(direct_urls(&repo, "a"), ["https://example.com/a"]);
+    }
+
+    #[test]
+    fn comments_are_kept_in_strings() {
+        let repo = parse(
+            r#"name "test"
+description "Test # not a comment"
+packages {
+  "a": Direct version("1.0") { linux[x86_64] "https://example.com/a#fragment" as "a" }
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(repo.description, "Test # not a comment");
+        assert_eq!(direct_urls(&repo, "a"), ["https://example.com/a#fragment"]);
+    }
+
+    #[test]
+    fn comments_cant_split_a_package_declaration() {
+        assert!(parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": Direct # comment
+  version("1.0") { linux[x86_64] "https://example.com/a" as "a" }
+}
+"#,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn comments_after_header_lines() {
+        let repo = parse(
+            r#"name "test" # The repository's name
+description "Test" # What it contains
+packages {
+  "a": Direct version("1.0") { linux[x86_64] "https://example.com/a" as "a" }
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(repo.name, "test");
+        assert_eq!(repo.description, "Test");
+    }
+
+    #[test]
+    fn trailing_comma_in_archive_files() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": Direct version("1.0") {
+    linux[x86_64] "https://example.com/a.tgz" archive(TarGz) {
+      bin "/a$",
+      bin "/b$",
+    }
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let DownloadSource::Direct(source) = &repo.packages["a"].source else {
+            panic!("package 'a' doesn't use a direct source");
+        };
+
+        let AssetType::Archive { files, .. } = &source.urls[&(System::linux, CpuArch::x86_64)].1
+        else {
+            panic!("asset isn't an archive");
+        };
+
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn trailing_comma_in_direct_assets() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": Direct version("1.0") {
+    linux[x86_64] "https://example.com/a" as "a",
+    linux[aarch64] "https://example.com/a-arm" as "a",
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let DownloadSource::Direct(source) = &repo.packages["a"].source else {
+            panic!("package 'a' doesn't use a direct source");
+        };
+
+        assert_eq!(source.urls.len(), 2);
+    }
+
+    #[test]
+    fn trailing_comma_in_github_assets() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": GitHub "user/a" version(TagName) {
+    linux[x86_64] "a-x86_64$" as "a",
+    linux[aarch64] "a-aarch64$" as "a",
+  }
+}
+"#,
+        )
+        .unwrap();
+
+        let DownloadSource::GitHub(source) = &repo.packages["a"].source else {
+            panic!("package 'a' doesn't use a GitHub source");
+        };
+
+        assert_eq!(source.asset.len(), 2);
+    }
+
+    #[test]
+    fn trailing_comma_in_dependencies() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a" (requires "b", "c",): Direct version("1.0") { linux[x86_64] "https://example.com/a" as "a" }
+}
+"#,
+        )
+        .unwrap();
+
+        let deps = repo.packages["a"]
+            .depends_on
+            .iter()
+            .map(|dep| dep.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(deps, ["b", "c"]);
+    }
+
+    #[test]
+    fn repeated_or_lone_commas_are_rejected() {
+        for deps in [r#""b",, "c""#, r#""b", ,"#, ","] {
+            assert!(
+                parse(&format!(
+                    r#"name "test"
+description "Test"
+packages {{
+  "a" (requires {deps}): Direct version("1.0") {{ linux[x86_64] "https://example.com/a" as "a" }}
+}}
+"#
+                ))
+                .is_none(),
+                "dependencies list '{deps}' should be rejected"
+            );
+        }
+
+        assert!(parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": Direct version("1.0") { linux[x86_64] "https://example.com/a" as "a",, }
+}
+"#,
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn escape_sequences_in_strings() {
+        let repo = parse(
+            r#"name "test"
+description "A \"quoted\" word, a \\ backslash,\ta tab and a \. dot"
+packages {
+  "a": Direct version("1.0") { linux[x86_64] "https://example.com/a" as "a" }
+}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            repo.description,
+            "A \"quoted\" word, a \\ backslash,\ta tab and a \\. dot"
+        );
+    }
+
+    #[test]
+    fn escaped_quote_in_asset_pattern() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": GitHub "user/a" version(TagName) { linux[x86_64] "^a\"b\.tgz$" as "a" }
+}
+"#,
+        )
+        .unwrap();
+
+        let DownloadSource::GitHub(source) = &repo.packages["a"].source else {
+            panic!("package 'a' doesn't use a GitHub source");
+        };
+
+        let pattern = &source.asset[&(System::linux, CpuArch::x86_64)].0;
+
+        assert!(pattern.is_match("a\"b.tgz"));
+        assert!(!pattern.is_match("a\"bxtgz"));
+    }
+
+    #[test]
+    fn backslashes_are_kept_in_patterns() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": GitHub "user/a" version(TagName) { linux[x86_64] "^a\\b\d$" as "a" }
+}
+"#,
+        )
+        .unwrap();
+
+        let DownloadSource::GitHub(source) = &repo.packages["a"].source else {
+            panic!("package 'a' doesn't use a GitHub source");
+        };
+
+        let pattern = &source.asset[&(System::linux, CpuArch::x86_64)].0;
+
+        assert_eq!(pattern.as_str(), r"^a\\b\d$");
+        assert!(pattern.is_match(r"a\b1"));
+    }
+
+    #[test]
+    fn escape_sequences_are_only_decoded_outside_patterns() {
+        let repo = parse(
+            r#"name "test"
+description "Test"
+packages {
+  "a": GitHub "user/a" version(TagName) { linux[x86_64] "^a\n\tb$" as "a\n\tb" }
+}
+"#,
+        )
+        .unwrap();
+
+        let DownloadSource::GitHub(source) = &repo.packages["a"].source else {
+            panic!("package 'a' doesn't use a GitHub source");
+        };
+
+        let (pattern, asset_type) = &source.asset[&(System::linux, CpuArch::x86_64)];
+
+        assert_eq!(pattern.as_str(), r"^a\n\tb$");
+        assert!(pattern.is_match("a\n\tb"));
+        assert_eq!(asset_type.binaries(), ["a\n\tb"]);
+    }
+
+    #[test]
+    fn invalid_strings_are_rejected() {
+        for description in [r#""unterminated"#, r#""trailing backslash\"#, r#""""#] {
+            assert!(
+                parse(&format!(
+                    "name \"test\"\ndescription {description}\npackages {{\n  \"a\": Direct version(\"1.0\") {{ linux[x86_64] \"https://example.com/a\" as \"a\" }}\n}}\n"
+                ))
+                .is_none(),
+                "description {description} should be rejected"
+            );
+        }
+    }
+}
@@ -1,41 +1,115 @@
-use std::collections::{btree_map::Entry, BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use log::{info, warn};
+use rapidfuzz::distance::jaro_winkler::BatchComparator;
 
 use crate::{
     db::data::InstalledPackage,
-    repos::ast::{PackageManifest, Repository},
-    utils::join_iter,
+    repos::{
+        arch::System,
+        ast::{PackageManifest, Repository},
+        constraint::{VersionConstraint, VersionRange},
+    },
+    utils::{choose, join_iter},
 };
 
-pub fn resolve_pkg_by_name(
+/// Find a package in the repositories
+///
+/// The package can be prefixed with a repository name (`<repo>/<pkg>`), which takes
+/// precedence over the provided repository hint. Otherwise, name clashes between
+/// repositories are solved using their priority.
+pub fn resolve_pkg_by_name<'a>(
     name: impl AsRef<str>,
-    repos: &BTreeMap<String, Repository>,
-) -> Result<ResolvedPkg> {
-    let name = name.as_ref();
+    repos: &'a BTreeMap<String, Repository>,
+    priorities: &BTreeMap<String, i64>,
+    repo_hint: Option<&str>,
+) -> Result<ResolvedPkg<'a, 'a>> {
+    let (repo_hint, name) = match name.as_ref().split_once('/') {
+        Some((repo_name, name)) => (Some(repo_name), name),
+        None => (repo_hint, name.as_ref()),
+    };
+
+    if let Some(repo_name) = repo_hint {
+        let repository = repos
+            .get(repo_name)
+            .with_context(|| format!("Repository {} was not found", repo_name.bright_blue()))?;
+
+        let Some(manifest) = repository.packages.get(name) else {
+            let containing = repos
+                .values()
+                .filter(|repo| repo.packages.contains_key(name))
+                .map(|repo| format!("* {}", repo.name.bright_blue()))
+                .collect::<Vec<_>>();
+
+            if containing.is_empty() {
+                bail!(
+                    "Package {} was not found in repository {} (nor in any other repository){}",
+                    name.bright_yellow(),
+                    repo_name.bright_blue(),
+                    did_you_mean(name, repository.packages.keys())
+                );
+            }
+
+            bail!(
+                "Package {} was not found in repository {}, but exists in:\n\n{}",
+                name.bright_yellow(),
+                repo_name.bright_blue(),
+                join_iter(containing.into_iter(), "\n")
+            );
+        };
+
+        return Ok(ResolvedPkg {
+            manifest,
+            repository,
+            is_dep: false,
+            pinned_version: None,
+        });
+    }
 
     let mut candidates = repos
         .values()
-        .filter_map(|repo| repo.packages.get(name).map(|pkg| (pkg, repo)));
+        .filter_map(|repo| repo.packages.get(name).map(|pkg| (pkg, repo)))
+        .collect::<Vec<_>>();
 
-    let (manifest, repository) = candidates
-        .next()
-        .with_context(|| format!("Package {} was not found", name.bright_yellow()))?;
+    let priority = |repo: &Repository| priorities.get(&repo.name).copied().unwrap_or(0);
 
-    // This does not allocate if there are no clashing packages
-    let clashing = candidates.collect::<Vec<_>>();
+    // Highest priority first
+    candidates.sort_by_key(|(_, repo)| std::cmp::Reverse(priority(repo)));
 
-    if !clashing.is_empty() {
+    let Some(&(manifest, repository)) = candidates.first() else {
         bail!(
-            "Package {} exists in multiple repositories:\n\n{}",
+            "Package {} was not found{}",
             name.bright_yellow(),
-            join_iter(
-                clashing
-                    .into_iter()
-                    .map(|(_, repo)| format!("* {}", repo.name.bright_yellow())),
-                "\n"
-            )
+            did_you_mean(name, repos.values().flat_map(|repo| repo.packages.keys()))
+        );
+    };
+
+    if candidates.len() > 1 {
+        let clashing = candidates
+            .iter()
+            .filter(|(_, repo)| priority(repo) == priority(repository))
+            .collect::<Vec<_>>();
+
+        if clashing.len() > 1 {
+            bail!(
+                "Package {} exists in multiple repositories with the same priority:\n\n{}\n\nPrefix the package with the repository to use (e.g. {})",
+                name.bright_yellow(),
+                join_iter(
+                    clashing
+                        .iter()
+                        .map(|(_, repo)| format!("* {}", repo.name.bright_yellow())),
+                    "\n"
+                ),
+                format!("{}/{name}", clashing[0].1.name).bright_yellow()
+            );
+        }
+
+        info!(
+            "Package {} exists in multiple repositories, using the one from repository {} which has the highest priority",
+            name.bright_yellow(),
+            repository.name.bright_blue()
         );
     }
 
@@ -43,34 +117,59 @@ pub fn resolve_pkg_by_name(
         manifest,
         repository,
         is_dep: false,
+        pinned_version: None,
     })
 }
 
 pub fn resolve_pkgs_by_name<'a, S: AsRef<str>>(
     names: &[S],
     repos: &'a BTreeMap<String, Repository>,
+    priorities: &BTreeMap<String, i64>,
+    repo_hint: Option<&str>,
 ) -> Result<Vec<ResolvedPkg<'a, 'a>>> {
     names
         .iter()
-        .map(|name| resolve_pkg_by_name(name, repos))
+        .map(|name| resolve_pkg_by_name(name, repos, priorities, repo_hint))
         .collect::<Result<Vec<_>, _>>()
 }
 
-pub fn resolve_pkgs_by_name_with_deps<'a, S: AsRef<str>>(
+pub async fn resolve_pkgs_by_name_with_deps<'a, S: AsRef<str>>(
     names: &[S],
     repos: &'a BTreeMap<String, Repository>,
+    priorities: &BTreeMap<String, i64>,
+    repo_hint: Option<&str>,
+    with_optional: bool,
+    installed: &BTreeMap<String, InstalledPackage>,
+    target_system: System,
 ) -> Result<Vec<ResolvedPkg<'a, 'a>>> {
-    resolve_pkgs_with_deps(&resolve_pkgs_by_name(names, repos)?)
+    resolve_pkgs_with_deps(
+        &resolve_pkgs_by_name(names, repos, priorities, repo_hint)?,
+        with_optional,
+        true,
+        installed,
+        target_system,
+    )
+    .await
 }
 
+/// Resolve the provided packages along with their dependencies
+///
+/// Dependencies restricted to a system are only pulled when resolving for that system.
+///
+/// When a virtual dependency is provided by several packages, none of which is requested or installed,
+/// the user is asked to choose one if `choose_providers` is set, otherwise all of them are included.
 // TODO: show paths in errors
-pub fn resolve_pkgs_with_deps<
+pub async fn resolve_pkgs_with_deps<
     'a,
     // This bound is required as we return packages from the original list ('a)
     // but also from the provided repositories ('b)
     'b: 'a,
 >(
     pkgs: &[ResolvedPkg<'a, 'b>],
+    with_optional: bool,
+    choose_providers: bool,
+    installed: &BTreeMap<String, InstalledPackage>,
+    target_system: System,
 ) -> Result<Vec<ResolvedPkg<'a, 'b>>> {
     // List of packages to handle
     let mut queue = pkgs.iter().cloned().collect::<VecDeque<_>>();
@@ -79,6 +178,9 @@ pub fn resolve_pkgs_with_deps<
     // Used to detect conflicts when we need two packages with the same name but from different repositories
     let mut handled = BTreeMap::<&str, ResolvedPkg>::new();
 
+    // Version constraints put on each package, with the name of the package requiring them
+    let mut constraints = BTreeMap::<&str, Vec<(&str, &VersionConstraint)>>::new();
+
     // Process the queue, item by item
     // Each package is pushed to the output, and all its dependencies are queued
     // The `handled` variable ensures we don't push packages twice
@@ -87,6 +189,7 @@ pub fn resolve_pkgs_with_deps<
             manifest,
             repository,
             is_dep: _,
+            pinned_version: _,
         } = &resolved;
 
         match handled.entry(&manifest.name) {
@@ -104,10 +207,23 @@ pub fn resolve_pkgs_with_deps<
             Entry::Vacant(vacant) => {
                 vacant.insert(resolved);
 
-                for dep_name in &resolved.manifest.depends_on {
-                    if let Some(existing_pkg) =
-                        pkgs.iter().find(|pkg| pkg.manifest.name == *dep_name)
-                    {
+                let optional_deps = if with_optional {
+                    manifest.optional_deps.as_slice()
+                } else {
+                    &[]
+                };
+
+                let deps = manifest
+                    .depends_on
+                    .iter()
+                    .filter(|dep| dep.applies_to(target_system))
+                    .map(|dep| (&dep.name, dep.constraint.as_ref(), false))
+                    .chain(optional_deps.iter().map(|dep_name| (dep_name, None, true)));
+
+                for (dep_name, constraint, optional) in deps {
+                    let existing_pkg = pkgs.iter().find(|pkg| pkg.manifest.name == *dep_name);
+
+                    if let Some(existing_pkg) = existing_pkg {
                         if existing_pkg.repository.name != repository.name {
                             bail!(
                                     "Requested package {} from repository {} clashes with package {} which has a dependency of the same name but from repository {}",
@@ -119,28 +235,204 @@ pub fn resolve_pkgs_with_deps<
                         }
                     }
 
-                    let dep_manifest = repository.packages
-                            .get(dep_name)
-                            .with_context(|| format!(
-                                "Failed to find package {} which is a dependency of {} in repository {}",
-                                dep_name.bright_yellow(),
-                                manifest.name.bright_yellow(),
-                                repository.name.bright_blue()
-                            ))?;
-
-                    queue.push_back(ResolvedPkg {
-                        manifest: dep_manifest,
-                        repository,
-                        is_dep: true,
-                    });
+                    let dep_manifests = match repository.packages.get(dep_name) {
+                        Some(dep_manifest) => vec![dep_manifest],
+
+                        // The dependency may be a virtual package
+                        None => {
+                            find_providers(
+                                dep_name,
+                                manifest,
+                                repository,
+                                pkgs,
+                                &handled,
+                                installed,
+                                choose_providers,
+                            )
+                            .await?
+                        }
+                    };
+
+                    // Missing optional dependencies are simply skipped
+                    if optional && dep_manifests.is_empty() {
+                        warn!(
+                            "Optional dependency {} of package {} was not found in repository {}, skipping it",
+                            dep_name.bright_yellow(),
+                            manifest.name.bright_yellow(),
+                            repository.name.bright_blue()
+                        );
+
+                        continue;
+                    }
+
+                    if dep_manifests.is_empty() {
+                        bail!(
+                            "Failed to find package {} which is a dependency of {} in repository {}",
+                            dep_name.bright_yellow(),
+                            manifest.name.bright_yellow(),
+                            repository.name.bright_blue()
+                        );
+                    }
+
+                    for dep_manifest in dep_manifests {
+                        if let Some(constraint) = constraint {
+                            let requirements = constraints.entry(&dep_manifest.name).or_default();
+
+                            requirements.push((&manifest.name, constraint));
+
+                            check_constraints(&dep_manifest.name, requirements, None)?;
+                        }
+
+                        queue.push_back(ResolvedPkg {
+                            manifest: dep_manifest,
+                            repository,
+                            is_dep: true,
+                            // Dependencies which were requested as well keep their pinned version
+                            pinned_version: existing_pkg.and_then(|pkg| pkg.pinned_version),
+                        });
+                    }
                 }
             }
         }
     }
 
+    // Pinned versions are known in advance, so they can be checked right away
+    for (name, requirements) in &constraints {
+        if let Some(pinned_version) = handled[name].pinned_version {
+            check_constraints(name, requirements, Some(pinned_version))?;
+        }
+    }
+
     Ok(handled.into_values().collect())
 }
 
+/// Ensure at least one version of a package satisfies all the constraints put on it
+///
+/// If a version is provided, it is checked against the constraints instead.
+fn check_constraints(
+    name: &str,
+    requirements: &[(&str, &VersionConstraint)],
+    version: Option<&str>,
+) -> Result<()> {
+    let satisfiable = match version {
+        Some(version) => requirements
+            .iter()
+            .all(|(_, constraint)| constraint.matches(version) != Some(false)),
+
+        None => !requirements
+            .iter()
+            .fold(VersionRange::any(), |range, (_, constraint)| {
+                range.intersect(&constraint.range())
+            })
+            .is_empty(),
+    };
+
+    if satisfiable {
+        return Ok(());
+    }
+
+    let requirements = join_iter(
+        requirements.iter().map(|(dependent, constraint)| {
+            format!(
+                "* {} requires {} {}",
+                dependent.bright_yellow(),
+                name.bright_yellow(),
+                constraint.to_string().bright_cyan()
+            )
+        }),
+        "\n",
+    );
+
+    match version {
+        Some(version) => bail!(
+            "Version {} of package {} doesn't satisfy the requirements of the packages depending on it:\n\n{requirements}",
+            version.bright_cyan(),
+            name.bright_yellow()
+        ),
+
+        None => bail!(
+            "No version of package {} can satisfy the requirements of the packages depending on it:\n\n{requirements}",
+            name.bright_yellow()
+        ),
+    }
+}
+
+/// Find the packages to use as providers of a virtual package in a repository
+///
+/// When several packages provide it, the ones already requested or installed are preferred,
+/// otherwise the user is asked to choose one if `ask_user` is set, or all of them are returned.
+async fn find_providers<'b>(
+    virtual_name: &str,
+    dependent: &PackageManifest,
+    repository: &'b Repository,
+    requested: &[ResolvedPkg<'_, 'b>],
+    handled: &BTreeMap<&str, ResolvedPkg<'_, 'b>>,
+    installed: &BTreeMap<String, InstalledPackage>,
+    ask_user: bool,
+) -> Result<Vec<&'b PackageManifest>> {
+    let mut providers = repository
+        .packages
+        .values()
+        .filter(|manifest| manifest.provides.iter().any(|name| name == virtual_name))
+        .collect::<Vec<_>>();
+
+    providers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if providers.len() <= 1 {
+        return Ok(providers);
+    }
+
+    let is_selected = |name: &str| {
+        requested
+            .iter()
+            .chain(handled.get(name))
+            .any(|pkg| pkg.manifest.name == name && pkg.repository.name == repository.name)
+    };
+
+    if let Some(selected) = providers
+        .iter()
+        .find(|manifest| is_selected(&manifest.name))
+    {
+        return Ok(vec![selected]);
+    }
+
+    let installed_providers = providers
+        .iter()
+        .copied()
+        .filter(|manifest| {
+            installed
+                .get(&manifest.name)
+                .is_some_and(|installed| installed.repo_name == repository.name)
+        })
+        .collect::<Vec<_>>();
+
+    let candidates = match installed_providers.as_slice() {
+        [] => providers,
+        [installed] => return Ok(vec![installed]),
+        _ => installed_providers,
+    };
+
+    if !ask_user {
+        return Ok(candidates);
+    }
+
+    info!(
+        "Package {} depends on {} which is provided by multiple packages, please choose one:",
+        dependent.name.bright_yellow(),
+        virtual_name.bright_yellow()
+    );
+
+    let choice = choose(
+        &candidates
+            .iter()
+            .map(|manifest| &manifest.name)
+            .collect::<Vec<_>>(),
+    )
+    .await?;
+
+    Ok(vec![candidates[choice]])
+}
+
 pub fn resolve_installed_pkg<'a, 'b>(
     installed: &'a InstalledPackage,
     repos: &'b BTreeMap<String, Repository>,
@@ -157,6 +449,7 @@ pub fn resolve_installed_pkg<'a, 'b>(
         manifest: &installed.manifest,
         repository,
         is_dep: installed.installed_as_dep,
+        pinned_version: None,
     })
 }
 
@@ -181,13 +474,14 @@ pub fn resolve_installed_pkg_by_name<'a, 'b>(
         .get(name)
         .with_context(|| {
             format!(
-                "Package {} is not installed{}",
+                "Package {} is not installed{}{}",
                 name.bright_yellow(),
                 if repos.values().any(|repo| repo.packages.contains_key(name)) {
                     ""
                 } else {
-                    "(and does not exist in any registered repository)"
-                }
+                    " (and does not exist in any registered repository)"
+                },
+                did_you_mean(name, installed.keys())
             )
         })
         .and_then(|installed| {
@@ -206,11 +500,44 @@ pub fn resolve_installed_pkgs_by_name<'a, 'b>(
         .collect()
 }
 
+/// Maximum distance for a name to be suggested in place of a mistyped one
+static SUGGESTION_MAX_DISTANCE: f64 = 0.2;
+
+/// Suggest the names closest to a mistyped one, if any is close enough
+fn did_you_mean<'a>(name: &str, candidates: impl Iterator<Item = &'a String>) -> String {
+    let comparator = BatchComparator::new(name.chars());
+
+    let mut suggestions = candidates
+        .map(|candidate| (comparator.distance(candidate.chars()), candidate))
+        .filter(|(distance, _)| *distance <= SUGGESTION_MAX_DISTANCE)
+        .collect::<Vec<_>>();
+
+    if suggestions.is_empty() {
+        return String::new();
+    }
+
+    // The same name may come from multiple repositories
+    suggestions.sort_by(|(a_dist, a), (b_dist, b)| a_dist.total_cmp(b_dist).then_with(|| a.cmp(b)));
+    suggestions.dedup_by_key(|(_, candidate)| *candidate);
+
+    format!(
+        "\n\nDid you mean: {}?",
+        join_iter(
+            suggestions
+                .iter()
+                .take(3)
+                .map(|(_, candidate)| candidate.bright_yellow()),
+            ", "
+        )
+    )
+}
+
 pub fn refresh_pkg<'b>(resolved: ResolvedPkg<'_, 'b>) -> Result<ResolvedPkg<'b, 'b>> {
     let ResolvedPkg {
         manifest,
         repository,
         is_dep,
+        pinned_version: _,
     } = resolved;
 
     let manifest = repository.packages.get(&manifest.name).with_context(|| {
@@ -225,23 +552,125 @@ pub fn refresh_pkg<'b>(resolved: ResolvedPkg<'_, 'b>) -> Result<ResolvedPkg<'b,
         manifest,
         repository,
         is_dep,
+        pinned_version: None,
     })
 }
 
 pub fn build_pkgs_reverse_deps_map<'a>(
     pkgs: impl Iterator<Item = &'a PackageManifest>,
 ) -> HashMap<&'a str, HashSet<&'a str>> {
+    let pkgs = pkgs.collect::<Vec<_>>();
+
     let mut deps_map = HashMap::<&str, HashSet<&str>>::new();
 
-    for manifest in pkgs {
-        for dep in &manifest.depends_on {
-            deps_map.entry(dep).or_default().insert(&manifest.name);
+    for manifest in &pkgs {
+        for dep in manifest.all_deps() {
+            for satisfying in satisfying_pkgs(manifest, dep, &pkgs) {
+                deps_map
+                    .entry(satisfying)
+                    .or_default()
+                    .insert(&manifest.name);
+            }
         }
     }
 
     deps_map
 }
 
+/// Get the names of the packages satisfying a dependency among a list of packages
+///
+/// Virtual packages are satisfied by every package of the list providing them.
+pub fn satisfying_pkgs<'a>(
+    dependent: &PackageManifest,
+    dep: &'a str,
+    pkgs: &[&'a PackageManifest],
+) -> Vec<&'a str> {
+    if pkgs.iter().any(|manifest| manifest.name == dep) {
+        return vec![dep];
+    }
+
+    let providers = pkgs
+        .iter()
+        .filter(|manifest| manifest.name != dependent.name)
+        .filter(|manifest| manifest.provides.iter().any(|name| name == dep))
+        .map(|manifest| manifest.name.as_str())
+        .collect::<Vec<_>>();
+
+    if providers.is_empty() {
+        vec![dep]
+    } else {
+        providers
+    }
+}
+
+/// Sort a list of packages so that every package comes after its dependencies
+///
+/// Dependencies that are not part of the list are ignored. Packages which don't depend
+/// on each other keep their original relative order.
+pub fn sort_pkgs_by_deps<T>(
+    pkgs: Vec<T>,
+    manifest: impl Fn(&T) -> &PackageManifest,
+) -> Result<Vec<T>> {
+    let manifests = pkgs.iter().map(&manifest).collect::<Vec<_>>();
+
+    let positions = manifests
+        .iter()
+        .enumerate()
+        .map(|(i, manifest)| (manifest.name.as_str(), i))
+        .collect::<HashMap<_, _>>();
+
+    // Number of dependencies each package is waiting for
+    let mut pending_deps = vec![0; pkgs.len()];
+
+    // Packages to unlock once a package is handled
+    let mut dependents = vec![vec![]; pkgs.len()];
+
+    for (i, pkg) in manifests.iter().enumerate() {
+        for dep in pkg.all_deps() {
+            for satisfying in satisfying_pkgs(pkg, dep, &manifests) {
+                if let Some(&dep_pos) = positions.get(satisfying) {
+                    pending_deps[i] += 1;
+                    dependents[dep_pos].push(i);
+                }
+            }
+        }
+    }
+
+    let mut ready = (0..pkgs.len())
+        .filter(|&i| pending_deps[i] == 0)
+        .collect::<BTreeSet<_>>();
+
+    let mut order = Vec::with_capacity(pkgs.len());
+
+    while let Some(i) = ready.pop_first() {
+        order.push(i);
+
+        for &dependent in &dependents[i] {
+            pending_deps[dependent] -= 1;
+
+            if pending_deps[dependent] == 0 {
+                ready.insert(dependent);
+            }
+        }
+    }
+
+    if order.len() < pkgs.len() {
+        bail!(
+            "Found a dependency cycle between packages: {}",
+            join_iter(
+                (0..pkgs.len())
+                    .filter(|&i| pending_deps[i] > 0)
+                    .map(|i| manifest(&pkgs[i]).name.bright_yellow()),
+                ", "
+            )
+        );
+    }
+
+    let mut pkgs = pkgs.into_iter().map(Some).collect::<Vec<_>>();
+
+    Ok(order.into_iter().map(|i| pkgs[i].take().unwrap()).collect())
+}
+
 pub fn compute_no_longer_needed_deps<'a, 'b>(
     installed: &[(ResolvedPkg<'a, 'b>, &'a InstalledPackage)],
     uninstalling: &HashSet<&'a str>,
@@ -298,4 +727,7 @@ pub struct ResolvedPkg<'a, 'b> {
     pub manifest: &'a PackageManifest,
     pub repository: &'b Repository,
     pub is_dep: bool,
+
+    // Exact version to install instead of the latest one
+    pub pinned_version: Option<&'a str>,
 }
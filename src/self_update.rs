@@ -0,0 +1,241 @@
+//! Fetchy is updated like any package sourced from GitHub, except its binary replaces the running executable
+
+use std::{
+    cmp::Ordering,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use indicatif::ProgressBar;
+use log::{info, warn};
+use regex::Regex;
+
+use fetchy::{
+    http::ensure_online,
+    install::{download_assets_and, extract_asset, ExtractedAsset},
+    repos::{
+        arch::{CpuArch, PlatformDependent, PlatformDependentEntry, System},
+        ast::{DownloadSource, PackageManifest},
+        constraint::compare_versions,
+    },
+    sources::{
+        github::{GitHubVersionExtraction, GithubSource},
+        pattern::Pattern,
+        ArchiveFormat, AssetInfos, AssetType, BinaryInArchive,
+    },
+    utils::confirm,
+    validator::parse_version,
+};
+
+/// Public key release assets are signed with, provided when building Fetchy
+const FETCHY_MINISIGN_KEY: Option<&str> = option_env!("FETCHY_MINISIGN_KEY");
+
+pub async fn self_update() -> Result<()> {
+    ensure_online()?;
+
+    let Some(minisign_key) = FETCHY_MINISIGN_KEY else {
+        bail!(
+            "This build of Fetchy doesn't embed the public key its updates are signed with, so they can't be verified.\nPlease update it manually."
+        );
+    };
+
+    let manifest = fetchy_manifest(minisign_key);
+
+    let current_exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("Failed to get path to Fetchy's executable")?;
+
+    let asset_infos = manifest
+        .source
+        .fetch_infos(None)
+        .await
+        .context("Failed to fetch informations about Fetchy's latest release")?;
+
+    let current_version = env!("CARGO_PKG_VERSION");
+    let latest_version = asset_infos.version.trim_start_matches('v').to_owned();
+
+    let (Some(current), Some(latest)) = (
+        parse_version(current_version),
+        parse_version(&latest_version),
+    ) else {
+        bail!(
+            "Failed to compare current version {} with latest version {}",
+            current_version.bright_cyan(),
+            latest_version.bright_cyan()
+        );
+    };
+
+    // Local builds may be more recent than the latest release
+    if compare_versions(&latest, &current) != Ordering::Greater {
+        info!(
+            "Fetchy is already up to date (version {}, latest release is {})",
+            current_version.bright_cyan(),
+            latest_version.bright_cyan()
+        );
+
+        return Ok(());
+    }
+
+    warn!(
+        "Do you want to update Fetchy from version {} to version {}?",
+        current_version.bright_cyan(),
+        latest_version.bright_cyan()
+    );
+
+    if !confirm().await? {
+        return Ok(());
+    }
+
+    let (tmp_dir, _) = download_assets_and(
+        vec![(manifest, asset_infos)],
+        false,
+        current_exe,
+        replace_current_exe,
+    )
+    .await?;
+
+    tmp_dir
+        .close()
+        .context("Failed to remove temporary downloads directory")?;
+
+    info!(
+        "Successfully updated Fetchy to version {}!",
+        latest_version.bright_cyan()
+    );
+
+    Ok(())
+}
+
+fn fetchy_manifest(minisign_key: &str) -> PackageManifest {
+    let asset = |target: &str, bin: &str| {
+        (
+            Pattern(Regex::new(&format!("^fetchy-{target}\\.tgz$")).unwrap()),
+            AssetType::Archive {
+                format: ArchiveFormat::TarGz,
+                files: vec![BinaryInArchive {
+                    path_matcher: Pattern(Regex::new(&format!("(^|/){bin}$")).unwrap()),
+                    copy_as: None,
+                }],
+                extra_files: vec![],
+                strip_components: 0,
+            },
+        )
+    };
+
+    PackageManifest {
+        name: "fetchy".to_owned(),
+        source: DownloadSource::GitHub(GithubSource {
+            author: "ClementNerma".to_owned(),
+            repo_name: "Fetchy".to_owned(),
+            asset: PlatformDependent::new([
+                PlatformDependentEntry::new(
+                    System::linux,
+                    CpuArch::x86_64,
+                    asset("x86_64-unknown-linux-musl", "fetchy"),
+                ),
+                PlatformDependentEntry::new(
+                    System::linux,
+                    CpuArch::aarch64,
+                    asset("aarch64-unknown-linux-musl", "fetchy"),
+                ),
+                PlatformDependentEntry::new(
+                    System::windows,
+                    CpuArch::x86_64,
+                    asset("x86_64-pc-windows-gnu", "fetchy\\.exe"),
+                ),
+            ]),
+            version: GitHubVersionExtraction::TagName,
+            minisign_key: Some(minisign_key.to_owned()),
+        }),
+        depends_on: vec![],
+        optional_deps: vec![],
+        provides: vec![],
+        verify: None,
+        description: None,
+        tags: vec![],
+        deprecated: None,
+        no_exe_suffix: false,
+    }
+}
+
+async fn replace_current_exe(
+    _: PackageManifest,
+    asset_infos: AssetInfos,
+    asset_path: PathBuf,
+    current_exe: PathBuf,
+    pb: ProgressBar,
+) -> Result<()> {
+    let staging_dir = asset_path.with_extension("staging");
+    let extras_dir = asset_path.with_extension("extras");
+
+    tokio::task::spawn_blocking(move || {
+        fs::create_dir_all(&staging_dir).with_context(|| {
+            format!(
+                "Failed to create staging directory at path: {}",
+                staging_dir.display()
+            )
+        })?;
+
+        let ExtractedAsset {
+            binaries,
+            extra_files: _,
+        } = extract_asset(
+            "fetchy",
+            &asset_path,
+            &asset_infos.typ,
+            &staging_dir,
+            &extras_dir,
+            true,
+            pb,
+        )
+        .context("Failed to extract downloaded asset")?;
+
+        let [binary] = binaries.as_slice() else {
+            bail!("Expected Fetchy's release asset to contain a single binary");
+        };
+
+        replace_exe(&staging_dir.join(binary), &current_exe)
+    })
+    .await
+    .context("Failed to wait on Tokio task")?
+}
+
+/// Replace an executable, which may be running, by renaming the new one over it
+fn replace_exe(new_exe: &Path, exe: &Path) -> Result<()> {
+    // The new executable is first copied next to the current one, so the final rename doesn't cross filesystems
+    let tmp_path = exe.with_extension("new");
+
+    fs::copy(new_exe, &tmp_path).with_context(|| {
+        format!(
+            "Failed to copy new executable to path: {}",
+            tmp_path.display()
+        )
+    })?;
+
+    // Running executables can't be replaced on Windows, but they can be renamed
+    #[cfg(target_family = "windows")]
+    {
+        let old_path = exe.with_extension("old");
+
+        if old_path.exists() {
+            fs::remove_file(&old_path).with_context(|| {
+                format!(
+                    "Failed to remove previous executable at path: {}",
+                    old_path.display()
+                )
+            })?;
+        }
+
+        fs::rename(exe, &old_path).with_context(|| {
+            format!(
+                "Failed to move current executable to path: {}",
+                old_path.display()
+            )
+        })?;
+    }
+
+    fs::rename(&tmp_path, exe)
+        .with_context(|| format!("Failed to replace executable at path: {}", exe.display()))
+}
@@ -1,16 +1,36 @@
-use anyhow::Result;
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
 use reqwest::{header::HeaderMap, Url};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{repos::arch::PlatformDependent, validator::validate_asset_type};
+use crate::{
+    repos::arch::PlatformDependent,
+    validator::{validate_asset_type, validate_download_url, validate_minisign_key},
+};
 
-use super::{AssetInfos, AssetSource, AssetType};
+use super::{AssetChecksum, AssetInfos, AssetSignature, AssetSource, AssetType};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirectSource {
-    pub urls: PlatformDependent<(String, AssetType)>,
+    pub urls: PlatformDependent<(AssetUrls, AssetType)>,
     pub hardcoded_version: String,
+    pub minisign_key: Option<String>,
+
+    // Checksums file published alongside the assets
+    #[serde(default)]
+    pub checksums: Option<ChecksumsFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksumsFile {
+    pub url: String,
+
+    // Name of the asset inside the checksums file, defaults to the asset URL's file name
+    #[serde(default)]
+    pub filename: Option<String>,
 }
 
 impl AssetSource for DirectSource {
@@ -18,16 +38,34 @@ impl AssetSource for DirectSource {
         let Self {
             urls,
             hardcoded_version: _,
+            minisign_key,
+            checksums,
         } = self;
 
         let mut errors = vec![];
 
-        for (url, asset_typ) in urls.values() {
-            if let Err(err) = Url::parse(url) {
-                errors.push(format!(
-                    "Invalid asset URL {}: {err}",
-                    format!("{url:?}").bright_magenta()
-                ));
+        if let Some(minisign_key) = minisign_key {
+            validate_minisign_key(minisign_key, &mut errors);
+        }
+
+        if let Some(ChecksumsFile { url, filename }) = checksums {
+            validate_download_url("checksums file", url, &mut errors);
+
+            if filename
+                .as_ref()
+                .is_some_and(|filename| filename.is_empty())
+            {
+                errors.push("Asset's name in checksums file cannot be empty".to_owned());
+            }
+        }
+
+        for (AssetUrls(urls), asset_typ) in urls.values() {
+            if urls.is_empty() {
+                errors.push("Assets must have at least one URL".to_owned());
+            }
+
+            for url in urls {
+                validate_download_url("asset", url, &mut errors);
             }
 
             validate_asset_type(asset_typ, &mut errors);
@@ -36,19 +74,109 @@ impl AssetSource for DirectSource {
         errors
     }
 
-    async fn fetch_infos(&self) -> Result<AssetInfos> {
+    async fn fetch_infos(&self, version: Option<&str>) -> Result<AssetInfos> {
         let Self {
             urls,
             hardcoded_version,
+            minisign_key,
+            checksums,
         } = self;
 
-        let (url, content) = urls.get_for_current_platform()?;
+        if let Some(version) = version {
+            if version != hardcoded_version {
+                bail!(
+                    "Version {} is not available, this source only provides version {}",
+                    version.bright_cyan(),
+                    hardcoded_version.bright_cyan()
+                );
+            }
+        }
+
+        let (AssetUrls(urls), content) = urls.get_for_current_platform()?;
+
+        let Some((url, mirrors)) = urls.split_first() else {
+            bail!("Asset has no URL");
+        };
+
+        let checksum = match checksums {
+            None => None,
+
+            Some(ChecksumsFile {
+                url: checksums_url,
+                filename,
+            }) => {
+                let filename = match filename {
+                    Some(filename) => filename.clone(),
+                    None => Url::parse(url)
+                        .ok()
+                        .and_then(|url| {
+                            url.path_segments()
+                                .and_then(|mut segments| segments.next_back())
+                                .filter(|segment| !segment.is_empty())
+                                .map(str::to_owned)
+                        })
+                        .with_context(|| {
+                            format!(
+                                "Failed to determine the asset's file name from URL {}, please provide it explicitly",
+                                url.bright_magenta()
+                            )
+                        })?,
+                };
+
+                Some(AssetChecksum {
+                    url: checksums_url.clone(),
+                    filename,
+                })
+            }
+        };
 
         Ok(AssetInfos {
             url: url.clone(),
+            mirrors: mirrors.to_vec(),
             headers: HeaderMap::new(),
             version: hardcoded_version.clone(),
             typ: content.clone(),
+            signature: minisign_key.as_ref().map(|minisign_key| AssetSignature {
+                url: format!("{url}.minisig"),
+                minisign_key: minisign_key.clone(),
+            }),
+            checksum,
         })
     }
 }
+
+/// URLs of an asset, tried in order until one of them succeeds (the first one being the main one)
+#[derive(Debug, Clone)]
+pub struct AssetUrls(pub Vec<String>);
+
+impl fmt::Display for AssetUrls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(" | "))
+    }
+}
+
+// Assets with a single URL are represented as a string, for compatibility with repositories without mirrors
+impl Serialize for AssetUrls {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.as_slice() {
+            [url] => serializer.serialize_str(url),
+            urls => urls.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AssetUrls {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Urls {
+            Single(String),
+            Multiple(Vec<String>),
+        }
+
+        Ok(Self(match Urls::deserialize(deserializer)? {
+            Urls::Single(url) => vec![url],
+            Urls::Multiple(urls) => urls,
+        }))
+    }
+}
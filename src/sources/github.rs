@@ -1,17 +1,31 @@
-use std::{env, sync::LazyLock};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env, fmt,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{LazyLock, OnceLock},
+    time::Duration,
+};
 
 use anyhow::{bail, Context, Result};
-use log::debug;
+use jiff::{SignedDuration, Timestamp};
+use log::{debug, warn};
 use regex::Regex;
 use reqwest::{
     header::{self, HeaderMap, HeaderName, HeaderValue},
-    Client, StatusCode,
+    StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::{fs, process::Command, sync::Mutex};
+
+use crate::{
+    http::{acquire_network_slot, http_client, is_offline, send_with_retries},
+    repos::arch::PlatformDependent,
+    utils::join_iter,
+    validator::{validate_asset_type, validate_minisign_key},
 };
-use serde::{Deserialize, Serialize};
-
-use crate::{repos::arch::PlatformDependent, utils::join_iter, validator::validate_asset_type};
 
-use super::{pattern::Pattern, AssetInfos, AssetSource, AssetType};
+use super::{pattern::Pattern, AssetInfos, AssetSignature, AssetSource, AssetType};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GithubSource {
@@ -19,6 +33,7 @@ pub struct GithubSource {
     pub repo_name: String,
     pub asset: PlatformDependent<(Pattern, AssetType)>,
     pub version: GitHubVersionExtraction,
+    pub minisign_key: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -29,6 +44,230 @@ pub enum GitHubVersionExtraction {
 
 static NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new("^[A-Za-z0-9_.-]+$").unwrap());
 
+/// Base URL of GitHub's API, unless another one is configured
+static DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+
+static GITHUB_API_URL: OnceLock<String> = OnceLock::new();
+
+/// Use another base URL for GitHub's API (e.g. `https://github.example.com/api/v3` for GitHub Enterprise)
+pub fn init_github_api_url(url: &str) -> Result<()> {
+    let parsed = Url::parse(url).with_context(|| format!("Invalid GitHub API URL: {url}"))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("GitHub API URL must use HTTP or HTTPS: {url}");
+    }
+
+    assert!(
+        GITHUB_API_URL
+            .set(url.trim_end_matches('/').to_owned())
+            .is_ok(),
+        "GitHub API URL was initialized twice"
+    );
+
+    Ok(())
+}
+
+/// Base URL of the GitHub API in use
+pub fn github_api_url() -> &'static str {
+    GITHUB_API_URL
+        .get()
+        .map_or(DEFAULT_GITHUB_API_URL, String::as_str)
+}
+
+/// URL of the GraphQL API, which GitHub Enterprise serves next to the versioned REST API
+fn github_graphql_url() -> String {
+    let api_url = github_api_url();
+
+    format!("{}/graphql", api_url.strip_suffix("/v3").unwrap_or(api_url))
+}
+
+/// Where to look for the GitHub access token
+///
+/// Sources are consulted in this order: the command-line flag, the `FETCHY_GITHUB_TOKEN`
+/// environment variable, the configuration file's token for the API's base URL, its global
+/// token, and finally the credential helper.
+#[derive(Default)]
+pub struct GitHubTokenSources {
+    pub from_flag: Option<String>,
+    pub from_config: Option<String>,
+
+    // Tokens from the configuration file, keyed by API base URL
+    pub per_host: BTreeMap<String, String>,
+
+    // Command printing the token on its standard output
+    pub helper: Option<String>,
+}
+
+static GITHUB_TOKEN_SOURCES: OnceLock<GitHubTokenSources> = OnceLock::new();
+
+// Resolved lazily, as the credential helper is only worth running when calling the API
+static GITHUB_TOKENS: LazyLock<Mutex<HashMap<String, Option<String>>>> =
+    LazyLock::new(Default::default);
+
+/// Maximum duration of the credential helper command
+static TOKEN_HELPER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Set the sources of the GitHub access token to use for API calls
+pub fn init_github_token(sources: GitHubTokenSources) {
+    assert!(
+        GITHUB_TOKEN_SOURCES.set(sources).is_ok(),
+        "GitHub token was initialized twice"
+    );
+}
+
+/// Get the access token to use for an API, if any
+async fn github_token(base_url: &str) -> Result<Option<String>> {
+    let mut tokens = GITHUB_TOKENS.lock().await;
+
+    if let Some(token) = tokens.get(base_url) {
+        return Ok(token.clone());
+    }
+
+    let token = match configured_token(base_url) {
+        Some((token, _)) => Some(token),
+        None => match &GITHUB_TOKEN_SOURCES.get_or_init(Default::default).helper {
+            Some(helper) => run_token_helper(helper, base_url).await?,
+            None => None,
+        },
+    };
+
+    tokens.insert(base_url.to_owned(), token.clone());
+
+    Ok(token)
+}
+
+/// Where the GitHub access token comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitHubTokenSource {
+    Flag,
+    Env,
+    PerHost,
+    Config,
+    Helper,
+}
+
+impl fmt::Display for GitHubTokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitHubTokenSource::Flag => write!(f, "--github-token flag"),
+            GitHubTokenSource::Env => write!(f, "FETCHY_GITHUB_TOKEN environment variable"),
+            GitHubTokenSource::PerHost => write!(f, "'github_tokens' configuration entry"),
+            GitHubTokenSource::Config => write!(f, "'github_token' configuration entry"),
+            GitHubTokenSource::Helper => write!(f, "credential helper"),
+        }
+    }
+}
+
+/// Get the token provided for an API without running the credential helper, along with its source
+fn configured_token(base_url: &str) -> Option<(String, GitHubTokenSource)> {
+    let GitHubTokenSources {
+        from_flag,
+        from_config,
+        per_host,
+        helper: _,
+    } = GITHUB_TOKEN_SOURCES.get_or_init(Default::default);
+
+    [
+        (from_flag.clone(), GitHubTokenSource::Flag),
+        (env::var("FETCHY_GITHUB_TOKEN").ok(), GitHubTokenSource::Env),
+        (
+            per_host.get(base_url.trim_end_matches('/')).cloned(),
+            GitHubTokenSource::PerHost,
+        ),
+        (from_config.clone(), GitHubTokenSource::Config),
+    ]
+    .into_iter()
+    .find_map(|(token, source)| {
+        token
+            .filter(|token| !token.is_empty())
+            .map(|token| (token, source))
+    })
+}
+
+/// Get where the token for GitHub's API would come from, without revealing it
+///
+/// The credential helper isn't run, so it is reported even if it doesn't provide any token.
+pub fn github_token_source() -> Option<GitHubTokenSource> {
+    configured_token(github_api_url())
+        .map(|(_, source)| source)
+        .or_else(|| {
+            GITHUB_TOKEN_SOURCES
+                .get_or_init(Default::default)
+                .helper
+                .is_some()
+                .then_some(GitHubTokenSource::Helper)
+        })
+}
+
+/// Run the credential helper command, which prints the token to use for an API
+///
+/// The API's base URL is provided in the `FETCHY_GITHUB_API_URL` environment variable.
+async fn run_token_helper(helper: &str, base_url: &str) -> Result<Option<String>> {
+    let args = shell_words::split(helper)
+        .with_context(|| format!("Failed to split GitHub token helper command: {helper}"))?;
+
+    let (program, args) = args
+        .split_first()
+        .context("GitHub token helper command is empty")?;
+
+    debug!("Running GitHub token helper: {helper}");
+
+    let child = Command::new(program)
+        .args(args)
+        .env("FETCHY_GITHUB_API_URL", base_url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run GitHub token helper: {helper}"))?;
+
+    let output = tokio::time::timeout(TOKEN_HELPER_TIMEOUT, child.wait_with_output())
+        .await
+        .with_context(|| {
+            format!(
+                "GitHub token helper timed out after {} seconds: {helper}",
+                TOKEN_HELPER_TIMEOUT.as_secs()
+            )
+        })?
+        .with_context(|| format!("Failed to wait on GitHub token helper: {helper}"))?;
+
+    if !output.status.success() {
+        bail!(
+            "GitHub token helper failed ({}): {helper}\n\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let token = String::from_utf8(output.stdout)
+        .context("GitHub token helper returned an invalid UTF-8 output")?;
+
+    let token = token.trim();
+
+    Ok((!token.is_empty()).then(|| token.to_owned()))
+}
+
+static GITHUB_CACHE: OnceLock<GitHubCache> = OnceLock::new();
+
+/// Responses fetched more recently than this are reused without contacting the API
+static GITHUB_CACHE_TTL: SignedDuration = SignedDuration::from_mins(5);
+
+struct GitHubCache {
+    dir: PathBuf,
+    refresh: bool,
+}
+
+/// Set the directory API responses are cached into
+///
+/// When `refresh` is set, cached responses are ignored (but still updated).
+pub fn init_github_cache(dir: PathBuf, refresh: bool) {
+    assert!(
+        GITHUB_CACHE.set(GitHubCache { dir, refresh }).is_ok(),
+        "GitHub cache was initialized twice"
+    );
+}
+
 static GITHUB_BASE_HEADERS: LazyLock<HeaderMap> = LazyLock::new(|| {
     HeaderMap::from_iter([
         (
@@ -46,10 +285,15 @@ impl AssetSource for GithubSource {
             repo_name,
             asset,
             version: _,
+            minisign_key,
         } = self;
 
         let mut errors = vec![];
 
+        if let Some(minisign_key) = minisign_key {
+            validate_minisign_key(minisign_key, &mut errors);
+        }
+
         if !NAME_REGEX.is_match(author) {
             errors.push(format!(
                 "Author name {author:?} contains invalid character(s)"
@@ -69,41 +313,50 @@ impl AssetSource for GithubSource {
         errors
     }
 
-    async fn fetch_infos(&self) -> Result<AssetInfos> {
+    async fn fetch_infos(&self, version: Option<&str>) -> Result<AssetInfos> {
         let Self {
             author,
             repo_name,
             asset,
-            version,
+            version: version_extraction,
+            minisign_key,
         } = self;
 
         let (asset_pattern, asset_content) = asset.get_for_current_platform()?;
 
-        let mut headers = GITHUB_BASE_HEADERS.clone();
-
-        if let Some(access_token) = env::var("FETCHY_GITHUB_TOKEN")
-            .ok()
-            .filter(|token| !token.is_empty())
-        {
-            headers.append(
-                "Authorization",
-                HeaderValue::from_str(&format!("Bearer {access_token}"))
-                    .context("Failed to use access token as a header value")?,
-            );
-        }
-
-        let release = fetch_latest_release(author, repo_name, headers.clone())
+        let headers = github_headers().await?;
+
+        let release = match version {
+            None => fetch_latest_release(author, repo_name, headers.clone())
+                .await
+                .with_context(|| {
+                    format!("Failed to fetch latest release of repo '{author}/{repo_name}'")
+                })?,
+
+            Some(version) => fetch_release_with_version(
+                author,
+                repo_name,
+                version,
+                *version_extraction,
+                headers.clone(),
+            )
             .await
             .with_context(|| {
-                format!("Failed to fetch latest release of repo '{author}/{repo_name}'")
-            })?;
+                format!("Failed to fetch release '{version}' of repo '{author}/{repo_name}'")
+            })?,
+        };
 
         if release.assets.is_empty() {
-            bail!("No asset found in latest release in repo {author}/{repo_name}");
+            bail!("No asset found in release in repo {author}/{repo_name}");
         }
 
-        let (filtered_assets, non_matching_assets) = release
-            .assets
+        // Signature files are not candidates for the asset itself
+        let (signature_assets, assets) =
+            release.assets.into_iter().partition::<Vec<_>, _>(|asset| {
+                minisign_key.is_some() && asset.name.ends_with(".minisig")
+            });
+
+        let (filtered_assets, non_matching_assets) = assets
             .into_iter()
             .partition::<Vec<_>, _>(|asset| asset_pattern.is_match(&asset.name));
 
@@ -128,7 +381,29 @@ impl AssetSource for GithubSource {
             )
         })?;
 
-        let version = match version {
+        let signature = match minisign_key {
+            None => None,
+
+            Some(minisign_key) => {
+                let signature_name = format!("{}.minisig", asset.name);
+
+                let signature_asset = signature_assets
+                    .into_iter()
+                    .find(|asset| asset.name == signature_name)
+                    .with_context(|| {
+                        format!(
+                            "No signature asset '{signature_name}' found in release of repo {author}/{repo_name}"
+                        )
+                    })?;
+
+                Some(AssetSignature {
+                    url: signature_asset.browser_download_url,
+                    minisign_key: minisign_key.clone(),
+                })
+            }
+        };
+
+        let version = match version_extraction {
             GitHubVersionExtraction::TagName => release.tag_name,
             GitHubVersionExtraction::ReleaseTitle => {
                 release.name.context("Fetched released has no title")?
@@ -137,53 +412,493 @@ impl AssetSource for GithubSource {
 
         Ok(AssetInfos {
             url: asset.browser_download_url,
+            mirrors: vec![],
             headers,
             version,
             typ: asset_content.clone(),
+            signature,
+            checksum: None,
         })
     }
 }
 
+/// Headers to send to GitHub's API, including the access token if there is one
+async fn github_headers() -> Result<HeaderMap<HeaderValue>> {
+    let mut headers = GITHUB_BASE_HEADERS.clone();
+
+    if let Some(access_token) = github_token(github_api_url()).await? {
+        headers.append(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {access_token}"))
+                .context("Failed to use access token as a header value")?,
+        );
+    }
+
+    Ok(headers)
+}
+
+fn latest_release_url(author: &str, repo_name: &str) -> String {
+    format!(
+        "{}/repos/{author}/{repo_name}/releases/latest",
+        github_api_url()
+    )
+}
+
+/// Maximum number of releases GitHub's API returns at once
+static RELEASES_PER_PAGE: usize = 100;
+
 async fn fetch_latest_release(
     author: &str,
     repo_name: &str,
     headers: HeaderMap<HeaderValue>,
 ) -> Result<GitHubRelease> {
-    let url = format!("https://api.github.com/repos/{author}/{repo_name}/releases/latest");
+    let url = latest_release_url(author, repo_name);
+
+    if let Some(release) = PREFETCHED_RELEASES.lock().await.get(&url) {
+        debug!("Using prefetched release for: {url}");
+        return Ok(release.clone());
+    }
 
     debug!("Fetching latest release from: {url}");
 
-    let resp = Client::new()
-        .get(url)
-        .headers(headers)
-        .send()
+    fetch_github_api(&url, headers).await
+}
+
+/// Maximum number of repositories queried in a single GraphQL request
+static GRAPHQL_BATCH_SIZE: usize = 50;
+
+// Latest releases fetched in advance through the GraphQL API, keyed by their REST API URL
+static PREFETCHED_RELEASES: LazyLock<Mutex<HashMap<String, GitHubRelease>>> =
+    LazyLock::new(Default::default);
+
+/// Fetch the latest release of multiple repositories at once using GitHub's GraphQL API
+///
+/// This replaces one REST API call per package with a single request, which greatly reduces
+/// the pressure on the rate limit. Releases which can't be fetched this way are left to the
+/// REST API, so failures are not fatal.
+pub async fn prefetch_latest_releases<'a>(sources: impl Iterator<Item = &'a GithubSource>) {
+    let sources = sources.collect::<Vec<_>>();
+
+    // Not worth it for a single package
+    if sources.len() < 2 || is_offline() {
+        return;
+    }
+
+    let repos = sources
+        .iter()
+        .map(|source| (source.author.as_str(), source.repo_name.as_str()))
+        .collect::<BTreeSet<_>>();
+
+    if let Err(err) = try_prefetch_latest_releases(repos).await {
+        debug!("Failed to prefetch latest releases, falling back to the REST API: {err:?}");
+    }
+}
+
+async fn try_prefetch_latest_releases(repos: BTreeSet<(&str, &str)>) -> Result<()> {
+    let mut to_fetch = vec![];
+
+    for (author, repo_name) in repos {
+        let url = latest_release_url(author, repo_name);
+
+        if PREFETCHED_RELEASES.lock().await.contains_key(&url) {
+            continue;
+        }
+
+        // Fresh cached responses don't require any request
+        if let Some(GitHubCache {
+            dir,
+            refresh: false,
+        }) = GITHUB_CACHE.get()
+        {
+            if let Some(cached) = read_cached_response(&dir.join(cache_file_name(&url))).await {
+                if Timestamp::now().duration_since(cached.fetched_at) < GITHUB_CACHE_TTL {
+                    continue;
+                }
+            }
+        }
+
+        to_fetch.push((author, repo_name, url));
+    }
+
+    if to_fetch.is_empty() {
+        return Ok(());
+    }
+
+    let mut headers = github_headers().await?;
+
+    if !headers.contains_key(header::AUTHORIZATION) {
+        bail!("GitHub's GraphQL API requires an access token");
+    }
+
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    let graphql_url = github_graphql_url();
+
+    for chunk in to_fetch.chunks(GRAPHQL_BATCH_SIZE) {
+        let mut query = String::from("query {");
+
+        for (i, (author, repo_name, _)) in chunk.iter().enumerate() {
+            query.push_str(&format!(
+                " r{i}: repository(owner: {}, name: {}) {{ latestRelease {{ name tagName releaseAssets(first: 100) {{ pageInfo {{ hasNextPage }} nodes {{ name downloadUrl }} }} }} }}",
+                serde_json::to_string(author)?,
+                serde_json::to_string(repo_name)?
+            ));
+        }
+
+        query.push_str(" }");
+
+        debug!(
+            "Fetching latest release of {} repositories from: {graphql_url}",
+            chunk.len()
+        );
+
+        let body = serde_json::to_string(&serde_json::json!({ "query": query }))
+            .context("Failed to serialize GraphQL query")?;
+
+        let _network_slot = acquire_network_slot().await;
+
+        let resp = send_with_retries(
+            http_client()
+                .post(&graphql_url)
+                .headers(headers.clone())
+                .body(body),
+            |attempt, max| warn!("Request to {graphql_url} failed, retrying ({attempt}/{max})..."),
+        )
         .await
-        .with_context(|| {
-            format!("Failed to fetch latest release of repo '{author}/{repo_name}'")
-        })?;
+        .with_context(|| format!("Failed to fetch URL: {graphql_url}"))?;
+
+        let status = resp.status();
+
+        let text = resp
+            .text()
+            .await
+            .context("Failed to decode response as text")?;
+
+        if status != StatusCode::OK {
+            bail!("Server returned an error:\n{text}");
+        }
+
+        // Repositories which failed (e.g. not found) are reported as errors alongside the data
+        let GraphQLResponse { data } =
+            serde_json::from_str(&text).context("Failed to parse response as JSON")?;
+
+        let mut data = data.unwrap_or_default();
+
+        let mut prefetched = PREFETCHED_RELEASES.lock().await;
+
+        for (i, (_, _, url)) in chunk.iter().enumerate() {
+            let Some(Some(GraphQLRepository {
+                latest_release: Some(release),
+            })) = data.remove(&format!("r{i}"))
+            else {
+                continue;
+            };
+
+            let GraphQLRelease {
+                name,
+                tag_name,
+                release_assets,
+            } = release;
+
+            if release_assets.page_info.has_next_page {
+                continue;
+            }
+
+            let release = GitHubRelease {
+                name,
+                tag_name,
+                assets: release_assets
+                    .nodes
+                    .into_iter()
+                    .map(
+                        |GraphQLReleaseAsset { name, download_url }| GitHubReleaseAsset {
+                            browser_download_url: download_url,
+                            name,
+                        },
+                    )
+                    .collect(),
+            };
+
+            if let Some(GitHubCache { dir, refresh: _ }) = GITHUB_CACHE.get() {
+                let cache_path = dir.join(cache_file_name(url));
+
+                // Keep the ETag of the cached response if the release didn't change,
+                // so the API can still be asked whether it was modified later on
+                let etag = read_cached_response(&cache_path)
+                    .await
+                    .filter(|cached| {
+                        serde_json::from_str::<GitHubRelease>(&cached.body)
+                            .is_ok_and(|cached| cached == release)
+                    })
+                    .and_then(|cached| cached.etag);
+
+                let cached = CachedResponse {
+                    etag,
+                    fetched_at: Timestamp::now(),
+                    body: serde_json::to_string(&release)
+                        .context("Failed to serialize prefetched release")?,
+                };
+
+                // Failing to cache a response shouldn't prevent the operation from succeeding
+                if let Err(err) = write_cached_response(&cache_path, &cached).await {
+                    warn!("Failed to cache response from {url}: {err:?}");
+                }
+            }
+
+            prefetched.insert(url.clone(), release);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct GraphQLResponse {
+    data: Option<HashMap<String, Option<GraphQLRepository>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLRepository {
+    latest_release: Option<GraphQLRelease>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLRelease {
+    name: Option<String>,
+    tag_name: String,
+    release_assets: GraphQLReleaseAssets,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLReleaseAssets {
+    page_info: GraphQLPageInfo,
+    nodes: Vec<GraphQLReleaseAsset>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLPageInfo {
+    has_next_page: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQLReleaseAsset {
+    name: String,
+    download_url: String,
+}
+
+async fn fetch_release_with_version(
+    author: &str,
+    repo_name: &str,
+    version: &str,
+    version_extraction: GitHubVersionExtraction,
+    headers: HeaderMap<HeaderValue>,
+) -> Result<GitHubRelease> {
+    match version_extraction {
+        GitHubVersionExtraction::TagName => {
+            let url = format!(
+                "{}/repos/{author}/{repo_name}/releases/tags/{version}",
+                github_api_url()
+            );
+
+            debug!("Fetching release from: {url}");
+
+            fetch_github_api(&url, headers).await
+        }
+
+        GitHubVersionExtraction::ReleaseTitle => {
+            // Releases can only be listed by pages, so look through them until the right one is found
+            for page in 1.. {
+                let url = format!(
+                    "{}/repos/{author}/{repo_name}/releases?per_page={RELEASES_PER_PAGE}&page={page}",
+                    github_api_url()
+                );
+
+                debug!("Fetching releases list from: {url}");
+
+                let releases =
+                    fetch_github_api::<Vec<GitHubRelease>>(&url, headers.clone()).await?;
+
+                let is_last_page = releases.len() < RELEASES_PER_PAGE;
+
+                if let Some(release) = releases
+                    .into_iter()
+                    .find(|release| release.name.as_deref() == Some(version))
+                {
+                    return Ok(release);
+                }
+
+                if is_last_page {
+                    break;
+                }
+            }
+
+            bail!("No release found with title '{version}'")
+        }
+    }
+}
+
+async fn fetch_github_api<T: DeserializeOwned>(
+    url: &str,
+    mut headers: HeaderMap<HeaderValue>,
+) -> Result<T> {
+    let cache_path = GITHUB_CACHE
+        .get()
+        .map(|cache| cache.dir.join(cache_file_name(url)));
+
+    let cached = match (&cache_path, GITHUB_CACHE.get()) {
+        (Some(cache_path), Some(GitHubCache { refresh: false, .. })) => {
+            read_cached_response(cache_path).await
+        }
+        _ => None,
+    };
+
+    if let Some(cached) = &cached {
+        // Outdated responses are better than nothing when the network can't be accessed
+        if is_offline() || Timestamp::now().duration_since(cached.fetched_at) < GITHUB_CACHE_TTL {
+            debug!("Using cached response for: {url}");
+            return serde_json::from_str(&cached.body).context("Failed to parse response as JSON");
+        }
+
+        if let Some(etag) = &cached.etag {
+            headers.insert(
+                header::IF_NONE_MATCH,
+                HeaderValue::from_str(etag).context("Invalid cached ETag")?,
+            );
+        }
+    }
+
+    let resp = send_with_retries(http_client().get(url).headers(headers), |attempt, max| {
+        warn!("Request to {url} failed, retrying ({attempt}/{max})...")
+    })
+    .await
+    .with_context(|| format!("Failed to fetch URL: {url}"))?;
 
     let status = resp.status();
 
-    let text = resp
-        .text()
-        .await
-        .context("Failed to decode response as text")?;
+    let etag = resp
+        .headers()
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_owned);
+
+    let (etag, body) = match cached {
+        Some(cached) if status == StatusCode::NOT_MODIFIED => {
+            debug!("Cached response is still up to date for: {url}");
+
+            // "Not modified" responses aren't required to repeat the ETag
+            (etag.or(cached.etag), cached.body)
+        }
+
+        _ => {
+            let text = resp
+                .text()
+                .await
+                .context("Failed to decode response as text")?;
+
+            if status != StatusCode::OK {
+                bail!("Server returned an error:\n{text}");
+            }
+
+            (etag, text)
+        }
+    };
+
+    let parsed = serde_json::from_str(&body).context("Failed to parse response as JSON")?;
+
+    if let Some(cache_path) = cache_path {
+        let cached = CachedResponse {
+            etag,
+            fetched_at: Timestamp::now(),
+            body,
+        };
 
-    if status != StatusCode::OK {
-        bail!("Server returned an error:\n{text}");
+        // Failing to cache a response shouldn't prevent the operation from succeeding
+        if let Err(err) = write_cached_response(&cache_path, &cached).await {
+            warn!("Failed to cache response from {url}: {err:?}");
+        }
     }
 
-    serde_json::from_str(&text).context("Failed to parse response as JSON")
+    Ok(parsed)
 }
 
 #[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: Option<String>,
+    fetched_at: Timestamp,
+    body: String,
+}
+
+/// Escape an URL into a valid file name (on all platforms)
+fn cache_file_name(url: &str) -> String {
+    let url = url
+        .strip_prefix(DEFAULT_GITHUB_API_URL)
+        .and_then(|url| url.strip_prefix('/'))
+        .unwrap_or(url);
+
+    let mut name = String::with_capacity(url.len());
+
+    for byte in url.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.') {
+            name.push(char::from(byte));
+        } else {
+            name.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    name.push_str(".json");
+    name
+}
+
+/// Read a cached response, treating unreadable or corrupted entries as missing
+async fn read_cached_response(path: &Path) -> Option<CachedResponse> {
+    let content = fs::read_to_string(path).await.ok()?;
+
+    match serde_json::from_str(&content) {
+        Ok(cached) => Some(cached),
+        Err(err) => {
+            debug!(
+                "Ignoring invalid cache file at path {}: {err}",
+                path.display()
+            );
+
+            None
+        }
+    }
+}
+
+async fn write_cached_response(path: &Path, cached: &CachedResponse) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await.with_context(|| {
+            format!(
+                "Failed to create cache directory at path: {}",
+                parent.display()
+            )
+        })?;
+    }
+
+    let content = serde_json::to_string(cached).context("Failed to serialize cached response")?;
+
+    fs::write(path, content)
+        .await
+        .with_context(|| format!("Failed to write cache file at path: {}", path.display()))
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct GitHubRelease {
     name: Option<String>,
     assets: Vec<GitHubReleaseAsset>,
     tag_name: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct GitHubReleaseAsset {
     browser_download_url: String,
     name: String,
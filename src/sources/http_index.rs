@@ -0,0 +1,226 @@
+//! Generic source for forges without a dedicated integration (e.g. Codeberg, SourceHut)
+//!
+//! Releases are described by a JSON index, from which the version and the assets' URLs
+//! are extracted using JSON pointers (RFC 6901, e.g. `/latest/assets/0/url`).
+
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use log::{debug, warn};
+use reqwest::{header::HeaderMap, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    http::{http_client, send_with_retries},
+    repos::arch::PlatformDependent,
+    validator::{validate_asset_type, validate_download_url, validate_minisign_key},
+};
+
+use super::{AssetInfos, AssetSignature, AssetSource, AssetType};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpIndexSource {
+    // Index describing the latest release
+    pub url: String,
+
+    // Index describing a specific release, `{version}` being replaced by the requested version
+    #[serde(default)]
+    pub versioned_url: Option<String>,
+
+    // Location of the release's version in the index
+    pub version: JsonPointer,
+
+    // Location of each platform's asset URL in the index
+    pub asset: PlatformDependent<(JsonPointer, AssetType)>,
+
+    pub minisign_key: Option<String>,
+}
+
+/// Location of a value inside a JSON document (RFC 6901)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JsonPointer(pub String);
+
+impl JsonPointer {
+    pub fn validate(&self) -> Result<(), String> {
+        let Self(pointer) = self;
+
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(format!(
+                "JSON pointer {} must start with a '/'",
+                format!("{pointer:?}").bright_cyan()
+            ));
+        }
+
+        // '~' is only allowed in the '~0' and '~1' escape sequences
+        let mut chars = pointer.chars();
+
+        while let Some(c) = chars.next() {
+            if c == '~' && !matches!(chars.next(), Some('0' | '1')) {
+                return Err(format!(
+                    "JSON pointer {} contains an invalid escape sequence (only '~0' and '~1' are allowed)",
+                    format!("{pointer:?}").bright_cyan()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AssetSource for HttpIndexSource {
+    fn validate(&self) -> Vec<String> {
+        let Self {
+            url,
+            versioned_url,
+            version,
+            asset,
+            minisign_key,
+        } = self;
+
+        let mut errors = vec![];
+
+        validate_download_url("index", url, &mut errors);
+
+        if let Some(versioned_url) = versioned_url {
+            validate_download_url("versioned index", versioned_url, &mut errors);
+
+            if !versioned_url.contains("{version}") {
+                errors.push(format!(
+                    "Versioned index URL {} doesn't contain the {} placeholder",
+                    format!("{versioned_url:?}").bright_magenta(),
+                    "{version}".bright_cyan()
+                ));
+            }
+        }
+
+        if let Some(minisign_key) = minisign_key {
+            validate_minisign_key(minisign_key, &mut errors);
+        }
+
+        if let Err(err) = version.validate() {
+            errors.push(err);
+        }
+
+        for (pointer, asset_typ) in asset.values() {
+            if let Err(err) = pointer.validate() {
+                errors.push(err);
+            }
+
+            validate_asset_type(asset_typ, &mut errors);
+        }
+
+        errors
+    }
+
+    async fn fetch_infos(&self, version: Option<&str>) -> Result<AssetInfos> {
+        let Self {
+            url,
+            versioned_url,
+            version: version_pointer,
+            asset,
+            minisign_key,
+        } = self;
+
+        let (asset_pointer, asset_content) = asset.get_for_current_platform()?;
+
+        let index_url = match (version, versioned_url) {
+            (Some(version), Some(versioned_url)) => versioned_url.replace("{version}", version),
+            (_, _) => url.clone(),
+        };
+
+        let index = fetch_index(&index_url).await?;
+
+        let found_version = lookup(&index, version_pointer, "version", &index_url)?;
+
+        if let Some(version) = version {
+            if found_version != version {
+                bail!(
+                    "Version {} is not available, index {} only provides version {}",
+                    version.bright_cyan(),
+                    index_url.bright_magenta(),
+                    found_version.bright_cyan()
+                );
+            }
+        }
+
+        let asset_url = lookup(&index, asset_pointer, "asset URL", &index_url)?;
+
+        // Relative URLs are resolved against the index's URL
+        let asset_url = Url::parse(&index_url)
+            .and_then(|base| base.join(&asset_url))
+            .with_context(|| {
+                format!(
+                    "Invalid asset URL {} in index {}",
+                    asset_url.bright_magenta(),
+                    index_url.bright_magenta()
+                )
+            })?
+            .to_string();
+
+        Ok(AssetInfos {
+            signature: minisign_key.as_ref().map(|minisign_key| AssetSignature {
+                url: format!("{asset_url}.minisig"),
+                minisign_key: minisign_key.clone(),
+            }),
+            url: asset_url,
+            mirrors: vec![],
+            headers: HeaderMap::new(),
+            version: found_version,
+            typ: asset_content.clone(),
+            checksum: None,
+        })
+    }
+}
+
+async fn fetch_index(url: &str) -> Result<Value> {
+    debug!("Fetching release index from: {url}");
+
+    let resp = send_with_retries(http_client().get(url), |attempt, max| {
+        warn!("Request to {url} failed, retrying ({attempt}/{max})...")
+    })
+    .await
+    .with_context(|| format!("Failed to fetch URL: {url}"))?;
+
+    let status = resp.status();
+
+    let text = resp
+        .text()
+        .await
+        .context("Failed to decode response as text")?;
+
+    if !status.is_success() {
+        bail!("Server returned an error ({status}):\n{text}");
+    }
+
+    serde_json::from_str(&text).context("Failed to parse release index as JSON")
+}
+
+/// Get a string (or number) from the index
+fn lookup(index: &Value, pointer: &JsonPointer, what: &str, index_url: &str) -> Result<String> {
+    let value = index.pointer(&pointer.0).with_context(|| {
+        format!(
+            "No {what} found at {} in index {}",
+            pointer.to_string().bright_cyan(),
+            index_url.bright_magenta()
+        )
+    })?;
+
+    match value {
+        Value::String(string) => Ok(string.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        _ => bail!(
+            "Value at {} in index {} should be a string, found: {value}",
+            pointer.to_string().bright_cyan(),
+            index_url.bright_magenta()
+        ),
+    }
+}
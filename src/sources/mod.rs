@@ -1,6 +1,8 @@
+use std::{fmt, future::Future};
+
 use anyhow::Result;
 use reqwest::header::{HeaderMap, HeaderValue};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 
 use crate::ast_friendly;
 
@@ -8,41 +10,140 @@ use self::pattern::Pattern;
 
 pub mod direct;
 pub mod github;
+pub mod http_index;
 pub mod pattern;
 
 pub trait AssetSource: Serialize + DeserializeOwned {
     fn validate(&self) -> Vec<String>;
-    async fn fetch_infos(&self) -> Result<AssetInfos>;
+    fn fetch_infos(&self, version: Option<&str>)
+        -> impl Future<Output = Result<AssetInfos>> + Send;
 }
 
 #[derive(Debug, Clone)]
 pub struct AssetInfos {
     pub url: String,
+
+    // Fallback URLs, tried in order if downloading from the main one fails
+    // Their signature (if any) is expected next to them, with a `.minisig` suffix
+    pub mirrors: Vec<String>,
+
     pub headers: HeaderMap<HeaderValue>,
     pub version: String,
     pub typ: AssetType,
+    pub signature: Option<AssetSignature>,
+    pub checksum: Option<AssetChecksum>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetSignature {
+    pub url: String,
+    pub minisign_key: String,
+}
+
+/// Checksums file (e.g. `SHA256SUMS`) containing the expected SHA-256 digest of an asset
+#[derive(Debug, Clone)]
+pub struct AssetChecksum {
+    pub url: String,
+
+    // Name of the asset inside the checksums file
+    pub filename: String,
 }
 
 ast_friendly! {
     pub enum AssetType {
         Binary {
-            copy_as: String,
+            // The binary is copied under each of these names
+            #[serde(deserialize_with = "deserialize_binary_names")]
+            copy_as: Vec<String>,
         },
         Archive {
             format: ArchiveFormat,
             files: Vec<BinaryInArchive>,
+
+            // Completions and man pages
+            #[serde(default)]
+            extra_files: Vec<ExtraFileInArchive>,
+
+            // Number of leading path components removed from the archive's entries (like `tar --strip-components`)
+            #[serde(default)]
+            strip_components: usize,
         },
     }
 
-    #[derive(Copy)]
+    #[derive(Copy, PartialEq, Eq)]
     pub enum ArchiveFormat {
         TarGz,
         TarXz,
+        TarBz2,
+        TarZst,
         Zip,
     }
 
     pub struct BinaryInArchive {
         pub path_matcher: Pattern,
-        pub copy_as: String,
+
+        // Defaults to the basename of the matched file
+        pub copy_as: Option<String>,
+    }
+
+    pub struct ExtraFileInArchive {
+        pub path_matcher: Pattern,
+        pub kind: ExtraFileKind,
+    }
+
+    #[derive(PartialEq, Eq)]
+    pub enum ExtraFileKind {
+        Completion { shell: String },
+        Man,
+    }
+}
+
+impl AssetType {
+    /// Names of the binaries this asset provides
+    ///
+    /// Binaries named after the file they are extracted from are only known after extraction,
+    /// so they are not included here.
+    pub fn binaries(&self) -> Vec<&str> {
+        match self {
+            AssetType::Binary { copy_as } => copy_as.iter().map(String::as_str).collect(),
+            AssetType::Archive {
+                format: _,
+                files,
+                extra_files: _,
+                strip_components: _,
+            } => files
+                .iter()
+                .filter_map(|bin| bin.copy_as.as_deref())
+                .collect(),
+        }
+    }
+}
+
+/// Accept either a single binary name or a list of names
+fn deserialize_binary_names<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BinaryNames {
+        Single(String),
+        Multiple(Vec<String>),
+    }
+
+    Ok(match BinaryNames::deserialize(deserializer)? {
+        BinaryNames::Single(name) => vec![name],
+        BinaryNames::Multiple(names) => names,
+    })
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveFormat::TarGz => write!(f, "TarGz"),
+            ArchiveFormat::TarXz => write!(f, "TarXz"),
+            ArchiveFormat::TarBz2 => write!(f, "TarBz2"),
+            ArchiveFormat::TarZst => write!(f, "TarZst"),
+            ArchiveFormat::Zip => write!(f, "Zip"),
+        }
     }
 }
@@ -1,12 +1,55 @@
 use std::{ops::Deref, str::FromStr};
 
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Required as 'regex' doesn't support serde
 #[derive(Debug, Clone)]
 pub struct Pattern(pub Regex);
 
+/// Inline flag making a regular expression case-insensitive
+///
+/// Case-insensitive patterns from repositories are stored with this prefix, so they
+/// keep their behaviour when serialized.
+pub static CASE_INSENSITIVE_FLAG: &str = "(?i)";
+
+impl Pattern {
+    pub fn new(regex: &str, case_insensitive: bool) -> Result<Self, regex::Error> {
+        RegexBuilder::new(regex)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map(Self)
+    }
+
+    /// Build a pattern from a glob, where `*` matches any sequence of characters and `?` any single character
+    ///
+    /// Globs without any wildcard match any string containing them.
+    pub fn from_glob(glob: &str, case_insensitive: bool) -> Self {
+        if !glob.contains(['*', '?']) {
+            return Self::new(&regex::escape(glob), case_insensitive).unwrap();
+        }
+
+        let mut regex = String::from("^");
+
+        for c in glob.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                _ => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+
+        regex.push('$');
+
+        Self::new(&regex, case_insensitive).unwrap()
+    }
+
+    /// Source of the pattern without the case-insensitivity flag, if any
+    pub fn strip_case_insensitive_flag(&self) -> Option<&str> {
+        self.as_str().strip_prefix(CASE_INSENSITIVE_FLAG)
+    }
+}
+
 impl Deref for Pattern {
     type Target = Regex;
 
@@ -1,10 +1,21 @@
-use std::{borrow::Cow, fmt::Display, sync::LazyLock, time::Duration};
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock, OnceLock,
+    },
+    time::Duration,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use comfy_table::Table;
 use dialoguer::Select;
 use indicatif::{ProgressBar, ProgressStyle};
+use jiff::{Timestamp, Zoned};
+use log::info;
 use std::fmt::Write;
-use tokio::task::JoinSet;
+use tokio::{sync::watch, task::JoinSet};
 
 pub static PROGRESS_BAR_TICK_CHARS: &str = "##-";
 
@@ -18,7 +29,7 @@ pub static ITEMS_PROGRESS_BAR_STYLE: LazyLock<ProgressStyle> = LazyLock::new(||
 
 pub static BYTES_PROGRESS_BAR_STYLE: LazyLock<ProgressStyle> = LazyLock::new(|| {
     ProgressStyle::with_template(
-        "{spinner:.green} {prefix}[{bar:40.cyan/blue}] {bytes:>10}/{total_bytes:>10} ({eta:>3}) {msg}",
+        "{spinner:.green} {prefix}[{bar:40.cyan/blue}] {bytes:>10}/{total_bytes:>10} {bytes_per_sec:>12} ({eta:>3}) {msg}",
     )
     .unwrap().progress_chars(PROGRESS_BAR_TICK_CHARS)
 });
@@ -31,6 +42,16 @@ pub fn progress_bar(
     style: ProgressStyle,
     msg: impl Into<Cow<'static, str>>,
 ) -> ProgressBar {
+    if !progress_bars_enabled() {
+        let msg = msg.into();
+
+        if !msg.is_empty() {
+            info!("{msg}");
+        }
+
+        return ProgressBar::hidden().with_message(msg);
+    }
+
     let pb = ProgressBar::new(len.try_into().unwrap())
         .with_style(style)
         .with_message(msg);
@@ -41,18 +62,119 @@ pub fn progress_bar(
     pb
 }
 
-pub async fn confirm() -> Result<bool> {
-    tokio::task::spawn_blocking(|| {
+static NO_PROGRESS_BARS: AtomicBool = AtomicBool::new(false);
+
+/// Replace progress bars with occasional log messages
+pub fn disable_progress_bars() {
+    NO_PROGRESS_BARS.store(true, Ordering::Relaxed);
+}
+
+pub fn progress_bars_enabled() -> bool {
+    !NO_PROGRESS_BARS.load(Ordering::Relaxed)
+}
+
+static NO_COLORS: AtomicBool = AtomicBool::new(false);
+
+/// Disable all styling (messages, tables and progress bars)
+pub fn disable_colors() {
+    NO_COLORS.store(true, Ordering::Relaxed);
+
+    colored::control::set_override(false);
+    console::set_colors_enabled(false);
+    console::set_colors_enabled_stderr(false);
+}
+
+/// Create a table, which is only styled if colors are enabled
+pub fn new_table() -> Table {
+    let mut table = Table::new();
+
+    if NO_COLORS.load(Ordering::Relaxed) {
+        table.force_no_tty();
+    }
+
+    table
+}
+
+static SKIP_CONFIRMATIONS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_skip_confirmations(skip: bool) {
+    SKIP_CONFIRMATIONS.store(skip, Ordering::Relaxed);
+}
+
+/// Answers questions asked to the user
+///
+/// Library consumers may provide their own implementation with [`set_prompter`]
+pub trait Prompter: Send + Sync {
+    /// Ask the user whether the current operation should continue
+    fn confirm(&self) -> Result<bool>;
+
+    /// Ask the user to choose between several items, returning the chosen item's index
+    fn choose(&self, items: &[String]) -> Result<usize>;
+}
+
+/// Default prompter, which interacts with the user through the terminal
+pub struct TerminalPrompter;
+
+impl Prompter for TerminalPrompter {
+    fn confirm(&self) -> Result<bool> {
         Select::new()
             .items(&["Continue", "Abort"])
             .interact()
             .map(|choice| choice == 0)
             .context("Failed to get user choice")
-    })
-    .await
-    .context("Failed to wait on Tokio task")
-    .flatten()
-    .inspect(|_| println!())
+            .inspect(|_| println!())
+    }
+
+    fn choose(&self, items: &[String]) -> Result<usize> {
+        Select::new()
+            .items(items)
+            .default(0)
+            .interact()
+            .context("Failed to get user choice")
+            .inspect(|_| println!())
+    }
+}
+
+static PROMPTER: OnceLock<Box<dyn Prompter>> = OnceLock::new();
+
+/// Replace the default terminal prompter
+///
+/// Must be called before any question is asked to the user
+pub fn set_prompter(prompter: impl Prompter + 'static) -> Result<()> {
+    PROMPTER
+        .set(Box::new(prompter))
+        .map_err(|_| anyhow!("Prompter was already set"))
+}
+
+fn prompter() -> &'static dyn Prompter {
+    PROMPTER.get_or_init(|| Box::new(TerminalPrompter)).as_ref()
+}
+
+pub async fn confirm() -> Result<bool> {
+    if SKIP_CONFIRMATIONS.load(Ordering::Relaxed) {
+        return Ok(true);
+    }
+
+    tokio::task::spawn_blocking(|| prompter().confirm())
+        .await
+        .context("Failed to wait on Tokio task")
+        .flatten()
+}
+
+/// Ask the user to choose between several items
+///
+/// The first item is picked if confirmations are skipped
+pub async fn choose(items: &[impl ToString]) -> Result<usize> {
+    if SKIP_CONFIRMATIONS.load(Ordering::Relaxed) {
+        return Ok(0);
+    }
+
+    let items = items.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    tokio::task::spawn_blocking(move || prompter().choose(&items))
+        .await
+        .context("Failed to wait on Tokio task")
+        .flatten()
 }
 
 pub async fn join_fallible_set<T: 'static>(mut tasks: JoinSet<Result<T>>) -> Result<Vec<T>> {
@@ -79,6 +201,60 @@ pub async fn join_fallible_set<T: 'static>(mut tasks: JoinSet<Result<T>>) -> Res
     Ok(results)
 }
 
+/// Same as [`join_fallible_ordered_set`], but tasks are never aborted
+///
+/// If the user hits Ctrl-C or any of the tasks fails, the others are asked to stop through the
+/// provided channel instead, so they can finish the operations which can't be safely interrupted.
+/// All tasks are waited for before returning.
+pub async fn join_cancellable_ordered_set<T: 'static>(
+    mut tasks: JoinSet<Result<(usize, T)>>,
+    cancel: watch::Sender<bool>,
+) -> Result<Vec<T>> {
+    let interrupted = async {
+        // If the handler can't be registered, the default behaviour (exiting) is kept
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::pin!(interrupted);
+
+    let mut results = Vec::with_capacity(tasks.len());
+    let mut first_err = None;
+
+    loop {
+        let result = tokio::select! {
+            result = tasks.join_next() => match result {
+                Some(result) => result,
+                None => break,
+            },
+
+            () = &mut interrupted, if first_err.is_none() => {
+                cancel.send_replace(true);
+                first_err = Some(anyhow!("Interrupted by user"));
+                continue;
+            }
+        };
+
+        match result.context("Failed to join Tokio task").flatten() {
+            Ok(result) => results.push(result),
+
+            Err(err) => {
+                cancel.send_replace(true);
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+
+    if let Some(err) = first_err {
+        return Err(err);
+    }
+
+    results.sort_by_key(|(pos, _)| *pos);
+
+    Ok(results.into_iter().map(|(_, value)| value).collect())
+}
+
 pub async fn join_fallible_ordered_set<T: 'static>(
     tasks: JoinSet<Result<(usize, T)>>,
 ) -> Result<Vec<T>> {
@@ -111,3 +287,26 @@ pub fn join_iter<D: Display>(mut iter: impl Iterator<Item = D>, sep: &str) -> St
         }
     }
 }
+
+/// Describe how long ago a date was (e.g. "3 days ago")
+pub fn relative_time(at: &Zoned) -> String {
+    let secs = Timestamp::now().as_second() - at.timestamp().as_second();
+
+    if secs < 60 {
+        return "just now".to_owned();
+    }
+
+    let (amount, unit) = [
+        (60 * 60 * 24 * 365, "year"),
+        (60 * 60 * 24 * 30, "month"),
+        (60 * 60 * 24 * 7, "week"),
+        (60 * 60 * 24, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ]
+    .into_iter()
+    .find_map(|(unit_secs, unit)| (secs >= unit_secs).then_some((secs / unit_secs, unit)))
+    .unwrap();
+
+    format!("{amount} {unit}{} ago", if amount > 1 { "s" } else { "" })
+}
@@ -1,26 +1,48 @@
-use std::{fmt::Display, sync::LazyLock};
+use std::{collections::HashSet, fmt::Display, sync::LazyLock};
 
 use colored::Colorize;
+use minisign_verify::PublicKey;
 use regex::Regex;
+use reqwest::Url;
 
 use crate::{
-    repos::ast::{DownloadSource, PackageManifest, Repository},
+    repos::ast::{Dependency, DownloadSource, PackageManifest, Repository},
     sources::{
-        direct::DirectSource, github::GithubSource, AssetSource, AssetType, BinaryInArchive,
+        direct::DirectSource, github::GithubSource, http_index::HttpIndexSource, AssetSource,
+        AssetType, BinaryInArchive, ExtraFileInArchive, ExtraFileKind,
     },
 };
 
 static NAME_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"^([a-zA-Z0-9\-_.]+)$"#).unwrap());
 
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^([a-z0-9\-]+)$"#).unwrap());
+
+/// Maximum number of tags a single package can have
+static MAX_TAGS: usize = 10;
+
+/// Issue found in a repository
+#[derive(Debug)]
+pub struct ValidationError {
+    /// Package the issue relates to, if any
+    pub package: Option<String>,
+    pub message: String,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
 // TODO: detect cyclic dependencies
-pub fn validate_repository(repo: &Repository) -> Result<(), Vec<String>> {
+pub fn validate_repository(repo: &Repository) -> Result<(), Vec<ValidationError>> {
     let mut errors = vec![];
 
     macro_rules! validate_name {
-        ($typ: expr, $name: expr, $colorize: ident) => {
+        ($errors: expr, $typ: expr, $name: expr, $colorize: ident) => {
             if let Err(err) = validate_name($typ, $name, Colorize::$colorize) {
-                errors.push(err);
+                $errors.push(err);
             }
         };
     }
@@ -28,18 +50,44 @@ pub fn validate_repository(repo: &Repository) -> Result<(), Vec<String>> {
     let Repository {
         name,
         description: _,
+        min_fetchy_version,
+        // Included files are merged before validation
+        includes: _,
         packages,
     } = repo;
 
-    validate_name!("Repository", name, bright_blue);
+    let mut repo_errors = vec![];
+
+    validate_name!(repo_errors, "Repository", name, bright_blue);
+
+    if packages.is_empty() {
+        repo_errors.push("Repository doesn't contain any package".to_owned());
+    }
+
+    if let Some(version) = min_fetchy_version {
+        if parse_version(version).is_none() {
+            repo_errors.push(format!(
+                "Minimum Fetchy version {} is invalid (expected e.g. '0.15.0')",
+                version.bright_cyan()
+            ));
+        }
+    }
+
+    errors.extend(repo_errors.into_iter().map(|message| ValidationError {
+        package: None,
+        message,
+    }));
 
     for (name, manifest) in packages {
         if *name != manifest.name {
-            errors.push(format!(
-                "Repository contains package {} under name {}",
-                name.bright_yellow(),
-                manifest.name.bright_yellow()
-            ));
+            errors.push(ValidationError {
+                package: Some(name.clone()),
+                message: format!(
+                    "Repository contains package {} under name {}",
+                    name.bright_yellow(),
+                    manifest.name.bright_yellow()
+                ),
+            });
         }
     }
 
@@ -53,13 +101,37 @@ pub fn validate_repository(repo: &Repository) -> Result<(), Vec<String>> {
             name,
             source,
             depends_on,
+            // Optional dependencies are allowed to be missing
+            optional_deps: _,
+            provides,
+            verify,
+            description,
+            tags,
+            deprecated,
+            no_exe_suffix: _,
         } = manifest;
 
-        validate_name!("Package", name, bright_yellow);
+        let mut pkg_errors = vec![];
+
+        validate_name!(pkg_errors, "Package", name, bright_yellow);
+
+        for provided in provides {
+            validate_name!(pkg_errors, "Virtual package", provided, bright_yellow);
+        }
 
-        for depend_on in depends_on {
-            if !repo.packages.contains_key(depend_on) {
-                errors.push(format!(
+        for Dependency {
+            name: depend_on,
+            constraint: _,
+            system: _,
+        } in depends_on
+        {
+            if !repo.packages.contains_key(depend_on)
+                && !repo
+                    .packages
+                    .values()
+                    .any(|manifest| manifest.provides.contains(depend_on))
+            {
+                pkg_errors.push(format!(
                     "Package {} depends on package {} which was not found in the repository",
                     name.bright_yellow(),
                     depend_on.bright_yellow()
@@ -67,16 +139,83 @@ pub fn validate_repository(repo: &Repository) -> Result<(), Vec<String>> {
             }
         }
 
+        if verify
+            .as_ref()
+            .is_some_and(|verify| verify.trim().is_empty())
+        {
+            pkg_errors.push(format!(
+                "Package {} has an empty verification command",
+                name.bright_yellow()
+            ));
+        } else if let Some(Err(err)) = verify.as_deref().map(shell_words::split) {
+            pkg_errors.push(format!(
+                "Package {} has an invalid verification command: {err}",
+                name.bright_yellow()
+            ));
+        }
+
+        if description
+            .as_ref()
+            .is_some_and(|description| description.trim().is_empty())
+        {
+            pkg_errors.push(format!(
+                "Package {} has an empty description",
+                name.bright_yellow()
+            ));
+        }
+
+        if deprecated
+            .as_ref()
+            .is_some_and(|deprecated| deprecated.trim().is_empty())
+        {
+            pkg_errors.push(format!(
+                "Package {} has an empty deprecation message",
+                name.bright_yellow()
+            ));
+        }
+
+        if tags.len() > MAX_TAGS {
+            pkg_errors.push(format!(
+                "Package {} has {} tags (maximum is {MAX_TAGS})",
+                name.bright_yellow(),
+                tags.len()
+            ));
+        }
+
+        for (i, tag) in tags.iter().enumerate() {
+            if !TAG_REGEX.is_match(tag) {
+                pkg_errors.push(format!(
+                    "Tag {} of package {} is invalid (tags should only contain lowercase letters, digits and dashes)",
+                    tag.bright_cyan(),
+                    name.bright_yellow()
+                ));
+            }
+
+            if tags[..i].contains(tag) {
+                pkg_errors.push(format!(
+                    "Tag {} is specified multiple times in package {}",
+                    tag.bright_cyan(),
+                    name.bright_yellow()
+                ));
+            }
+        }
+
         let param_errors = match source {
             DownloadSource::Direct(params) => DirectSource::validate(params),
             DownloadSource::GitHub(params) => GithubSource::validate(params),
+            DownloadSource::HttpIndex(params) => HttpIndexSource::validate(params),
         };
 
-        errors.extend(
+        pkg_errors.extend(
             param_errors
                 .iter()
                 .map(|err| format!("In package {}: {err}", name.bright_yellow())),
         );
+
+        errors.extend(pkg_errors.into_iter().map(|message| ValidationError {
+            package: Some(name.clone()),
+            message,
+        }));
     }
 
     if errors.is_empty() {
@@ -89,30 +228,137 @@ pub fn validate_repository(repo: &Repository) -> Result<(), Vec<String>> {
 pub fn validate_asset_type(typ: &AssetType, errors: &mut Vec<String>) {
     match typ {
         AssetType::Binary { copy_as } => {
-            if let Err(err) = validate_binary_name(copy_as) {
-                errors.push(err);
+            if copy_as.is_empty() {
+                errors.push("Binary assets must have at least one name".to_owned());
             }
+
+            for copy_as in copy_as {
+                if let Err(err) = validate_binary_name(copy_as) {
+                    errors.push(err);
+                }
+            }
+
+            validate_unique_bin_names(copy_as.iter(), errors);
         }
 
-        AssetType::Archive { format: _, files } => {
+        AssetType::Archive {
+            format: _,
+            files,
+            extra_files,
+            strip_components: _,
+        } => {
+            if files.is_empty() {
+                errors.push("Archives must contain at least one binary".to_owned());
+            }
+
             for file in files {
                 let BinaryInArchive {
                     path_matcher: _,
                     copy_as,
                 } = file;
 
-                if let Err(err) = validate_binary_name(copy_as) {
-                    errors.push(err);
+                if let Some(copy_as) = copy_as {
+                    if let Err(err) = validate_binary_name(copy_as) {
+                        errors.push(err);
+                    }
+                }
+            }
+
+            // Names of binaries without an explicit one are only known when extracting the archive
+            validate_unique_bin_names(
+                files.iter().filter_map(|file| file.copy_as.as_ref()),
+                errors,
+            );
+
+            for file in extra_files {
+                let ExtraFileInArchive {
+                    path_matcher: _,
+                    kind,
+                } = file;
+
+                match kind {
+                    ExtraFileKind::Completion { shell } => {
+                        if let Err(err) = validate_name("Shell", shell, Colorize::bright_cyan) {
+                            errors.push(err);
+                        }
+                    }
+
+                    ExtraFileKind::Man => {}
                 }
             }
         }
     }
 }
 
+/// Ensure binaries of a single asset don't overwrite each other
+fn validate_unique_bin_names<'a>(
+    names: impl Iterator<Item = &'a String>,
+    errors: &mut Vec<String>,
+) {
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+
+    for name in names {
+        if !seen.insert(name) && reported.insert(name) {
+            errors.push(format!(
+                "Multiple binaries are named {}, they would overwrite each other",
+                name.bright_green()
+            ));
+        }
+    }
+}
+
+pub fn validate_minisign_key(key: &str, errors: &mut Vec<String>) {
+    if let Err(err) = PublicKey::from_base64(key) {
+        errors.push(format!(
+            "Invalid minisign public key {}: {err}",
+            format!("{key:?}").bright_magenta()
+        ));
+    }
+}
+
+/// Ensure an URL can be downloaded from (`what` describes the URL, e.g. "asset")
+pub fn validate_download_url(what: &str, url: &str, errors: &mut Vec<String>) {
+    let display = || format!("{url:?}").bright_magenta();
+
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            errors.push(format!("Invalid {what} URL {}: {err}", display()));
+            return;
+        }
+    };
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        errors.push(format!(
+            "Unsupported scheme {} in {what} URL {} (only {} and {} are supported)",
+            parsed.scheme().bright_cyan(),
+            display(),
+            "http".bright_cyan(),
+            "https".bright_cyan()
+        ));
+    } else if parsed.host_str().is_none_or(str::is_empty)
+        // Extra slashes are skipped when parsing (e.g. 'https:///file' is read as 'https://file/')
+        || url
+            .split_once("://")
+            .is_some_and(|(_, rest)| rest.starts_with('/'))
+    {
+        errors.push(format!("Missing host in {what} URL {}", display()));
+    }
+}
+
 pub fn validate_binary_name(bin_name: &str) -> Result<(), String> {
     validate_name("Binary", bin_name, Colorize::bright_green)
 }
 
+/// Parse a version number made of dot-separated integers (e.g. `0.15.0`)
+pub fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect()
+}
+
 fn validate_name<'a, T: Display>(
     typ: &str,
     name: &'a str,
@@ -129,3 +375,43 @@ fn validate_name<'a, T: Display>(
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::validate_download_url;
+
+    fn url_errors(url: &str) -> Vec<String> {
+        let mut errors = vec![];
+        validate_download_url("asset", url, &mut errors);
+        errors
+    }
+
+    #[test]
+    fn http_urls_are_accepted() {
+        assert!(url_errors("https://example.com/tool.tar.gz").is_empty());
+        assert!(url_errors("http://127.0.0.1:8080/tool").is_empty());
+    }
+
+    #[test]
+    fn ftp_urls_are_rejected() {
+        let errors = url_errors("ftp://example.com/tool.tar.gz");
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Unsupported scheme"));
+    }
+
+    #[test]
+    fn urls_without_scheme_are_rejected() {
+        let errors = url_errors("example.com/tool.tar.gz");
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Invalid asset URL"));
+    }
+
+    #[test]
+    fn urls_with_empty_host_are_rejected() {
+        for url in ["https:///tool.tar.gz", "http://", "https://:8080/tool"] {
+            assert_eq!(url_errors(url).len(), 1, "URL {url:?} should be rejected");
+        }
+    }
+}